@@ -0,0 +1,44 @@
+//! Times building the configuration space obstruction of the kinematic chain
+//! on a single thread and on the default rayon thread pool.
+
+use nalgebra as na;
+use phyesthon::{
+    numerics::{kinematics::flat_chain, Rect},
+    simulators::kinematic_chain::ConfigObstuction,
+};
+use std::time::{Duration, Instant};
+
+const REPETITIONS: u32 = 10;
+
+fn obstruction_time() -> Duration {
+    let origin = na::point![400.0, 400.0];
+    let start = Instant::now();
+
+    for _ in 0..REPETITIONS {
+        let mut obstruction = ConfigObstuction::new(flat_chain::System::new(150.0, 100.0), origin);
+        for offset in [-200.0, 0.0, 150.0] {
+            obstruction.add_rect(&Rect {
+                p_1: origin + na::vector![offset, 120.0],
+                p_2: origin + na::vector![offset + 60.0, 200.0],
+            });
+        }
+    }
+
+    start.elapsed() / REPETITIONS
+}
+
+fn main() {
+    let single_thread = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap()
+        .install(obstruction_time);
+    let parallel = obstruction_time();
+
+    println!("Single thread: {single_thread:?}");
+    println!("{} threads: {parallel:?}", rayon::current_num_threads());
+    println!(
+        "Speedup: {:.2}",
+        single_thread.as_secs_f64() / parallel.as_secs_f64()
+    );
+}
@@ -0,0 +1,50 @@
+//! Smoke test of the render pipeline, renders a cube offscreen and checks that it shows up.
+//! Has to be run from the crate root so that the shaders are found.
+
+use egui_winit::winit::{dpi::PhysicalSize, event_loop::EventLoop};
+use image::GenericImageView;
+use nalgebra as na;
+use phyesthon::{
+    render::{gl_drawable::GlDrawable, gl_mesh::GlTriangleMesh, gl_program::GlProgram, models},
+    window::Headless,
+};
+
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(64, 64);
+
+fn main() {
+    let event_loop = EventLoop::new();
+    let headless = unsafe { Headless::new(&event_loop, SIZE) };
+
+    let program = GlProgram::vertex_fragment(headless.clone_gl(), "perspective_vert", "color_frag");
+    let cube = GlTriangleMesh::new(headless.clone_gl(), &models::cube());
+
+    let view = na::Matrix4::new_translation(&na::vector![0.0, 0.0, -6.0])
+        * na::Matrix4::from_euler_angles(0.5, 0.5, 0.0);
+    let projection = na::Perspective3::new(1.0, std::f32::consts::FRAC_PI_4, 0.1, 100.0);
+
+    headless.draw_with(|| {
+        program.enable();
+        program.uniform_matrix_4_f32_slice("model_transform", na::Matrix4::identity().as_slice());
+        program.uniform_matrix_4_f32_slice("view_transform", view.as_slice());
+        program.uniform_matrix_4_f32_slice(
+            "projection_transform",
+            projection.to_homogeneous().as_slice(),
+        );
+        program.uniform_4_f32_slice("color", &[1.0, 0.0, 0.0, 1.0]);
+        cube.draw();
+    });
+
+    let capture = headless.capture().expect("failed to read the drawbuffer");
+    let background = capture.image.get_pixel(0, 0);
+    let covered = capture
+        .image
+        .pixels()
+        .filter(|(_, _, pixel)| *pixel != background)
+        .count();
+
+    assert!(covered > 0, "the cube was not rendered");
+    println!(
+        "The cube covers {covered} of {} pixels",
+        SIZE.width * SIZE.height
+    );
+}
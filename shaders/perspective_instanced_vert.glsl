@@ -0,0 +1,22 @@
+#version 430
+
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 normal;
+layout (location = 2) in mat4 instance_transform;
+
+out PointData {
+    vec3 normal;
+    vec3 position;
+} point;
+
+uniform mat4 model_transform;
+uniform mat4 view_transform;
+uniform mat4 projection_transform;
+
+void main() {
+    mat4 transform = instance_transform * model_transform;
+    vec4 world = transform * vec4(position, 1.0f);
+    gl_Position = projection_transform * view_transform * world;
+    point.position = world.xyz;
+    point.normal = normalize((transform * vec4(normal, 0.0)).xyz);
+}
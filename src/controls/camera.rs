@@ -1,32 +1,159 @@
-use super::mouse::MouseState;
-use egui_winit::winit::dpi::{PhysicalPosition, PhysicalSize};
+use super::{keyboard::KeyboardState, mouse::MouseState};
+use crate::json;
+use egui_winit::winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::VirtualKeyCode,
+};
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Rotates around `center` at `linear_distance()`
+    Orbit,
+    /// Eye is placed at `center`, `linear_distance()` only scales the movement speed
+    Fly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraPreset {
+    pub azimuth: f32,
+    pub altitude: f32,
+    pub log_distance: f32,
+    /// Orbit center, independent of the camera mode
+    pub center: na::Point3<f32>,
+}
+
+impl CameraPreset {
+    pub fn top() -> Self {
+        Self {
+            azimuth: 0.0,
+            altitude: std::f32::consts::FRAC_PI_2,
+            log_distance: 2.0,
+            center: na::Point3::origin(),
+        }
+    }
+
+    pub fn side() -> Self {
+        Self {
+            azimuth: 0.0,
+            altitude: 0.0,
+            log_distance: 2.0,
+            center: na::Point3::origin(),
+        }
+    }
+
+    pub fn iso() -> Self {
+        Self {
+            azimuth: -std::f32::consts::FRAC_PI_4,
+            altitude: std::f32::consts::FRAC_PI_4,
+            log_distance: 2.0,
+            center: na::Point3::origin(),
+        }
+    }
+}
+
+/// Mouse preferences, which can be saved as the defaults of every camera
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraSettings {
+    /// Radians per pixel of mouse movement
+    pub rotation_speed: f32,
+    /// Distance per pixel of mouse movement, relative to the distance from the center
+    pub movement_speed: f32,
+    /// Change of the logarithmic distance per scroll line
+    pub scroll_speed: f32,
+    pub invert_scroll: bool,
+    /// Moving the mouse up looks down
+    pub invert_look: bool,
+}
+
+impl CameraSettings {
+    pub const PATH: &'static str = "camera_settings.json";
+
+    /// Saved settings or the defaults if none were saved
+    pub fn load() -> Result<Self, json::Error> {
+        Self::load_from(Path::new(Self::PATH))
+    }
+
+    fn load_from(path: &Path) -> Result<Self, json::Error> {
+        match json::load(path) {
+            Err(json::Error::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Self::default())
+            }
+            result => result,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), json::Error> {
+        json::save(Path::new(Self::PATH), self)
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            rotation_speed: 0.05,
+            movement_speed: 0.01,
+            scroll_speed: 0.2,
+            invert_scroll: false,
+            invert_look: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OrbitTarget {
+    azimuth: f32,
+    altitude: f32,
+    log_distance: f32,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     pub azimuth: f32,
     pub altitude: f32,
     pub log_distance: f32,
+    pub roll: f32,
     pub center: na::Point3<f32>,
     pub resolution: PhysicalSize<u32>,
     pub near_plane: f32,
     pub far_plane: f32,
+    pub presets: [Option<CameraPreset>; Camera::PRESET_SLOTS],
+    /// Time constant in seconds of easing toward the mouse-controlled angles, 0 disables it
+    pub smoothing: f32,
+    pub settings: CameraSettings,
+    mode: CameraMode,
+    target: Option<OrbitTarget>,
+    last_update: Option<Instant>,
 }
 
 impl Camera {
-    const ROTATION_SPEED: f32 = 0.05;
-    const MOVEMENT_SPEED: f32 = 0.01;
-    const SCROLL_SPEED: f32 = 0.2;
+    pub const PRESET_SLOTS: usize = 4;
+    /// Fly mode movement per second, relative to the linear distance
+    const FLY_SPEED: f32 = 1.2;
+    const FIELD_OF_VIEW: f32 = std::f32::consts::FRAC_PI_2;
+    const SMOOTHING_EPSILON: f32 = 1e-4;
 
-    pub fn new() -> Camera {
+    pub fn new(settings: CameraSettings) -> Camera {
         Camera {
             azimuth: -std::f32::consts::FRAC_PI_4,
             altitude: std::f32::consts::FRAC_PI_4,
             log_distance: 2.0,
+            roll: 0.0,
             center: na::Point3::new(0.0, 0.0, 0.0),
             resolution: PhysicalSize::new(0, 0),
             near_plane: 0.1,
             far_plane: 10000.0,
+            presets: [None; Self::PRESET_SLOTS],
+            smoothing: 0.0,
+            settings,
+            mode: CameraMode::Orbit,
+            target: None,
+            last_update: None,
         }
     }
 
@@ -38,48 +165,217 @@ impl Camera {
         self.log_distance = linear_distance.ln();
     }
 
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switches the mode while keeping the eye position and view direction
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if self.mode == mode {
+            return;
+        }
+
+        self.target = None;
+
+        self.center = match mode {
+            CameraMode::Orbit => self.center + self.forward() * self.linear_distance(),
+            CameraMode::Fly => self.position(),
+        };
+
+        self.mode = mode;
+    }
+
+    pub fn to_preset(&self) -> CameraPreset {
+        CameraPreset {
+            azimuth: self.azimuth,
+            altitude: self.altitude,
+            log_distance: self.log_distance,
+            center: match self.mode {
+                CameraMode::Orbit => self.center,
+                CameraMode::Fly => self.center + self.forward() * self.linear_distance(),
+            },
+        }
+    }
+
+    pub fn apply_preset(&mut self, preset: &CameraPreset) {
+        self.target = None;
+        self.azimuth = preset.azimuth;
+        self.altitude = preset.altitude;
+        self.log_distance = preset.log_distance;
+        self.center = match self.mode {
+            CameraMode::Orbit => preset.center,
+            CameraMode::Fly => preset.center - self.forward() * self.linear_distance(),
+        };
+    }
+
+    /// Moves the camera so that the whole sphere is visible in a viewport of the given
+    /// `aspect` ratio, keeping the view direction.
+    /// Spheres deeper than the clipping range are centered in it.
+    pub fn frame_bounding_sphere(&mut self, center: na::Point3<f32>, radius: f32, aspect: f32) {
+        // Degenerate viewports, e.g. of minimized windows, are treated as square
+        let aspect = if aspect > 0.0 { aspect } else { 1.0 };
+        let vertical = (0.5 * Self::FIELD_OF_VIEW).tan();
+        let half_fov = vertical.min(vertical * aspect).atan();
+
+        let middle = 0.5 * (self.near_plane + self.far_plane);
+        let distance = (radius / half_fov.sin()).clamp(
+            (self.near_plane + radius).min(middle),
+            (self.far_plane - radius).max(middle),
+        );
+
+        self.target = None;
+        self.set_linear_distance(distance);
+        self.center = match self.mode {
+            CameraMode::Orbit => center,
+            CameraMode::Fly => center - self.forward() * distance,
+        };
+    }
+
     pub fn update_from_mouse(&mut self, mut mouse: MouseState) -> bool {
+        let now = Instant::now();
+        let delta = self
+            .last_update
+            .map_or(0.0, |last| (now - last).as_secs_f32());
+        self.last_update = Some(now);
+
         let mouse_delta = mouse.position_delta();
         let scroll_delta = mouse.scroll_delta();
+        let pan_delta = mouse.pan_delta();
+        let magnify_delta = mouse.magnify_delta();
 
-        if mouse_delta.x != 0.0 || mouse_delta.y != 0.0 || scroll_delta != 0.0 {
+        let changed = if mouse_delta.x != 0.0
+            || mouse_delta.y != 0.0
+            || scroll_delta != 0.0
+            || pan_delta.x != 0.0
+            || pan_delta.y != 0.0
+            || magnify_delta != 0.0
+        {
             self.update_angles(&mouse, &mouse_delta);
             self.update_center(&mouse, &mouse_delta);
+            self.pan(&pan_delta);
 
-            self.log_distance -= Self::SCROLL_SPEED * scroll_delta;
-            self.log_distance = self
-                .log_distance
-                .clamp(self.near_plane.ln(), self.far_plane.ln());
+            let (near_plane, far_plane) = (self.near_plane, self.far_plane);
+            let scroll_speed = if self.settings.invert_scroll {
+                -self.settings.scroll_speed
+            } else {
+                self.settings.scroll_speed
+            };
+            let target = self.target_mut();
+            // Magnification is relative, which is additive for the logarithm of the distance
+            target.log_distance -= scroll_speed * scroll_delta + magnify_delta;
+            target.log_distance = target.log_distance.clamp(near_plane.ln(), far_plane.ln());
 
             true
         } else {
             false
+        };
+
+        self.ease_to_target(delta);
+
+        changed
+    }
+
+    fn target_mut(&mut self) -> &mut OrbitTarget {
+        self.target.get_or_insert(OrbitTarget {
+            azimuth: self.azimuth,
+            altitude: self.altitude,
+            log_distance: self.log_distance,
+        })
+    }
+
+    fn ease_to_target(&mut self, delta: f32) {
+        let Some(target) = self.target else { return };
+
+        let factor = if self.smoothing > 0.0 {
+            1.0 - (-delta / self.smoothing).exp()
+        } else {
+            1.0
+        };
+
+        self.azimuth += (target.azimuth - self.azimuth) * factor;
+        self.altitude += (target.altitude - self.altitude) * factor;
+        self.log_distance += (target.log_distance - self.log_distance) * factor;
+
+        if (target.azimuth - self.azimuth).abs() < Self::SMOOTHING_EPSILON
+            && (target.altitude - self.altitude).abs() < Self::SMOOTHING_EPSILON
+            && (target.log_distance - self.log_distance).abs() < Self::SMOOTHING_EPSILON
+        {
+            self.azimuth = target.azimuth;
+            self.altitude = target.altitude;
+            self.log_distance = target.log_distance;
+            self.target = None;
         }
     }
 
     fn update_angles(&mut self, mouse: &MouseState, mouse_delta: &PhysicalPosition<f64>) {
         if mouse.is_middle_button_down() {
-            self.azimuth += mouse_delta.x as f32 * Self::ROTATION_SPEED;
-            self.altitude += mouse_delta.y as f32 * Self::ROTATION_SPEED;
+            let rotation_speed = self.settings.rotation_speed;
+            let look_sign = if self.settings.invert_look { -1.0 } else { 1.0 };
+            let target = self.target_mut();
+            target.azimuth += mouse_delta.x as f32 * rotation_speed;
+            target.altitude += look_sign * mouse_delta.y as f32 * rotation_speed;
         }
     }
 
     fn update_center(&mut self, mouse: &MouseState, mouse_delta: &PhysicalPosition<f64>) {
         if mouse.is_right_button_down() {
-            self.center += (na::geometry::Rotation3::from_axis_angle(
-                &na::Unit::new_normalize(na::vector![0.0, 1.0, 0.0]),
-                -self.azimuth,
-            )
-            .to_homogeneous()
-                * na::geometry::Rotation3::from_axis_angle(
-                    &na::Unit::new_normalize(na::vector![1.0, 0.0, 0.0]),
-                    -self.altitude,
-                )
-                .to_homogeneous()
-                * na::Vector4::new(-mouse_delta.x as f32, mouse_delta.y as f32, 0.0, 0.0))
-            .xyz()
-                * self.linear_distance()
-                * Self::MOVEMENT_SPEED;
+            self.pan(mouse_delta);
+        }
+    }
+
+    /// Moves the center in the view plane, `delta` is in pixels
+    fn pan(&mut self, delta: &PhysicalPosition<f64>) {
+        self.center += self.to_world_direction(na::vector![-delta.x as f32, delta.y as f32, 0.0])
+            * self.linear_distance()
+            * self.settings.movement_speed;
+    }
+
+    /// Moves the camera in fly mode, `delta` is the time since the previous update
+    pub fn update_from_keyboard(&mut self, keyboard: &KeyboardState, delta: Duration) -> bool {
+        if self.mode != CameraMode::Fly {
+            return false;
+        }
+
+        let axis = |positive, negative| {
+            keyboard.is_key_down(positive) as i32 as f32
+                - keyboard.is_key_down(negative) as i32 as f32
+        };
+
+        let direction = na::vector![
+            axis(VirtualKeyCode::D, VirtualKeyCode::A),
+            axis(VirtualKeyCode::E, VirtualKeyCode::Q),
+            axis(VirtualKeyCode::S, VirtualKeyCode::W)
+        ];
+
+        if direction == na::Vector3::zeros() {
+            return false;
+        }
+
+        self.fly(&direction, delta);
+
+        true
+    }
+
+    /// Moves along the nonzero view space `direction` for `delta`
+    fn fly(&mut self, direction: &na::Vector3<f32>, delta: Duration) {
+        self.center += self.to_world_direction(direction.normalize())
+            * self.linear_distance()
+            * Self::FLY_SPEED
+            * delta.as_secs_f32();
+    }
+
+    fn to_world_direction(&self, direction: na::Vector3<f32>) -> na::Vector3<f32> {
+        (self.inverse_rotation() * direction.to_homogeneous()).xyz()
+    }
+
+    fn forward(&self) -> na::Vector3<f32> {
+        self.to_world_direction(na::vector![0.0, 0.0, -1.0])
+    }
+
+    fn eye_distance(&self) -> f32 {
+        match self.mode {
+            CameraMode::Orbit => self.linear_distance(),
+            CameraMode::Fly => 0.0,
         }
     }
 
@@ -90,17 +386,22 @@ impl Camera {
     }
 
     pub fn view_transform(&self) -> na::Matrix4<f32> {
-        na::Translation3::new(0.0, 0.0, -self.linear_distance()).to_homogeneous()
+        na::Translation3::new(0.0, 0.0, -self.eye_distance()).to_homogeneous()
             * self.view_transform_no_translation()
             * na::Translation3::from(-self.center.coords).to_homogeneous()
     }
 
     pub fn view_transform_no_translation(&self) -> na::Matrix4<f32> {
         na::Rotation3::from_axis_angle(
-            &na::Unit::new_normalize(na::vector![1.0, 0.0, 0.0]),
-            self.altitude,
+            &na::Unit::new_normalize(na::vector![0.0, 0.0, 1.0]),
+            self.roll,
         )
         .to_homogeneous()
+            * na::Rotation3::from_axis_angle(
+                &na::Unit::new_normalize(na::vector![1.0, 0.0, 0.0]),
+                self.altitude,
+            )
+            .to_homogeneous()
             * na::Rotation3::from_axis_angle(
                 &na::Unit::new_normalize(na::vector![0.0, 1.0, 0.0]),
                 self.azimuth,
@@ -110,27 +411,56 @@ impl Camera {
 
     pub fn inverse_view_transform(&self) -> na::Matrix4<f32> {
         na::Translation3::from(self.center.coords).to_homogeneous()
+            * self.inverse_rotation()
+            * na::Translation3::new(0.0, 0.0, self.eye_distance()).to_homogeneous()
+    }
+
+    fn inverse_rotation(&self) -> na::Matrix4<f32> {
+        na::Rotation3::from_axis_angle(
+            &na::Unit::new_normalize(na::vector![0.0, 1.0, 0.0]),
+            -self.azimuth,
+        )
+        .to_homogeneous()
             * na::Rotation3::from_axis_angle(
-                &na::Unit::new_normalize(na::vector![0.0, 1.0, 0.0]),
-                -self.azimuth,
+                &na::Unit::new_normalize(na::vector![1.0, 0.0, 0.0]),
+                -self.altitude,
             )
             .to_homogeneous()
             * na::Rotation3::from_axis_angle(
-                &na::Unit::new_normalize(na::vector![1.0, 0.0, 0.0]),
-                -self.altitude,
+                &na::Unit::new_normalize(na::vector![0.0, 0.0, 1.0]),
+                -self.roll,
             )
             .to_homogeneous()
-            * na::Translation3::new(0.0, 0.0, self.linear_distance()).to_homogeneous()
     }
 
     pub fn projection_transform(&self, aspect: f32) -> na::Matrix4<f32> {
-        na::Perspective3::new(
-            aspect,
-            std::f32::consts::FRAC_PI_2,
-            self.near_plane,
-            self.far_plane,
-        )
-        .to_homogeneous()
+        na::Perspective3::new(aspect, Self::FIELD_OF_VIEW, self.near_plane, self.far_plane)
+            .to_homogeneous()
+    }
+
+    /// World space ray through the pixel at `position` of a window of `size`,
+    /// returns the origin on the near plane and a unit direction
+    pub fn screen_ray(
+        &self,
+        position: PhysicalPosition<f64>,
+        size: PhysicalSize<u32>,
+    ) -> (na::Point3<f32>, na::Vector3<f32>) {
+        let aspect = size.width as f32 / size.height as f32;
+        let x = 2.0 * position.x as f32 / size.width as f32 - 1.0;
+        let y = 1.0 - 2.0 * position.y as f32 / size.height as f32;
+
+        let inverse = (self.projection_transform(aspect) * self.view_transform())
+            .try_inverse()
+            .unwrap_or_else(na::Matrix4::identity);
+        let unproject = |z: f32| {
+            na::Point3::from_homogeneous(inverse * na::vector![x, y, z, 1.0])
+                .unwrap_or_else(na::Point3::origin)
+        };
+
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+
+        (near, (far - near).normalize())
     }
 
     pub fn aspect_ratio(&self) -> f32 {
@@ -140,6 +470,147 @@ impl Camera {
 
 impl Default for Camera {
     fn default() -> Self {
-        Self::new()
+        Self::new(CameraSettings::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        json::assert_round_trip(&CameraSettings {
+            rotation_speed: 0.013,
+            movement_speed: 0.7,
+            scroll_speed: 0.25,
+            invert_scroll: true,
+            invert_look: true,
+        });
+    }
+
+    /// Sphere points nearest, farthest and furthest to the sides in view space,
+    /// in normalized device coordinates
+    fn projected_extremes(
+        camera: &Camera,
+        center: na::Point3<f32>,
+        radius: f32,
+        aspect: f32,
+    ) -> Vec<na::Point3<f32>> {
+        let view_center = camera.view_transform().transform_point(&center);
+        let projection = camera.projection_transform(aspect);
+
+        [
+            na::vector![radius, 0.0, 0.0],
+            na::vector![0.0, radius, 0.0],
+            na::vector![0.0, 0.0, radius],
+        ]
+        .into_iter()
+        .flat_map(|offset| [view_center + offset, view_center - offset])
+        .map(|point| projection.transform_point(&point))
+        .collect()
+    }
+
+    #[test]
+    fn view_transform_no_translation_is_the_rotation() {
+        let camera = Camera {
+            center: na::point![3.0, -1.0, 2.0],
+            azimuth: 0.7,
+            altitude: -0.4,
+            roll: 0.2,
+            log_distance: 5.0f32.ln(),
+            ..Camera::default()
+        };
+
+        let rotation = camera.view_transform_no_translation();
+        let view = camera.view_transform();
+
+        assert_eq!(rotation.fixed_view::<3, 1>(0, 3), na::Vector3::zeros());
+        assert_eq!(
+            rotation.fixed_view::<1, 4>(3, 0),
+            na::RowVector4::new(0.0, 0.0, 0.0, 1.0)
+        );
+        assert!((rotation.fixed_view::<3, 3>(0, 0) - view.fixed_view::<3, 3>(0, 0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn missing_settings_load_as_defaults() {
+        let path = std::env::temp_dir().join("phyesthon_missing_camera_settings.json");
+        assert_eq!(
+            CameraSettings::load_from(&path).unwrap(),
+            CameraSettings::default()
+        );
+    }
+
+    #[test]
+    fn malformed_settings_are_reported() {
+        let path = std::env::temp_dir().join("phyesthon_malformed_camera_settings.json");
+        std::fs::write(&path, "{\"rotation_speed\": 0.1,").unwrap();
+        let result = CameraSettings::load_from(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(json::Error::Syntax(_))), "{result:?}");
+    }
+
+    #[test]
+    fn framed_sphere_is_visible() {
+        for aspect in [1.0, 0.4, 2.5] {
+            let mut camera = Camera::default();
+            let center = na::point![1.0, -2.0, 3.0];
+            camera.frame_bounding_sphere(center, 1.0, aspect);
+
+            for point in projected_extremes(&camera, center, 1.0, aspect) {
+                assert!(
+                    point.iter().all(|c| (-1.0..=1.0).contains(c)),
+                    "{point} for aspect {aspect}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_viewports_frame_from_further_away() {
+        let distance = |aspect| {
+            let mut camera = Camera::default();
+            camera.frame_bounding_sphere(na::Point3::origin(), 1.0, aspect);
+            camera.linear_distance()
+        };
+
+        assert_eq!(distance(2.0), distance(1.0));
+        assert!(distance(0.5) > distance(1.0));
+        assert_eq!(distance(0.0), distance(1.0));
+    }
+
+    #[test]
+    fn fly_distance_does_not_depend_on_frame_rate() {
+        let mut slow = Camera::default();
+        slow.set_mode(CameraMode::Fly);
+        let mut fast = Camera::default();
+        fast.set_mode(CameraMode::Fly);
+        let direction = na::vector![1.0, 0.0, -1.0];
+
+        slow.fly(&direction, Duration::from_millis(100));
+        for _ in 0..10 {
+            fast.fly(&direction, Duration::from_millis(10));
+        }
+
+        assert!(na::distance(&slow.position(), &fast.position()) < 1e-4);
+
+        let moved = na::distance(&slow.position(), &Camera::default().position());
+        let expected = 0.1 * Camera::FLY_SPEED * slow.linear_distance();
+        assert!((moved - expected).abs() < 1e-4, "{moved} != {expected}");
+    }
+
+    #[test]
+    fn sphere_deeper_than_clipping_range_is_centered() {
+        let mut camera = Camera::default();
+        let radius = camera.far_plane;
+        camera.frame_bounding_sphere(na::Point3::origin(), radius, 1.0);
+
+        let depth = -camera
+            .view_transform()
+            .transform_point(&na::Point3::origin())
+            .z;
+        assert!((depth - 0.5 * (camera.near_plane + camera.far_plane)).abs() < 1.0);
     }
 }
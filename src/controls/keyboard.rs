@@ -0,0 +1,83 @@
+use egui_winit::winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+const KEY_SET_WORDS: usize = 4;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+struct KeySet([u64; KEY_SET_WORDS]);
+
+impl KeySet {
+    fn location(key: VirtualKeyCode) -> (usize, u64) {
+        let index = key as usize;
+        (
+            index / u64::BITS as usize,
+            1 << (index % u64::BITS as usize),
+        )
+    }
+
+    fn contains(&self, key: VirtualKeyCode) -> bool {
+        let (word, bit) = Self::location(key);
+        self.0[word] & bit != 0
+    }
+
+    fn insert(&mut self, key: VirtualKeyCode) {
+        let (word, bit) = Self::location(key);
+        self.0[word] |= bit;
+    }
+
+    fn remove(&mut self, key: VirtualKeyCode) {
+        let (word, bit) = Self::location(key);
+        self.0[word] &= !bit;
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct KeyboardState {
+    keys_down: KeySet,
+    keys_pressed: KeySet,
+}
+
+impl KeyboardState {
+    pub fn new() -> KeyboardState {
+        KeyboardState::default()
+    }
+
+    pub fn is_key_down(&self, key: VirtualKeyCode) -> bool {
+        self.keys_down.contains(key)
+    }
+
+    pub fn has_key_been_pressed(&mut self, key: VirtualKeyCode) -> bool {
+        let before = self.keys_pressed.contains(key);
+        self.keys_pressed.remove(key);
+        before
+    }
+
+    pub fn update(&mut self) {
+        self.keys_pressed = KeySet::default();
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    if !self.keys_down.contains(*key) {
+                        self.keys_pressed.insert(*key);
+                    }
+                    self.keys_down.insert(*key);
+                }
+                ElementState::Released => self.keys_down.remove(*key),
+            },
+            WindowEvent::Focused(false) => {
+                self.keys_down = KeySet::default();
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod camera;
+pub mod keyboard;
+pub mod modifiers;
 pub mod mouse;
@@ -0,0 +1,33 @@
+use egui_winit::winit::event::{ModifiersState, WindowEvent};
+
+/// Shift, Ctrl and Alt held while using the mouse or the keyboard
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    state: ModifiersState,
+}
+
+impl Modifiers {
+    pub fn new() -> Modifiers {
+        Modifiers::default()
+    }
+
+    pub fn shift(&self) -> bool {
+        self.state.shift()
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.state.ctrl()
+    }
+
+    pub fn alt(&self) -> bool {
+        self.state.alt()
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::ModifiersChanged(state) => self.state = *state,
+            WindowEvent::Focused(false) => self.state = ModifiersState::empty(),
+            _ => {}
+        }
+    }
+}
@@ -2,6 +2,8 @@ use egui_winit::winit::{
     dpi::PhysicalPosition,
     event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
 };
+use super::modifiers::Modifiers;
+use std::time::{Duration, Instant};
 
 type MousePosition = PhysicalPosition<f64>;
 
@@ -13,9 +15,18 @@ pub struct MouseState {
     current_position: Option<MousePosition>,
     previous_position: Option<MousePosition>,
     scroll_delta: f32,
+    /// Two-finger trackpad scroll
+    pan_delta: MousePosition,
+    /// Trackpad pinch, positive when zooming in
+    magnify_delta: f32,
     left_button_pressed: bool,
     middle_button_pressed: bool,
     right_button_pressed: bool,
+    /// Left, middle and right
+    last_presses: [Option<Instant>; 3],
+    double_clicks: [bool; 3],
+    double_click_interval: Duration,
+    modifiers: Modifiers,
 }
 
 impl Default for MouseState {
@@ -25,6 +36,8 @@ impl Default for MouseState {
 }
 
 impl MouseState {
+    pub const DEFAULT_DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
     pub fn new() -> MouseState {
         MouseState {
             left_button_down: false,
@@ -33,12 +46,59 @@ impl MouseState {
             current_position: None,
             previous_position: None,
             scroll_delta: 0.0,
+            pan_delta: MousePosition::new(0.0, 0.0),
+            magnify_delta: 0.0,
             left_button_pressed: false,
             middle_button_pressed: false,
             right_button_pressed: false,
+            last_presses: [None; 3],
+            double_clicks: [false; 3],
+            double_click_interval: Self::DEFAULT_DOUBLE_CLICK_INTERVAL,
+            modifiers: Modifiers::new(),
         }
     }
 
+    pub fn double_click_interval(&self) -> Duration {
+        self.double_click_interval
+    }
+
+    /// Longest time between presses of a double-click
+    pub fn set_double_click_interval(&mut self, interval: Duration) {
+        self.double_click_interval = interval;
+    }
+
+    /// Whether `button` has been pressed a second time within the interval since the last update
+    pub fn double_clicked(&self, button: MouseButton) -> bool {
+        Self::button_index(button).is_some_and(|index| self.double_clicks[index])
+    }
+
+    fn button_index(button: MouseButton) -> Option<usize> {
+        match button {
+            MouseButton::Left => Some(0),
+            MouseButton::Middle => Some(1),
+            MouseButton::Right => Some(2),
+            MouseButton::Other(_) => None,
+        }
+    }
+
+    fn register_press(&mut self, button: MouseButton) {
+        let Some(index) = Self::button_index(button) else {
+            return;
+        };
+
+        let now = Instant::now();
+        let is_double =
+            self.last_presses[index].is_some_and(|last| now - last <= self.double_click_interval);
+
+        self.double_clicks[index] |= is_double;
+        // A third press starts a new double-click
+        self.last_presses[index] = (!is_double).then_some(now);
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
     pub fn is_left_button_down(&self) -> bool {
         self.left_button_down
     }
@@ -93,34 +153,55 @@ impl MouseState {
         last_value
     }
 
+    pub fn pan_delta(&mut self) -> MousePosition {
+        std::mem::replace(&mut self.pan_delta, MousePosition::new(0.0, 0.0))
+    }
+
+    pub fn magnify_delta(&mut self) -> f32 {
+        std::mem::take(&mut self.magnify_delta)
+    }
+
     pub fn update(&mut self) {
         self.scroll_delta = 0.0;
+        self.pan_delta = MousePosition::new(0.0, 0.0);
+        self.magnify_delta = 0.0;
         self.previous_position = None;
         self.left_button_pressed = false;
         self.middle_button_pressed = false;
         self.right_button_pressed = false;
+        self.double_clicks = [false; 3];
     }
 
     pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        self.modifiers.handle_window_event(event);
+
         match event {
-            WindowEvent::MouseInput { state, button, .. } => match (state, button) {
-                (ElementState::Pressed, MouseButton::Left) => {
-                    self.left_button_down = true;
-                    self.left_button_pressed = true
-                }
-                (ElementState::Released, MouseButton::Left) => self.left_button_down = false,
-                (ElementState::Pressed, MouseButton::Right) => {
-                    self.right_button_down = true;
-                    self.right_button_pressed = true
+            WindowEvent::MouseInput { state, button, .. } => {
+                if *state == ElementState::Pressed {
+                    self.register_press(*button);
                 }
-                (ElementState::Released, MouseButton::Right) => self.right_button_down = false,
-                (ElementState::Pressed, MouseButton::Middle) => {
-                    self.middle_button_down = true;
-                    self.middle_button_pressed = true
+
+                match (state, button) {
+                    (ElementState::Pressed, MouseButton::Left) => {
+                        self.left_button_down = true;
+                        self.left_button_pressed = true
+                    }
+                    (ElementState::Released, MouseButton::Left) => self.left_button_down = false,
+                    (ElementState::Pressed, MouseButton::Right) => {
+                        self.right_button_down = true;
+                        self.right_button_pressed = true
+                    }
+                    (ElementState::Released, MouseButton::Right) => self.right_button_down = false,
+                    (ElementState::Pressed, MouseButton::Middle) => {
+                        self.middle_button_down = true;
+                        self.middle_button_pressed = true
+                    }
+                    (ElementState::Released, MouseButton::Middle) => {
+                        self.middle_button_down = false
+                    }
+                    _ => {}
                 }
-                (ElementState::Released, MouseButton::Middle) => self.middle_button_down = false,
-                _ => {}
-            },
+            }
             WindowEvent::CursorLeft { .. } => {
                 self.left_button_down = false;
                 self.right_button_down = false;
@@ -135,6 +216,16 @@ impl MouseState {
             } => {
                 self.scroll_delta = *delta;
             }
+            WindowEvent::MouseWheel {
+                delta: MouseScrollDelta::PixelDelta(delta),
+                ..
+            } => {
+                self.pan_delta.x += delta.x;
+                self.pan_delta.y += delta.y;
+            }
+            WindowEvent::TouchpadMagnify { delta, .. } => {
+                self.magnify_delta += *delta as f32;
+            }
             _ => {}
         }
     }
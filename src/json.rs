@@ -0,0 +1,872 @@
+//! Minimal JSON support for serde, used for saving and loading presenter state
+
+use serde::{
+    de::{
+        self,
+        value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer,
+    },
+    ser, Serialize,
+};
+use std::fmt::{self, Display, Write};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    /// Byte offset of the unexpected input
+    Syntax(usize),
+    Message(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Syntax(position) => write!(f, "invalid JSON at byte {}", position),
+            Self::Message(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Self::Message(message.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(message: T) -> Self {
+        Self::Message(message.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Integers are kept apart from floats so that they are stored losslessly
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Keeps the order of the entries
+    Object(Vec<(String, Value)>),
+}
+
+pub fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
+    Ok(value.serialize(ValueSerializer)?.to_string())
+}
+
+pub fn from_str<T: DeserializeOwned>(string: &str) -> Result<T, Error> {
+    T::deserialize(Value::parse(string)?)
+}
+
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    Ok(std::fs::write(path, to_string(value)?)?)
+}
+
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, Error> {
+    from_str(&std::fs::read_to_string(path)?)
+}
+
+impl Value {
+    pub fn parse(string: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            bytes: string.as_bytes(),
+            position: 0,
+        };
+
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        if parser.position == parser.bytes.len() {
+            Ok(value)
+        } else {
+            Err(Error::Syntax(parser.position))
+        }
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        const INDENT: &str = "  ";
+
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Unsigned(value) => write!(f, "{}", value),
+            Value::Signed(value) => write!(f, "{}", value),
+            // Debug formatting keeps the fraction of whole floats and is exact
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::String(value) => write_string(f, value),
+            // Vectors and points are kept in a single line
+            Value::Array(values)
+                if values
+                    .iter()
+                    .all(|value| !matches!(value, Value::Array(_) | Value::Object(_))) =>
+            {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    value.write_indented(f, indent)?;
+                }
+                write!(f, "]")
+            }
+            Value::Array(values) => {
+                writeln!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    write!(f, "{}", INDENT.repeat(indent + 1))?;
+                    value.write_indented(f, indent + 1)?;
+                    writeln!(f, "{}", if i + 1 < values.len() { "," } else { "" })?;
+                }
+                write!(f, "{}]", INDENT.repeat(indent))
+            }
+            Value::Object(entries) if entries.is_empty() => write!(f, "{{}}"),
+            Value::Object(entries) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write!(f, "{}", INDENT.repeat(indent + 1))?;
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write_indented(f, indent + 1)?;
+                    writeln!(f, "{}", if i + 1 < entries.len() { "," } else { "" })?;
+                }
+                write!(f, "{}}}", INDENT.repeat(indent))
+            }
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in string.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.position).copied()
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), Error> {
+        if self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(())
+        } else {
+            Err(Error::Syntax(self.position))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        match self.peek() {
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(_) => self.number(),
+            None => Err(Error::Syntax(self.position)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.position;
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.eE".contains(b))
+        {
+            self.position += 1;
+        }
+
+        let number = std::str::from_utf8(&self.bytes[start..self.position])
+            .map_err(|_| Error::Syntax(start))?;
+
+        // Integers too large for 64 bits are read as floats
+        number
+            .parse()
+            .map(Value::Unsigned)
+            .or_else(|_| number.parse().map(Value::Signed))
+            .or_else(|_| number.parse().map(Value::Float))
+            .ok()
+            .filter(|value| !matches!(value, Value::Float(value) if !value.is_finite()))
+            .ok_or(Error::Syntax(start))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect("\"")?;
+        let mut string = Vec::new();
+
+        loop {
+            let Some(&byte) = self.bytes.get(self.position) else {
+                return Err(Error::Syntax(self.position));
+            };
+            self.position += 1;
+
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = match self.bytes.get(self.position) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            let code = self
+                                .bytes
+                                .get(self.position + 1..self.position + 5)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or(Error::Syntax(self.position))?;
+                            self.position += 4;
+                            code
+                        }
+                        _ => return Err(Error::Syntax(self.position)),
+                    };
+
+                    self.position += 1;
+                    string.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                byte => string.push(byte),
+            }
+        }
+
+        String::from_utf8(string).map_err(|_| Error::Syntax(self.position))
+    }
+
+    /// Parses comma-separated `item`s until `close`
+    fn list<T>(
+        &mut self,
+        close: u8,
+        mut item: impl FnMut(&mut Self) -> Result<T, Error>,
+    ) -> Result<Vec<T>, Error> {
+        self.position += 1;
+        let mut items = Vec::new();
+
+        if self.peek() == Some(close) {
+            self.position += 1;
+            return Ok(items);
+        }
+
+        loop {
+            items.push(item(self)?);
+
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b) if b == close => {
+                    self.position += 1;
+                    return Ok(items);
+                }
+                _ => return Err(Error::Syntax(self.position)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        self.list(b']', Self::value).map(Value::Array)
+    }
+
+    fn object(&mut self) -> Result<Value, Error> {
+        self.list(b'}', |parser| {
+            parser.skip_whitespace();
+            let key = parser.string()?;
+
+            if parser.peek() != Some(b':') {
+                return Err(Error::Syntax(parser.position));
+            }
+            parser.position += 1;
+
+            Ok((key, parser.value()?))
+        })
+        .map(Value::Object)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(value) => visitor.visit_bool(value),
+            Value::Unsigned(value) => visitor.visit_u64(value),
+            Value::Signed(value) => visitor.visit_i64(value),
+            Value::Float(value) => visitor.visit_f64(value),
+            Value::String(value) => visitor.visit_string(value),
+            Value::Array(values) => visitor.visit_seq(SeqDeserializer::new(values.into_iter())),
+            Value::Object(entries) => visitor.visit_map(MapDeserializer::new(entries.into_iter())),
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        // Newtypes are serialized transparently
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Object(entries) if entries.len() == 1 => visitor.visit_enum(
+                MapAccessDeserializer::new(MapDeserializer::new(entries.into_iter())),
+            ),
+            _ => Err(de::Error::custom("expected an enum variant")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct ValueSerializer;
+
+/// Collects sequences, tuples and their variants
+struct SeqSerializer {
+    variant: Option<&'static str>,
+    values: Vec<Value>,
+}
+
+/// Collects maps, structs and their variants
+struct MapSerializer {
+    variant: Option<&'static str>,
+    entries: Vec<(String, Value)>,
+    key: Option<String>,
+}
+
+fn variant_value(variant: Option<&'static str>, value: Value) -> Value {
+    match variant {
+        Some(variant) => Value::Object(vec![(variant.to_owned(), value)]),
+        None => value,
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(match u64::try_from(v) {
+            Ok(v) => Value::Unsigned(v),
+            Err(_) => Value::Signed(v),
+        })
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        Ok(Value::Unsigned(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        // Goes through the shortest decimal of the `f32` so that `0.1f32` is not stored
+        // as `0.10000000149011612`, it is still read back exactly
+        match v.to_string().parse::<f64>() {
+            Ok(v) if v.is_finite() => self.serialize_f64(v),
+            _ => self.serialize_f64(v as f64),
+        }
+    }
+
+    /// JSON has no representation of infinities and NaNs, so they are rejected
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        if v.is_finite() {
+            Ok(Value::Float(v))
+        } else {
+            Err(Error::Message(format!("{} cannot be stored in JSON", v)))
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::Array(
+            v.iter().map(|&b| Value::Unsigned(b as u64)).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        Ok(Value::String(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        Ok(variant_value(Some(variant), value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            variant: None,
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            variant: None,
+            entries: Vec::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            variant: Some(variant),
+            entries: Vec::new(),
+            key: None,
+        })
+    }
+}
+
+impl SeqSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, Error> {
+        Ok(variant_value(self.variant, Value::Array(self.values)))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl MapSerializer {
+    fn finish(self) -> Result<Value, Error> {
+        Ok(variant_value(self.variant, Value::Object(self.entries)))
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(match key.serialize(ValueSerializer)? {
+            Value::String(key) => key,
+            Value::Unsigned(key) => key.to_string(),
+            Value::Signed(key) => key.to_string(),
+            _ => return Err(ser::Error::custom("map keys have to be strings or numbers")),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Message("map value without a key".to_owned()))?;
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.entries
+            .push((key.to_owned(), value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        self.finish()
+    }
+}
+
+/// Saves `value`, loads it back and checks that saving it again gives the same text,
+/// numbers are stored exactly so this compares every field
+#[cfg(test)]
+pub(crate) fn assert_round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+    let string = to_string(value).unwrap();
+    let loaded: T = from_str(&string).unwrap();
+    assert_eq!(to_string(&loaded).unwrap(), string);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    fn assert_loads_back<T>(value: &T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        let string = to_string(value).unwrap();
+        assert_eq!(&from_str::<T>(&string).unwrap(), value, "{}", string);
+        assert_round_trip(value);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Segment(f64, f64),
+        Rect { width: f64, height: f64 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Everything {
+        seed: u64,
+        offset: i64,
+        count: usize,
+        small: f32,
+        large: f64,
+        tiny: f64,
+        whole: f64,
+        flag: bool,
+        name: String,
+        missing: Option<f64>,
+        present: Option<u8>,
+        shapes: Vec<Shape>,
+        point: (f64, f64, f64),
+    }
+
+    #[test]
+    fn integers_are_lossless() {
+        for seed in [0, 1, (1 << 53) + 1, u64::MAX - 1, u64::MAX] {
+            assert_loads_back(&seed);
+        }
+
+        for offset in [i64::MIN, i64::MIN + 1, -((1 << 53) + 1), -1] {
+            assert_loads_back(&offset);
+        }
+
+        assert_eq!(to_string(&u64::MAX).unwrap(), "18446744073709551615");
+    }
+
+    #[test]
+    fn floats_are_exact() {
+        for value in [
+            0.1,
+            -0.0,
+            1.0,
+            1e-300,
+            5e-324,
+            1.7976931348623157e308,
+            -2.5e17,
+        ] {
+            assert_loads_back(&value);
+        }
+
+        for value in [0.1f32, 1.0 / 3.0, f32::MAX, f32::MIN_POSITIVE] {
+            assert_loads_back(&value);
+        }
+
+        assert_eq!(to_string(&0.1f32).unwrap(), "0.1");
+        assert_eq!(to_string(&1.0).unwrap(), "1.0");
+    }
+
+    #[test]
+    fn non_finite_floats_are_rejected() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(to_string(&value), Err(Error::Message(_))));
+            assert!(to_string(&vec![1.0, value]).is_err());
+        }
+
+        assert!(to_string(&f32::NAN).is_err());
+        assert!(from_str::<f64>("1e400").is_err());
+    }
+
+    #[test]
+    fn whole_numbers_load_into_floats() {
+        assert_eq!(from_str::<f64>("3").unwrap(), 3.0);
+        assert_eq!(from_str::<f64>("-3").unwrap(), -3.0);
+        assert!(from_str::<u32>("3.5").is_err());
+    }
+
+    #[test]
+    fn nested_values_round_trip() {
+        assert_loads_back(&Everything {
+            seed: u64::MAX,
+            offset: -7,
+            count: 42,
+            small: 0.3,
+            large: 6.02214076e23,
+            tiny: 1.602176634e-19,
+            whole: 2.0,
+            flag: true,
+            name: "quote \" backslash \\ newline \n tab \t unicode ż \u{1}".to_owned(),
+            missing: None,
+            present: Some(255),
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Segment(-1.0, 1.0),
+                Shape::Rect {
+                    width: 3.0,
+                    height: 4.0,
+                },
+            ],
+            point: (1.0, -2.0, 0.5),
+        });
+    }
+
+    #[test]
+    fn syntax_errors_are_reported() {
+        assert!(matches!(from_str::<f64>("[1.0"), Err(Error::Syntax(_))));
+        assert!(matches!(
+            from_str::<Vec<f64>>("[1.0,]"),
+            Err(Error::Syntax(_))
+        ));
+        assert!(matches!(from_str::<String>("\"abc"), Err(Error::Syntax(_))));
+        assert!(matches!(from_str::<f64>("1.0 2.0"), Err(Error::Syntax(4))));
+    }
+}
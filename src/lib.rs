@@ -1,7 +1,9 @@
 pub mod controls;
+pub mod json;
 pub mod numerics;
 pub mod physics;
 pub mod presenters;
+pub mod recording;
 pub mod render;
 pub mod simulators;
 pub mod ui;
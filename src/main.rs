@@ -1,18 +1,217 @@
 use egui::containers::ComboBox;
 use egui_winit::winit::{self, platform::run_return::EventLoopExtRunReturn};
 use phyesthon::{
-    controls::mouse::MouseState,
+    controls::{camera::CameraSettings, keyboard::KeyboardState, mouse::MouseState},
     presenters::{
         black_hole::BlackHoleBuilder, hodograph::HodographBuilder, jelly::JellyBuilder,
-        kinematic_chain::KinematicChainBuilder, puma::PumaBuilder, quaternions::QuaternionsBuilder,
-        spinning_top::SpinningTopBuilder, spring::SpringBuilder, Presenter, PresenterBuilder,
+        kinematic_chain::KinematicChainBuilder, multi_link_chain::MultiLinkChainBuilder,
+        puma::PumaBuilder, quaternions::QuaternionsBuilder, spinning_top::SpinningTopBuilder,
+        spring::SpringBuilder, spring_chain::SpringChainBuilder, Presenter, PresenterBuilder,
     },
+    ui::widgets::PathAction,
     window::Window,
 };
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+struct Controls {
+    paused: bool,
+    /// Simulation time advanced by a single step while paused
+    step_delta: Duration,
+    vsync: bool,
+    vsync_error: Option<String>,
+    multisampling: bool,
+    auto_reset: bool,
+    capture: Capture,
+    parameters_save: PathAction,
+    parameters_load: PathAction,
+    stats: FrameStats,
+    /// Saved camera defaults, loaded once at startup and given to the built presenters
+    camera_settings: CameraSettings,
+    camera_settings_error: Option<String>,
+}
+
+#[derive(Default)]
+struct FrameStats {
+    frames_per_second: Option<f64>,
+    steps_per_second: Option<f64>,
+    /// Presenter index and its step count during the previous frame
+    last_steps: Option<(usize, usize)>,
+}
+
+impl FrameStats {
+    /// Weight of the previous value in the exponential moving average
+    const SMOOTHING: f64 = 0.95;
+
+    fn smooth(average: Option<f64>, sample: f64) -> f64 {
+        average.map_or(sample, |average| {
+            Self::SMOOTHING * average + (1.0 - Self::SMOOTHING) * sample
+        })
+    }
+
+    fn update(&mut self, delta: Duration, presenter: usize, steps: Option<usize>) {
+        let seconds = delta.as_secs_f64();
+        if seconds <= 0.0 {
+            return;
+        }
+
+        self.frames_per_second = Some(Self::smooth(self.frames_per_second, 1.0 / seconds));
+
+        let last_steps = self.last_steps.filter(|&(last, _)| last == presenter);
+        self.steps_per_second = match (steps, last_steps) {
+            (Some(steps), Some((_, last))) => Some(Self::smooth(
+                self.steps_per_second,
+                steps.saturating_sub(last) as f64 / seconds,
+            )),
+            _ => None,
+        };
+
+        self.last_steps = steps.map(|steps| (presenter, steps));
+    }
+
+    fn ui(&self, ui: &mut egui::Ui) {
+        let Some(frames_per_second) = self.frames_per_second else {
+            return;
+        };
+
+        let mut text = format!("{frames_per_second:.0} FPS");
+        if let Some(steps_per_second) = self.steps_per_second {
+            text += &format!(", {steps_per_second:.0} steps/s");
+        }
+
+        ui.label(text);
+    }
+}
+
+struct Capture {
+    screenshot_requested: bool,
+    status: Option<String>,
+
+    recording: bool,
+    recorded_frames: usize,
+    max_frames: usize,
+    frame_rate: f64,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self {
+            screenshot_requested: false,
+            status: None,
+
+            recording: false,
+            recorded_frames: 0,
+            max_frames: 1000,
+            frame_rate: 30.0,
+        }
+    }
+}
+
+impl Capture {
+    const RECORDINGS_DIRECTORY: &'static str = "recordings";
+
+    /// Simulation step of every recorded frame, independent of the rendering speed
+    fn frame_delta(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.frame_rate)
+    }
+
+    fn start_recording(&mut self) {
+        match std::fs::create_dir_all(Self::RECORDINGS_DIRECTORY) {
+            Ok(()) => {
+                self.recording = true;
+                self.recorded_frames = 0;
+                self.status = Some(String::from("Recording"));
+            }
+            Err(err) => self.status = Some(format!("Error: {err}")),
+        }
+    }
+
+    fn save_frame(&mut self, window: &Window) {
+        let path = Path::new(Self::RECORDINGS_DIRECTORY)
+            .join(format!("frame_{:05}.png", self.recorded_frames));
+
+        let result = match window.capture_framebuffer() {
+            Some(texture) => texture.save(&path).map_err(|err| err.to_string()),
+            None => Err(String::from("window has no size")),
+        };
+
+        match result {
+            Ok(()) => {
+                self.recorded_frames += 1;
+                if self.recorded_frames >= self.max_frames {
+                    self.recording = false;
+                    self.status = Some(format!("Recorded {} frames", self.recorded_frames));
+                }
+            }
+            Err(err) => {
+                self.recording = false;
+                self.status = Some(format!("Error: {err}"));
+            }
+        }
+    }
+
+    fn screenshot_path() -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        PathBuf::from(format!("screenshot_{timestamp}.png"))
+    }
+
+    fn save_screenshot(&mut self, window: &Window) {
+        let path = Self::screenshot_path();
+        self.status = Some(match window.capture_framebuffer() {
+            Some(texture) => match texture.save(&path) {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(err) => format!("Error: {err}"),
+            },
+            None => String::from("Error: window has no size"),
+        });
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Screenshot").clicked() {
+            self.screenshot_requested = true;
+        }
+
+        let mut recording = self.recording;
+        if ui.checkbox(&mut recording, "Record").changed() {
+            if recording {
+                self.start_recording();
+            } else {
+                self.recording = false;
+                self.status = Some(format!("Recorded {} frames", self.recorded_frames));
+            }
+        }
+
+        ui.add_enabled_ui(!self.recording, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Maximum frames");
+                ui.add(egui::DragValue::new(&mut self.max_frames).clamp_range(1..=100000));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Frame rate");
+                ui.add(egui::DragValue::new(&mut self.frame_rate).clamp_range(1.0..=240.0));
+            });
+        });
+
+        if self.recording {
+            ui.label(format!(
+                "Frame {}/{}",
+                self.recorded_frames, self.max_frames
+            ));
+        }
+
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+    }
+}
 
 fn main() {
     let mut mouse = MouseState::new();
+    let mut keyboard = KeyboardState::new();
     let mut event_loop = winit::event_loop::EventLoopBuilder::with_user_event().build();
     let window = unsafe { Window::new(&event_loop) };
 
@@ -25,20 +224,46 @@ fn main() {
         Box::new(PumaBuilder::new()),
         Box::new(JellyBuilder::new()),
         Box::new(KinematicChainBuilder::new()),
+        Box::new(MultiLinkChainBuilder::new()),
         Box::new(QuaternionsBuilder::new()),
         Box::new(SpinningTopBuilder::new()),
         Box::new(SpringBuilder::new()),
+        Box::new(SpringChainBuilder::new()),
     ];
 
+    let (camera_settings, camera_settings_error) = match CameraSettings::load() {
+        Ok(settings) => (settings, None),
+        Err(error) => (
+            CameraSettings::default(),
+            Some(format!(
+                "Failed to load {}, using the defaults: {error}",
+                CameraSettings::PATH
+            )),
+        ),
+    };
+
     let mut presenters: Vec<Box<dyn Presenter>> = builders
         .iter()
-        .map(|builder| builder.build(window.clone_gl()))
+        .map(|builder| builder.build(window.clone_gl(), camera_settings))
         .collect();
 
     let mut current_presenter = 0;
-    let mut auto_reset = true;
+    window.set_clear_color(presenters[current_presenter].clear_color());
+    let mut controls = Controls {
+        paused: true,
+        step_delta: Duration::from_millis(10),
+        vsync: true,
+        vsync_error: None,
+        multisampling: true,
+        auto_reset: true,
+        capture: Capture::default(),
+        parameters_save: PathAction::new("parameters.json"),
+        parameters_load: PathAction::new("parameters.json"),
+        stats: FrameStats::default(),
+        camera_settings,
+        camera_settings_error,
+    };
 
-    let mut pause = true;
     let mut last_draw = None;
 
     event_loop.run_return(move |event, _, control_flow| match event {
@@ -49,10 +274,10 @@ fn main() {
                 &mut presenters,
                 &mut builders,
                 &window,
-                &mut pause,
                 &mut mouse,
+                &mut keyboard,
                 &mut last_draw,
-                &mut auto_reset,
+                &mut controls,
             );
         }
         winit::event::Event::WindowEvent { event, .. } => {
@@ -77,6 +302,7 @@ fn main() {
 
             if !event_response.consumed {
                 mouse.handle_window_event(&event);
+                keyboard.handle_window_event(&event);
             }
         }
         winit::event::Event::LoopDestroyed => {
@@ -95,24 +321,44 @@ fn render(
     presenters: &mut [Box<dyn Presenter>],
     builders: &mut [Box<dyn PresenterBuilder>],
     window: &Window,
-    paused: &mut bool,
     mouse: &mut MouseState,
+    keyboard: &mut KeyboardState,
     last_draw: &mut Option<Instant>,
-    auto_reset: &mut bool,
+    controls: &mut Controls,
 ) {
     let now = Instant::now();
     let delta = last_draw.map(|last| now - last);
 
-    if !*paused {
-        if let Some(delta) = delta {
+    handle_shortcuts(
+        keyboard,
+        &mut presenters[*current_presenter],
+        builders[*current_presenter].as_ref(),
+        window,
+        controls,
+    );
+
+    if !controls.paused {
+        if controls.capture.recording {
+            presenters[*current_presenter].update(controls.capture.frame_delta());
+        } else if let Some(delta) = delta {
             presenters[*current_presenter].update(delta);
         }
     }
 
     *last_draw = Some(now);
 
+    if let Some(delta) = delta {
+        controls.stats.update(
+            delta,
+            *current_presenter,
+            presenters[*current_presenter].step_count(),
+        );
+    }
+
     presenters[*current_presenter].update_mouse(*mouse);
     mouse.update();
+    presenters[*current_presenter].update_keyboard(*keyboard, delta.unwrap_or_default());
+    keyboard.update();
 
     let repaint_after = egui_glow.run(window.window(), |egui_ctx| {
         draw_ui(
@@ -120,9 +366,8 @@ fn render(
             presenters,
             builders,
             window,
-            paused,
             egui_ctx,
-            auto_reset,
+            controls,
         );
     });
 
@@ -140,6 +385,15 @@ fn render(
 
     presenters[*current_presenter].draw(window.size());
 
+    if controls.capture.screenshot_requested {
+        controls.capture.screenshot_requested = false;
+        controls.capture.save_screenshot(window);
+    }
+
+    if controls.capture.recording {
+        controls.capture.save_frame(window);
+    }
+
     egui_glow.paint(window.window());
 
     // draw things on top of egui here
@@ -148,14 +402,41 @@ fn render(
     window.window().set_visible(true);
 }
 
+/// Space toggles the pause, R resets the presenter, period steps the paused simulation
+/// and F11 toggles fullscreen
+fn handle_shortcuts(
+    keyboard: &mut KeyboardState,
+    presenter: &mut Box<dyn Presenter>,
+    builder: &dyn PresenterBuilder,
+    window: &Window,
+    controls: &mut Controls,
+) {
+    use winit::event::VirtualKeyCode;
+
+    if keyboard.has_key_been_pressed(VirtualKeyCode::Space) {
+        controls.paused = !controls.paused;
+    }
+
+    if keyboard.has_key_been_pressed(VirtualKeyCode::R) {
+        builder.rebuild(presenter, window.clone_gl(), controls.camera_settings);
+    }
+
+    if keyboard.has_key_been_pressed(VirtualKeyCode::Period) && controls.paused {
+        presenter.update(controls.step_delta);
+    }
+
+    if keyboard.has_key_been_pressed(VirtualKeyCode::F11) {
+        window.set_fullscreen(!window.is_fullscreen());
+    }
+}
+
 fn draw_ui(
     current_presenter: &mut usize,
     presenters: &mut [Box<dyn Presenter>],
     builders: &mut [Box<dyn PresenterBuilder>],
     window: &Window,
-    paused: &mut bool,
     egui_ctx: &egui::Context,
-    auto_reset: &mut bool,
+    controls: &mut Controls,
 ) {
     egui::SidePanel::left("Side panel")
         .min_width(100.0)
@@ -171,24 +452,91 @@ fn draw_ui(
                                 .selectable_value(current_presenter, i, f.name())
                                 .clicked()
                             {
-                                *paused = true;
+                                controls.paused = true;
+                                window.set_clear_color(f.clear_color());
                             }
                         }
                     });
 
                 ui.heading(presenters[*current_presenter].name());
-                let text = if *paused { "Play" } else { "Pause" };
-                if ui.button(text).clicked() {
-                    *paused = !*paused;
+                controls.stats.ui(ui);
+                if ui.checkbox(&mut controls.vsync, "VSync").changed() {
+                    controls.vsync_error = window.set_vsync(controls.vsync).err().map(|error| {
+                        controls.vsync = !controls.vsync;
+                        format!("Failed to change the swap interval: {error}")
+                    });
+                }
+
+                if let Some(error) = &controls.vsync_error {
+                    ui.label(error);
+                }
+
+                if let Some(error) = &controls.camera_settings_error {
+                    ui.label(error);
                 }
 
+                if ui
+                    .checkbox(&mut controls.multisampling, "Anti-aliasing")
+                    .changed()
+                {
+                    window.set_multisampling(controls.multisampling);
+                }
+                ui.horizontal(|ui| {
+                    let text = if controls.paused { "Play" } else { "Pause" };
+                    if ui.button(text).clicked() {
+                        controls.paused = !controls.paused;
+                    }
+
+                    if ui
+                        .add_enabled(controls.paused, egui::Button::new("Step"))
+                        .clicked()
+                    {
+                        presenters[*current_presenter].update(controls.step_delta);
+                    }
+
+                    let mut step_ms = controls.step_delta.as_secs_f64() * 1000.0;
+                    if ui
+                        .add(
+                            egui::DragValue::new(&mut step_ms)
+                                .clamp_range(0.1..=1000.0)
+                                .suffix(" ms"),
+                        )
+                        .changed()
+                    {
+                        controls.step_delta = Duration::from_secs_f64(step_ms / 1000.0);
+                    }
+                });
+                ui.label("Space: play/pause, R: reset, period: step, F11: fullscreen");
+
+                controls.capture.ui(ui);
+
                 ui.separator();
 
                 let changed = builders[*current_presenter].build_ui(ui).changed();
-                ui.checkbox(auto_reset, "Autoreset");
-                if ui.button("Reset").clicked() || changed && *auto_reset {
-                    presenters[*current_presenter] =
-                        builders[*current_presenter].build(window.clone_gl());
+                ui.checkbox(&mut controls.auto_reset, "Autoreset");
+                if ui.button("Reset").clicked() || changed && controls.auto_reset {
+                    builders[*current_presenter].rebuild(
+                        &mut presenters[*current_presenter],
+                        window.clone_gl(),
+                        controls.camera_settings,
+                    );
+                }
+
+                if let Some(path) = controls.parameters_save.ui(ui, "Save parameters") {
+                    let result = builders[*current_presenter].save(&path);
+                    controls.parameters_save.report(result);
+                }
+
+                if let Some(path) = controls.parameters_load.ui(ui, "Load parameters") {
+                    let result = builders[*current_presenter].load(&path);
+                    if result.is_ok() {
+                        builders[*current_presenter].rebuild(
+                            &mut presenters[*current_presenter],
+                            window.clone_gl(),
+                            controls.camera_settings,
+                        );
+                    }
+                    controls.parameters_load.report(result);
                 }
 
                 ui.separator();
@@ -2,23 +2,129 @@ use super::Float;
 use nalgebra as na;
 use std::array;
 
-pub struct Cube<F: Float>(pub [[[na::Point3<F>; 4]; 4]; 4]);
+pub type Patch<F> = [[na::Point3<F>; 4]; 4];
 
-impl<F: Float> Cube<F> {
-    pub fn as_flat(&self) -> [F; 3 * 64] {
-        array::from_fn(|i| {
-            let u = i / 3 / 4 / 4;
-            let v = (i / 3 / 4) % 4;
-            let w = (i / 3) % 4;
-            let c = i % 3;
-            self.0[u][v][w][c]
-        })
+/// De Casteljau evaluation of a cubic Bézier curve
+pub fn cubic<F: Float>(points: &[na::Point3<F>; 4], t: F) -> na::Point3<F> {
+    let [b0, b1, b2, b3] = *points;
+
+    let b0 = b0.lerp(&b1, t);
+    let b1 = b1.lerp(&b2, t);
+    let b2 = b2.lerp(&b3, t);
+
+    let b0 = b0.lerp(&b1, t);
+    let b1 = b1.lerp(&b2, t);
+
+    b0.lerp(&b1, t)
+}
+
+/// First derivative of a cubic Bézier curve with respect to `t`
+pub fn cubic_derivative<F: Float>(points: &[na::Point3<F>; 4], t: F) -> na::Vector3<F> {
+    let three = F::from(3.0).unwrap();
+    let [d0, d1, d2] = array::from_fn(|i| points[i + 1] - points[i]);
+
+    let d0 = d0.lerp(&d1, t);
+    let d1 = d1.lerp(&d2, t);
+
+    d0.lerp(&d1, t) * three
+}
+
+/// De Casteljau evaluation of a Bézier curve of degree `N - 1`
+pub fn curve<F: Float, const N: usize>(points: &[na::Point3<F>; N], t: F) -> na::Point3<F> {
+    let mut points = *points;
+
+    for len in (1..N).rev() {
+        for i in 0..len {
+            points[i] = points[i].lerp(&points[i + 1], t);
+        }
+    }
+
+    points[0]
+}
+
+/// Control points of the cubic Bézier curve passing through `samples` at `t` equal to
+/// 0, ⅓, ⅔ and 1
+fn interpolating_cubic<F: Float>(samples: &[na::Point3<F>; 4]) -> [na::Point3<F>; 4] {
+    let [q0, q1, q2, q3] = samples.map(|q| q.coords);
+    let c = |a: f64, b: f64, c: f64, d: f64| {
+        let [a, b, c, d] = [a, b, c, d].map(|x| F::from(x / 6.0).unwrap());
+        na::Point3::from(q0 * a + q1 * b + q2 * c + q3 * d)
+    };
+
+    [
+        samples[0],
+        c(-5.0, 18.0, -9.0, 2.0),
+        c(2.0, -9.0, 18.0, -5.0),
+        samples[3],
+    ]
+}
+
+pub fn bicubic<F: Float>(patch: &Patch<F>, u: F, v: F) -> na::Point3<F> {
+    cubic(&patch.map(|row| cubic(&row, v)), u)
+}
+
+/// Splits a cubic Bézier curve at `t` into two curves covering [0, t] and [t, 1]
+pub fn split_cubic<F: Float>(
+    points: &[na::Point3<F>; 4],
+    t: F,
+) -> ([na::Point3<F>; 4], [na::Point3<F>; 4]) {
+    let [b0, b1, b2, b3] = *points;
+
+    let b01 = b0.lerp(&b1, t);
+    let b12 = b1.lerp(&b2, t);
+    let b23 = b2.lerp(&b3, t);
+
+    let b012 = b01.lerp(&b12, t);
+    let b123 = b12.lerp(&b23, t);
+
+    let b0123 = b012.lerp(&b123, t);
+
+    ([b0, b01, b012, b0123], [b0123, b123, b23, b3])
+}
+
+/// Subdivides a patch at `u = v = 0.5`, the sub-patches are ordered by `u` first, then by `v`
+pub fn subdivide_patch<F: Float>(patch: &Patch<F>) -> [Patch<F>; 4] {
+    let half = F::from(0.5).unwrap();
+    let rows = patch.map(|row| split_cubic(&row, half));
+
+    let split_columns = |rows: [[na::Point3<F>; 4]; 4]| {
+        let columns: [_; 4] = array::from_fn(|j| split_cubic(&rows.map(|row| row[j]), half));
+        (
+            array::from_fn(|i| array::from_fn(|j| columns[j].0[i])),
+            array::from_fn(|i| array::from_fn(|j| columns[j].1[i])),
+        )
+    };
+
+    let (low_low, high_low) = split_columns(rows.map(|(low, _)| low));
+    let (low_high, high_high) = split_columns(rows.map(|(_, high)| high));
+
+    [low_low, low_high, high_low, high_high]
+}
+
+/// Control lattice of `N`×`N`×`N` points of a Bézier volume of degree `N - 1`,
+/// derivatives are only available for the default 4×4×4 lattice
+pub struct Cube<F: Float, const N: usize = 4>(pub [[[na::Point3<F>; N]; N]; N]);
+
+impl<F: Float, const N: usize> Cube<F, N> {
+    pub const POINT_COUNT: usize = N * N * N;
+
+    /// Lattice spanning [-1, 1] in every coordinate
+    pub fn new() -> Self {
+        let step = F::from(2.0 / (N - 1) as f64).unwrap();
+
+        Self(array::from_fn(|u| {
+            array::from_fn(|v| {
+                array::from_fn(|w| {
+                    na::point![u, v, w].map(|i| F::from(i as f64).unwrap() * step - F::one())
+                })
+            })
+        }))
     }
 
     fn flat_idx(i: usize) -> (usize, usize, usize) {
-        let u = (i / 4) / 4;
-        let v = (i / 4) % 4;
-        let w = i % 4;
+        let u = (i / N) / N;
+        let v = (i / N) % N;
+        let w = i % N;
         (u, v, w)
     }
 
@@ -31,25 +137,88 @@ impl<F: Float> Cube<F> {
         let indices = Self::flat_idx(i);
         &mut self.0[indices.0][indices.1][indices.2]
     }
-}
 
-impl Cube<f64> {
-    pub fn new() -> Self {
-        let array = array::from_fn(|u| {
-            array::from_fn(|v| {
-                array::from_fn(|w| {
-                    na::point![
-                        2.0 * (u as f64 / 3.0) - 1.0,
-                        2.0 * (v as f64 / 3.0) - 1.0,
-                        2.0 * (w as f64 / 3.0) - 1.0,
-                    ]
-                })
-            })
+    /// Trivariate de Casteljau evaluation, parameters are in range [0, 1]
+    pub fn evaluate(&self, u: F, v: F, w: F) -> na::Point3<F> {
+        curve(
+            &self
+                .0
+                .map(|plane| curve(&plane.map(|row| curve(&row, w)), v)),
+            u,
+        )
+    }
+
+    /// Tricubic lattice interpolating the volume at the parameters 0, ⅓, ⅔ and 1 along each
+    /// axis, it describes the same volume for `N` up to 4 and approximates finer lattices
+    pub fn to_cubic(&self) -> Cube<F> {
+        if N == 4 {
+            return Cube(array::from_fn(|u| {
+                array::from_fn(|v| array::from_fn(|w| self.0[u][v][w]))
+            }));
+        }
+
+        let params: [F; 4] = array::from_fn(|i| F::from(i as f64 / 3.0).unwrap());
+        let samples: [[[na::Point3<F>; 4]; 4]; 4] = array::from_fn(|u| {
+            array::from_fn(|v| array::from_fn(|w| self.evaluate(params[u], params[v], params[w])))
+        });
+
+        let along_w = samples.map(|plane| plane.map(|row| interpolating_cubic(&row)));
+        let along_v: [[[na::Point3<F>; 4]; 4]; 4] = along_w.map(|plane| {
+            let columns: [_; 4] = array::from_fn(|w| interpolating_cubic(&plane.map(|row| row[w])));
+            array::from_fn(|v| array::from_fn(|w| columns[w][v]))
         });
+        let along_u: [[_; 4]; 4] = array::from_fn(|v| {
+            array::from_fn(|w| interpolating_cubic(&along_v.map(|plane| plane[v][w])))
+        });
+
+        Cube(array::from_fn(|u| {
+            array::from_fn(|v| array::from_fn(|w| along_u[v][w][u]))
+        }))
+    }
+}
+
+impl<F: Float, const N: usize> Default for Cube<F, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Float> Cube<F> {
+    pub fn as_flat(&self) -> [F; 3 * 64] {
+        array::from_fn(|i| {
+            let u = i / 3 / 4 / 4;
+            let v = (i / 3 / 4) % 4;
+            let w = (i / 3) % 4;
+            let c = i % 3;
+            self.0[u][v][w][c]
+        })
+    }
+
+    pub fn derivative_u(&self, u: F, v: F, w: F) -> na::Vector3<F> {
+        cubic_derivative(
+            &self
+                .0
+                .map(|plane| cubic(&plane.map(|row| cubic(&row, w)), v)),
+            u,
+        )
+    }
 
-        Self(array)
+    pub fn derivative_v(&self, u: F, v: F, w: F) -> na::Vector3<F> {
+        cubic_derivative(
+            &array::from_fn(|j| cubic(&self.0.map(|plane| cubic(&plane[j], w)), u)),
+            v,
+        )
     }
 
+    pub fn derivative_w(&self, u: F, v: F, w: F) -> na::Vector3<F> {
+        cubic_derivative(
+            &array::from_fn(|k| cubic(&self.0.map(|plane| cubic(&plane.map(|row| row[k]), v)), u)),
+            w,
+        )
+    }
+}
+
+impl Cube<f64> {
     pub fn as_f32_array(&self) -> [na::Point3<f32>; 64] {
         array::from_fn(|i| self.flat(i).map(|c| c as f32))
     }
@@ -69,3 +238,332 @@ impl Cube<f64> {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    fn assert_near(a: &na::Point3<f64>, b: &na::Point3<f64>) {
+        assert!(na::distance(a, b) < 1e-12, "{a} != {b}");
+    }
+
+    /// Lattice with every point moved by a different offset
+    fn bent_cube<const N: usize>() -> Cube<f64, N> {
+        let mut cube = Cube::<f64, N>::new();
+        for i in 0..Cube::<f64, N>::POINT_COUNT {
+            let x = i as f64;
+            *cube.flat_mut(i) +=
+                na::vector![(x * 0.7).sin(), (x * 1.3).cos(), (x * 0.4).sin()] * 0.3;
+        }
+
+        cube
+    }
+
+    fn parameters() -> impl Iterator<Item = (f64, f64, f64)> {
+        [0.0, 0.2, 0.5, 0.9, 1.0]
+            .into_iter()
+            .flat_map(|u| [0.1, 0.6, 1.0].into_iter().map(move |v| (u, v)))
+            .flat_map(|(u, v)| [0.0, 0.35, 0.8].into_iter().map(move |w| (u, v, w)))
+    }
+
+    #[test]
+    fn curve_matches_cubic() {
+        let points = [
+            na::point![0.0, 0.0, 0.0],
+            na::point![1.0, 2.0, 0.5],
+            na::point![3.0, -1.0, 1.0],
+            na::point![4.0, 0.5, -2.0],
+        ];
+
+        for t in [0.0, 0.25, 0.5, 0.8, 1.0] {
+            assert_eq!(curve(&points, t), cubic(&points, t));
+        }
+    }
+
+    #[test]
+    fn coarse_lattices_convert_exactly() {
+        let linear = bent_cube::<2>();
+        let quadratic = bent_cube::<3>();
+        let (linear_cubic, quadratic_cubic) = (linear.to_cubic(), quadratic.to_cubic());
+
+        for (u, v, w) in parameters() {
+            assert_near(&linear_cubic.evaluate(u, v, w), &linear.evaluate(u, v, w));
+            assert_near(
+                &quadratic_cubic.evaluate(u, v, w),
+                &quadratic.evaluate(u, v, w),
+            );
+        }
+    }
+
+    #[test]
+    fn default_lattice_converts_to_itself() {
+        let cube = bent_cube::<4>();
+        assert_eq!(cube.to_cubic().0, cube.0);
+    }
+
+    #[test]
+    fn fine_lattices_keep_corners() {
+        let cube = bent_cube::<6>();
+        let cubic = cube.to_cubic();
+
+        for (u, v, w) in [(0, 0, 0), (0, 5, 0), (5, 0, 5), (5, 5, 5)] {
+            assert_near(&cubic.0[u / 5 * 3][v / 5 * 3][w / 5 * 3], &cube.0[u][v][w]);
+        }
+
+        let thirds = [0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0];
+        for ((u, v), w) in thirds
+            .iter()
+            .cartesian_product(thirds)
+            .cartesian_product(thirds)
+        {
+            assert_near(&cubic.evaluate(*u, v, w), &cube.evaluate(*u, v, w));
+        }
+    }
+
+    #[test]
+    fn corners_evaluate_to_corner_points() {
+        let cube = bent_cube::<4>();
+        for ((u, v), w) in [0, 3]
+            .into_iter()
+            .cartesian_product([0, 3])
+            .cartesian_product([0, 3])
+        {
+            let [pu, pv, pw] = [u, v, w].map(|i| i as f64 / 3.0);
+            assert_near(&cube.evaluate(pu, pv, pw), &cube.0[u][v][w]);
+        }
+    }
+
+    #[test]
+    fn undeformed_lattice_is_the_identity_volume() {
+        let cube = Cube::<f64>::new();
+        for (u, v, w) in parameters() {
+            assert_near(
+                &cube.evaluate(u, v, w),
+                &na::point![2.0 * u - 1.0, 2.0 * v - 1.0, 2.0 * w - 1.0],
+            );
+        }
+    }
+
+    #[test]
+    fn faces_evaluate_like_their_patches() {
+        let cube = bent_cube::<4>();
+        for (_, v, w) in parameters() {
+            assert_near(&cube.evaluate(0.0, v, w), &bicubic(&cube.0[0], v, w));
+            assert_near(&cube.evaluate(1.0, v, w), &bicubic(&cube.0[3], v, w));
+        }
+    }
+
+    const H: f64 = 1e-5;
+
+    fn assert_derivative(
+        analytic: na::Vector3<f64>,
+        forward: na::Point3<f64>,
+        back: na::Point3<f64>,
+    ) {
+        let numeric = (forward - back) / (2.0 * H);
+        assert!(
+            (analytic - numeric).norm() < 1e-6,
+            "{analytic} != {numeric}"
+        );
+    }
+
+    #[test]
+    fn cubic_derivative_matches_finite_differences() {
+        let points = [
+            na::point![0.0, 0.0, 0.0],
+            na::point![1.0, 2.0, 0.5],
+            na::point![3.0, -1.0, 1.0],
+            na::point![4.0, 0.5, -2.0],
+        ];
+
+        for t in [0.0, 0.3, 0.5, 0.75, 1.0] {
+            assert_derivative(
+                cubic_derivative(&points, t),
+                cubic(&points, t + H),
+                cubic(&points, t - H),
+            );
+        }
+
+        // End tangents are three times the end legs of the control polygon
+        assert_eq!(
+            cubic_derivative(&points, 0.0),
+            (points[1] - points[0]) * 3.0
+        );
+        assert_eq!(
+            cubic_derivative(&points, 1.0),
+            (points[3] - points[2]) * 3.0
+        );
+    }
+
+    #[test]
+    fn partial_derivatives_match_finite_differences() {
+        let cube = bent_cube::<4>();
+
+        for (u, v, w) in parameters() {
+            assert_derivative(
+                cube.derivative_u(u, v, w),
+                cube.evaluate(u + H, v, w),
+                cube.evaluate(u - H, v, w),
+            );
+            assert_derivative(
+                cube.derivative_v(u, v, w),
+                cube.evaluate(u, v + H, w),
+                cube.evaluate(u, v - H, w),
+            );
+            assert_derivative(
+                cube.derivative_w(u, v, w),
+                cube.evaluate(u, v, w + H),
+                cube.evaluate(u, v, w - H),
+            );
+        }
+    }
+
+    fn bent_patch() -> Patch<f64> {
+        bent_cube::<4>().0[1]
+    }
+
+    #[test]
+    fn split_halves_reproduce_the_curve() {
+        let points = bent_patch()[2];
+
+        for t in [0.5, 0.3, 0.9] {
+            let (low, high) = split_cubic(&points, t);
+            assert_near(&low[3], &cubic(&points, t));
+            assert_near(&high[0], &cubic(&points, t));
+
+            for s in [0.0, 0.1, 0.4, 0.5, 0.77, 1.0] {
+                assert_near(&cubic(&low, s), &cubic(&points, t * s));
+                assert_near(&cubic(&high, s), &cubic(&points, t + (1.0 - t) * s));
+            }
+        }
+    }
+
+    #[test]
+    fn subdivided_patches_reproduce_the_patch() {
+        let patch = bent_patch();
+        let sub_patches = subdivide_patch(&patch);
+        let samples = [0.0, 0.25, 0.5, 0.6, 1.0];
+
+        for (i, sub_patch) in sub_patches.iter().enumerate() {
+            let (u_offset, v_offset) = ((i / 2) as f64 * 0.5, (i % 2) as f64 * 0.5);
+            for (u, v) in samples.iter().cartesian_product(samples) {
+                assert_near(
+                    &bicubic(sub_patch, *u, v),
+                    &bicubic(&patch, u_offset + 0.5 * u, v_offset + 0.5 * v),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn neighbouring_sub_patches_share_edges() {
+        let [low_low, low_high, high_low, high_high] = subdivide_patch(&bent_patch());
+
+        assert_eq!(low_low[3], high_low[0]);
+        assert_eq!(low_high[3], high_high[0]);
+        assert_eq!(low_low.map(|row| row[3]), low_high.map(|row| row[0]));
+        assert_eq!(high_low.map(|row| row[3]), high_high.map(|row| row[0]));
+    }
+
+    /// Lattice indices of the control points of a patch, the bent lattice has distinct points
+    fn patch_indices(cube: &Cube<f64>, patch: &[[na::Point3<f32>; 4]; 4]) -> [[[usize; 3]; 4]; 4] {
+        let points = cube.as_f32_array();
+        patch.map(|row| {
+            row.map(|point| {
+                let (i,) = points
+                    .iter()
+                    .positions(|p| *p == point)
+                    .collect_tuple()
+                    .expect("patch point should be a unique lattice point");
+                let (u, v, w) = Cube::<f64>::flat_idx(i);
+                [u, v, w]
+            })
+        })
+    }
+
+    /// Axis and index of the lattice slice containing the whole patch
+    fn slice_of(indices: &[[[usize; 3]; 4]; 4]) -> (usize, usize) {
+        let axis = (0..3)
+            .find(|&a| {
+                indices
+                    .iter()
+                    .flatten()
+                    .all(|idx| idx[a] == indices[0][0][a])
+            })
+            .expect("patch should lie in a lattice slice");
+        (axis, indices[0][0][axis])
+    }
+
+    fn boundary_rows(indices: &[[[usize; 3]; 4]; 4]) -> [[[usize; 3]; 4]; 4] {
+        [
+            indices[0],
+            indices[3],
+            indices.map(|row| row[0]),
+            indices.map(|row| row[3]),
+        ]
+    }
+
+    #[test]
+    fn patches_are_distinct_boundary_slices() {
+        let cube = bent_cube::<4>();
+        let patches = cube.patches_f32().map(|patch| patch_indices(&cube, &patch));
+        let slices = patches.map(|indices| slice_of(&indices));
+
+        assert!(slices.iter().all(|&(_, index)| index == 0 || index == 3));
+        assert_eq!(slices.iter().unique().count(), 6);
+
+        for indices in &patches {
+            for corner in [indices[0][0], indices[0][3], indices[3][0], indices[3][3]] {
+                assert!(corner.iter().all(|&i| i == 0 || i == 3), "{corner:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn adjacent_patches_share_boundary_rows() {
+        let cube = bent_cube::<4>();
+        let patches = cube.patches_f32().map(|patch| patch_indices(&cube, &patch));
+
+        for (k, l) in (0..6).tuple_combinations() {
+            if slice_of(&patches[k]).0 == slice_of(&patches[l]).0 {
+                continue;
+            }
+
+            let shared = boundary_rows(&patches[k]).into_iter().find(|row| {
+                boundary_rows(&patches[l])
+                    .iter()
+                    .any(|other| other == row || other.iter().rev().eq(row.iter()))
+            });
+            assert!(shared.is_some(), "patches {k} and {l} share no row");
+        }
+    }
+
+    #[test]
+    fn last_patches_are_opposite_the_first() {
+        let cube = bent_cube::<4>();
+        let patches = cube.patches_f32().map(|patch| patch_indices(&cube, &patch));
+
+        for k in 0..3 {
+            let ((axis, index), (opposite_axis, opposite_index)) =
+                (slice_of(&patches[k]), slice_of(&patches[k + 3]));
+            assert_eq!(axis, opposite_axis);
+            assert_eq!(index + opposite_index, 3);
+
+            // Same parametrization, so `mesh` only flips the winding
+            for (idx, opposite) in patches[k]
+                .iter()
+                .flatten()
+                .zip(patches[k + 3].iter().flatten())
+            {
+                assert!((0..3).filter(|&a| a != axis).all(|a| idx[a] == opposite[a]));
+            }
+        }
+
+        for (k, patch) in Cube::<f64>::new().patches_f32().iter().enumerate() {
+            let normal = (patch[3][0] - patch[0][0]).cross(&(patch[0][3] - patch[0][0]));
+            let outward = normal.dot(&patch[1][1].coords) > 0.0;
+            assert_eq!(outward, k < 3, "patch {k}");
+        }
+    }
+}
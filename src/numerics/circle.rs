@@ -0,0 +1,18 @@
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Circle {
+    pub center: na::Point2<f64>,
+    pub radius: f64,
+}
+
+impl Circle {
+    pub fn new(center: na::Point2<f64>, radius: f64) -> Self {
+        Self { center, radius }
+    }
+
+    pub fn contains_point(&self, p: &na::Point2<f64>) -> bool {
+        na::distance(&self.center, p) <= self.radius
+    }
+}
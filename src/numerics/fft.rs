@@ -0,0 +1,134 @@
+use nalgebra as na;
+use std::f64::consts::TAU;
+
+/// In-place radix-2 fast Fourier transform, length of `data` has to be a power of two
+pub fn fft(data: &mut [na::Complex<f64>]) {
+    let n = data.len();
+    assert!(n.is_power_of_two(), "FFT length has to be a power of two");
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -TAU / length as f64;
+        let root = na::Complex::new(angle.cos(), angle.sin());
+
+        for chunk in data.chunks_mut(length) {
+            let (even, odd) = chunk.split_at_mut(length / 2);
+            let mut twiddle = na::Complex::new(1.0, 0.0);
+
+            for (e, o) in even.iter_mut().zip(odd.iter_mut()) {
+                let t = twiddle * *o;
+                *o = *e - t;
+                *e += t;
+                twiddle *= root;
+            }
+        }
+
+        length <<= 1;
+    }
+}
+
+/// Magnitudes of the first half of the spectrum of the real `signal` with its mean removed,
+/// normalized by the signal length
+pub fn magnitude_spectrum(signal: &[f64]) -> Vec<f64> {
+    let n = signal.len();
+    let mean = signal.iter().sum::<f64>() / n as f64;
+    let mut data: Vec<_> = signal
+        .iter()
+        .map(|&x| na::Complex::new(x - mean, 0.0))
+        .collect();
+
+    fft(&mut data);
+
+    data[..n / 2]
+        .iter()
+        .map(|c| c.re.hypot(c.im) / n as f64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f64 = 1e-12;
+
+    fn distance(a: na::Complex<f64>, b: na::Complex<f64>) -> f64 {
+        let difference = a - b;
+        difference.re.hypot(difference.im)
+    }
+
+    #[test]
+    fn sinusoid_peaks_at_its_bin() {
+        let (n, k, amplitude) = (64, 5, 3.0);
+        let signal: Vec<_> = (0..n)
+            .map(|i| amplitude * (TAU * (k * i) as f64 / n as f64 + 0.3).cos())
+            .collect();
+
+        let spectrum = magnitude_spectrum(&signal);
+        assert_eq!(spectrum.len(), n / 2);
+
+        for (bin, magnitude) in spectrum.iter().enumerate() {
+            let expected = if bin == k { amplitude / 2.0 } else { 0.0 };
+            assert!((magnitude - expected).abs() < EPS, "bin {bin}: {magnitude}");
+        }
+    }
+
+    #[test]
+    fn constant_signal_has_no_spectrum() {
+        let spectrum = magnitude_spectrum(&[2.5; 32]);
+        assert!(spectrum.iter().all(|magnitude| magnitude.abs() < EPS));
+    }
+
+    #[test]
+    fn impulse_is_flat() {
+        let mut data = vec![na::Complex::new(0.0, 0.0); 16];
+        data[0] = na::Complex::new(1.0, 0.0);
+
+        fft(&mut data);
+        assert!(data
+            .iter()
+            .all(|c| distance(*c, na::Complex::new(1.0, 0.0)) < EPS));
+    }
+
+    #[test]
+    fn matches_naive_dft() {
+        let input: Vec<_> = [(1.0, 0.0), (2.0, -1.0), (0.0, 3.0), (-1.5, 0.5)]
+            .into_iter()
+            .chain([(0.25, 2.0), (4.0, 0.0), (-2.0, -2.0), (0.5, 1.0)])
+            .map(|(re, im)| na::Complex::new(re, im))
+            .collect();
+
+        let mut data = input.clone();
+        fft(&mut data);
+
+        let n = input.len();
+        for (k, value) in data.iter().enumerate() {
+            let expected: na::Complex<f64> = input
+                .iter()
+                .enumerate()
+                .map(|(j, x)| {
+                    let angle = -TAU * (j * k) as f64 / n as f64;
+                    x * na::Complex::new(angle.cos(), angle.sin())
+                })
+                .sum();
+            assert!(
+                distance(*value, expected) < 1e-9,
+                "bin {k}: {value} != {expected}"
+            );
+        }
+    }
+}
@@ -1 +1,2 @@
 pub mod flat_chain;
+pub mod multi_link;
@@ -0,0 +1,230 @@
+use super::flat_chain::{self, ReverseSolutions};
+use nalgebra as na;
+use std::f64::consts::{PI, TAU};
+
+/// Planar chain anchored at the origin, each angle is relative to the previous link
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiLinkSystem {
+    pub lengths: Vec<f64>,
+}
+
+impl MultiLinkSystem {
+    const MAX_ITERATIONS: usize = 200;
+    const TOLERANCE: f64 = 1e-6;
+    /// Damping relative to the reach of the chain
+    const DAMPING: f64 = 1e-2;
+    /// Additional damping relative to the reach times the remaining error,
+    /// without it a stretched chain flips between bends for unreachable targets
+    const ERROR_DAMPING: f64 = 1.0;
+    /// Maximal effector displacement in one iteration relative to the reach of the chain
+    const MAX_STEP: f64 = 0.1;
+
+    pub fn new(lengths: Vec<f64>) -> Self {
+        Self { lengths }
+    }
+
+    pub fn reach(&self) -> f64 {
+        self.lengths.iter().sum()
+    }
+
+    /// Returns positions of all joints, starting with the origin and ending with the effector
+    pub fn forward_kinematics(&self, angles: &[f64]) -> Vec<na::Point2<f64>> {
+        let mut joints = Vec::with_capacity(self.lengths.len() + 1);
+        let mut joint = na::Point2::origin();
+        let mut angle = 0.0;
+        joints.push(joint);
+
+        for (length, relative_angle) in self.lengths.iter().zip(angles) {
+            angle += relative_angle;
+            joint += *length * na::vector![angle.cos(), angle.sin()];
+            joints.push(joint);
+        }
+
+        joints
+    }
+
+    pub fn effector(&self, angles: &[f64]) -> na::Point2<f64> {
+        *self.forward_kinematics(angles).last().unwrap()
+    }
+
+    /// Jacobian of the effector position with respect to the angles
+    pub fn jacobian(&self, angles: &[f64]) -> na::Matrix2xX<f64> {
+        let joints = self.forward_kinematics(angles);
+        let effector = joints[joints.len() - 1];
+
+        na::Matrix2xX::from_columns(
+            &joints[..self.lengths.len()]
+                .iter()
+                .map(|joint| {
+                    let arm = effector - joint;
+                    na::vector![-arm.y, arm.x]
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Two links are solved in closed form choosing the solution closest to `initial`,
+    /// other chains use damped least squares starting from `initial`.
+    /// Unreachable targets are approached as close as possible.
+    /// `initial` has to have one angle per link.
+    pub fn inverse_kinematics(&self, target: &na::Point2<f64>, initial: &[f64]) -> Vec<f64> {
+        if let [l_1, l_2] = self.lengths[..] {
+            let initial = na::vector![initial[0], initial[1]];
+            // Shifts by full turns so that the solution is continuous with `initial`
+            let unwrap = |solution: na::Point2<f64>| {
+                initial + (solution.coords - initial).map(|d| (d + PI).rem_euclid(TAU) - PI)
+            };
+
+            let solution = match flat_chain::System::new(l_1, l_2).inverse_kinematics(target) {
+                ReverseSolutions::One(solution) => Some(unwrap(solution)),
+                ReverseSolutions::Two(first, second) => {
+                    let (first, second) = (unwrap(first), unwrap(second));
+                    Some(if (first - initial).norm() <= (second - initial).norm() {
+                        first
+                    } else {
+                        second
+                    })
+                }
+                ReverseSolutions::InfinitelyMany | ReverseSolutions::None => None,
+            };
+
+            if let Some(solution) = solution {
+                return vec![solution.x, solution.y];
+            }
+        }
+
+        self.damped_least_squares(target, initial)
+    }
+
+    fn damped_least_squares(&self, target: &na::Point2<f64>, initial: &[f64]) -> Vec<f64> {
+        let mut angles = na::DVector::from_column_slice(initial);
+
+        for _ in 0..Self::MAX_ITERATIONS {
+            let error = target - self.effector(angles.as_slice());
+            if error.norm() < Self::TOLERANCE {
+                break;
+            }
+
+            // Limiting the step keeps the linearization valid for distant targets
+            let error = error.cap_magnitude(Self::MAX_STEP * self.reach());
+            let damping = (Self::DAMPING * self.reach()).powi(2)
+                + Self::ERROR_DAMPING * self.reach() * error.norm();
+
+            let jacobian = self.jacobian(angles.as_slice());
+            let Some(inverse) = (&jacobian * jacobian.transpose()
+                + na::Matrix2::identity() * damping)
+                .try_inverse()
+            else {
+                break;
+            };
+
+            angles += jacobian.transpose() * inverse * error;
+        }
+
+        angles.as_slice().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_reaches(system: &MultiLinkSystem, angles: &[f64], target: &na::Point2<f64>) {
+        let distance = na::distance(&system.effector(angles), target);
+        assert!(
+            distance < MultiLinkSystem::TOLERANCE,
+            "{angles:?} misses {target} by {distance}"
+        );
+    }
+
+    #[test]
+    fn jacobian_matches_finite_differences() {
+        const H: f64 = 1e-6;
+        let system = MultiLinkSystem::new(vec![1.0, 0.7, 1.3, 0.4]);
+        let angles = [0.3, -0.8, 1.1, 0.5];
+        let jacobian = system.jacobian(&angles);
+
+        for i in 0..angles.len() {
+            let (mut forward, mut backward) = (angles, angles);
+            forward[i] += H;
+            backward[i] -= H;
+            let numeric = (system.effector(&forward) - system.effector(&backward)) / (2.0 * H);
+
+            assert!(
+                (jacobian.column(i) - numeric).norm() < 1e-6,
+                "column {i}: {} != {numeric}",
+                jacobian.column(i)
+            );
+        }
+    }
+
+    #[test]
+    fn two_links_agree_with_flat_chain() {
+        let system = MultiLinkSystem::new(vec![1.5, 1.0]);
+        let flat = flat_chain::System::new(1.5, 1.0);
+        let initial = [0.2, 0.4];
+
+        for target in [
+            na::point![1.2, 1.3],
+            na::point![-0.8, 1.9],
+            na::point![0.0, -2.0],
+            na::point![2.1, -0.3],
+        ] {
+            let angles = system.inverse_kinematics(&target, &initial);
+            assert_reaches(&system, &angles, &target);
+
+            let ReverseSolutions::Two(first, second) = flat.inverse_kinematics(&target) else {
+                panic!("{target} should have two solutions");
+            };
+            let same_modulo_turns = |solution: na::Point2<f64>| {
+                [solution.x, solution.y]
+                    .iter()
+                    .zip(&angles)
+                    .all(|(a, b)| ((a - b) / TAU - ((a - b) / TAU).round()).abs() < 1e-9)
+            };
+            assert!(same_modulo_turns(first) || same_modulo_turns(second));
+
+            let state = flat.forward_kinematics(&na::point![angles[0], angles[1]]);
+            assert!(na::distance(&state.p_2, &system.effector(&angles)) < 1e-12);
+        }
+    }
+
+    #[test]
+    fn damped_least_squares_reaches_targets() {
+        for lengths in [
+            vec![1.0, 0.8, 0.6],
+            vec![0.5, 1.0, 0.7, 0.9],
+            vec![0.6, 0.6, 0.5, 0.4, 0.3],
+        ] {
+            let system = MultiLinkSystem::new(lengths);
+            let initial = vec![0.3; system.lengths.len()];
+
+            for direction in [0.4, 2.0, -1.2, 3.0] {
+                let target = na::Point2::origin()
+                    + 0.7 * system.reach() * na::vector![f64::cos(direction), f64::sin(direction)];
+                let angles = system.inverse_kinematics(&target, &initial);
+                assert_reaches(&system, &angles, &target);
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_targets_stretch_the_chain() {
+        for lengths in [
+            vec![1.0, 1.0],
+            vec![1.0, 0.5, 0.8],
+            vec![0.4, 0.4, 0.4, 0.4],
+        ] {
+            let system = MultiLinkSystem::new(lengths);
+            let initial = vec![0.5; system.lengths.len()];
+            let target = na::point![3.0, 4.0];
+
+            let angles = system.inverse_kinematics(&target, &initial);
+            assert!(angles.iter().all(|angle| angle.is_finite()), "{angles:?}");
+
+            let effector = system.effector(&angles);
+            assert!((effector.coords.norm() - system.reach()).abs() < 1e-6);
+            assert!(effector.coords.normalize().dot(&target.coords.normalize()) > 1.0 - 1e-6);
+        }
+    }
+}
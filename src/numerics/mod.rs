@@ -1,18 +1,24 @@
 use nalgebra as na;
 
 pub mod bezier;
+pub mod circle;
 pub mod cylinder;
+pub mod fft;
 pub mod kinematics;
 pub mod ode;
 pub mod parametric;
+pub mod polygon;
 pub mod rect;
 pub mod rotations;
 pub mod segment;
+pub mod torus;
 pub mod angle;
 
+pub use circle::Circle;
 pub use ode::EulerODESolver;
+pub use ode::Heun;
 pub use ode::RungeKuttaIV;
-pub use ode::ODE;
+pub use polygon::Polygon;
 pub use rect::Rect;
 pub use segment::Segment;
 
@@ -1,24 +1,50 @@
-use super::{ODE, Float};
+use super::{PlainODE, Solver, SolverWithDelta, State};
 
-pub struct EulerODESolver<F: Float, const DIM_OUT: usize, O: ODE<F, DIM_OUT>> {
-    pub delta: F,
+pub struct EulerODESolver<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> {
+    pub delta: f64,
     pub ode: O,
 }
 
-impl<F: Float, const DIM_OUT: usize, O: ODE<F, DIM_OUT>> EulerODESolver<F, DIM_OUT, O> {
-    pub fn new(step: F, ode: O) -> Self {
+impl<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> EulerODESolver<DIM_OUT, O> {
+    pub fn new(step: f64, ode: O) -> Self {
         Self { delta: step, ode }
     }
+}
 
-    pub fn step(&mut self) {
-        let new_y = self.ode.y() + self.ode.derivative() * self.delta;
-        let new_t = self.ode.t() + self.delta;
+impl<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> Solver<DIM_OUT, O> for EulerODESolver<DIM_OUT, O> {
+    fn step(&self, state: &State<DIM_OUT>) -> State<DIM_OUT> {
+        State {
+            t: state.t + self.delta,
+            y: state.y + self.ode.derivative(state) * self.delta,
+        }
+    }
 
-        self.ode.set_t(new_t);
-        self.ode.set_y(new_y);
+    fn replace_ode(&mut self, mut ode: O) -> O {
+        std::mem::swap(&mut self.ode, &mut ode);
+        ode
     }
 
-    pub fn take_ode(self) -> O {
+    fn take_ode(self) -> O {
         self.ode
     }
+
+    fn ode_mut(&mut self) -> &mut O {
+        &mut self.ode
+    }
+
+    fn ode(&self) -> &O {
+        &self.ode
+    }
+}
+
+impl<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> SolverWithDelta<DIM_OUT, O>
+    for EulerODESolver<DIM_OUT, O>
+{
+    fn delta_mut(&mut self) -> &mut f64 {
+        &mut self.delta
+    }
+
+    fn delta(&self) -> f64 {
+        self.delta
+    }
 }
@@ -0,0 +1,57 @@
+use super::{PlainODE, Solver, SolverWithDelta, State};
+
+/// Explicit trapezoidal rule, second order
+pub struct Heun<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> {
+    pub delta: f64,
+    pub ode: O,
+}
+
+impl<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> Heun<DIM_OUT, O> {
+    pub fn new(step: f64, ode: O) -> Self {
+        Self { delta: step, ode }
+    }
+}
+
+impl<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> Solver<DIM_OUT, O> for Heun<DIM_OUT, O> {
+    fn step(&self, state: &State<DIM_OUT>) -> State<DIM_OUT> {
+        let h = self.delta;
+
+        let k1 = self.ode.derivative(state);
+        let k2 = self.ode.derivative(&State {
+            t: state.t + h,
+            y: state.y + k1 * h,
+        });
+
+        State {
+            t: state.t + h,
+            y: state.y + (k1 + k2) * h * 0.5,
+        }
+    }
+
+    fn replace_ode(&mut self, mut ode: O) -> O {
+        std::mem::swap(&mut self.ode, &mut ode);
+        ode
+    }
+
+    fn take_ode(self) -> O {
+        self.ode
+    }
+
+    fn ode_mut(&mut self) -> &mut O {
+        &mut self.ode
+    }
+
+    fn ode(&self) -> &O {
+        &self.ode
+    }
+}
+
+impl<const DIM_OUT: usize, O: PlainODE<DIM_OUT>> SolverWithDelta<DIM_OUT, O> for Heun<DIM_OUT, O> {
+    fn delta_mut(&mut self) -> &mut f64 {
+        &mut self.delta
+    }
+
+    fn delta(&self) -> f64 {
+        self.delta
+    }
+}
@@ -0,0 +1,41 @@
+use super::Segment;
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+/// Simple polygon, the last vertex is connected to the first one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Polygon {
+    pub vertices: Vec<na::Point2<f64>>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<na::Point2<f64>>) -> Self {
+        Self { vertices }
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = Segment> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(p_1, p_2)| Segment::new(*p_1, *p_2))
+    }
+
+    /// Even-odd rule
+    pub fn contains_point(&self, p: &na::Point2<f64>) -> bool {
+        let mut inside = false;
+
+        for (p_1, p_2) in self
+            .vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+        {
+            if (p_1.y > p.y) != (p_2.y > p.y)
+                && p.x < p_1.x + (p.y - p_1.y) / (p_2.y - p_1.y) * (p_2.x - p_1.x)
+            {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+}
@@ -1,6 +1,7 @@
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Rect {
     pub p_1: na::Point2<f64>,
     pub p_2: na::Point2<f64>,
@@ -1,7 +1,8 @@
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 use std::f64::consts::FRAC_PI_2;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Quaternion(pub na::Vector4<f64>);
 
 impl Quaternion {
@@ -118,7 +119,7 @@ impl std::ops::Neg for Quaternion {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct EulerAngles(pub na::Vector3<f64>);
 
 impl EulerAngles {
@@ -173,7 +174,7 @@ impl EulerAngles {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Rotation {
     Quaternion(Quaternion),
     EulerAngles(EulerAngles),
@@ -1,4 +1,4 @@
-use super::Rect;
+use super::{Circle, Polygon, Rect};
 use nalgebra as na;
 
 #[derive(PartialEq, Eq)]
@@ -78,4 +78,27 @@ impl Segment {
 
         intersects_1 || intersects_2 || intersects_3 || intersects_4 || contains_1 || contains_2
     }
+
+    pub fn distance_to_point(&self, p: &na::Point2<f64>) -> f64 {
+        let direction = self.1 - self.0;
+        let length_squared = direction.norm_squared();
+
+        let t = if length_squared == 0.0 {
+            0.0
+        } else {
+            ((p - self.0).dot(&direction) / length_squared).clamp(0.0, 1.0)
+        };
+
+        na::distance(&(self.0 + direction * t), p)
+    }
+
+    pub fn collides_with_circle(&self, circle: &Circle) -> bool {
+        self.distance_to_point(&circle.center) <= circle.radius
+    }
+
+    pub fn collides_with_polygon(&self, polygon: &Polygon) -> bool {
+        polygon.edges().any(|edge| self.intersects(&edge))
+            || polygon.contains_point(&self.0)
+            || polygon.contains_point(&self.1)
+    }
 }
@@ -0,0 +1,85 @@
+use super::parametric::ParametricForm;
+use crate::render::{
+    gridable::Triangable,
+    mesh::{ClassicVertex, Triangle},
+};
+use nalgebra as na;
+
+/// Torus around the Z axis, parametrized by the angle around the Z axis
+/// and the angle around the tube
+#[derive(Copy, Clone, Debug)]
+pub struct Torus {
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl Torus {
+    pub fn new(major_radius: f64, minor_radius: f64) -> Self {
+        Self {
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl ParametricForm<2, 3> for Torus {
+    fn bounds(&self) -> na::Vector2<(f64, f64)> {
+        na::Vector2::new(
+            (0.0, 2.0 * std::f64::consts::PI),
+            (0.0, 2.0 * std::f64::consts::PI),
+        )
+    }
+
+    fn wrapped(&self, _dim: usize) -> bool {
+        true
+    }
+
+    fn value(&self, vec: &na::Vector2<f64>) -> na::Point3<f64> {
+        let r = self.major_radius + self.minor_radius * vec.y.cos();
+
+        na::Point3::new(
+            r * vec.x.cos(),
+            r * vec.x.sin(),
+            self.minor_radius * vec.y.sin(),
+        )
+    }
+
+    fn normal(&self, vec: &na::Vector2<f64>) -> na::Vector3<f64> {
+        na::vector![
+            vec.y.cos() * vec.x.cos(),
+            vec.y.cos() * vec.x.sin(),
+            vec.y.sin()
+        ]
+    }
+}
+
+impl Triangable for Torus {
+    fn triangulation(&self, points_x: u32, points_y: u32) -> (Vec<ClassicVertex>, Vec<Triangle>) {
+        let mut vertices = Vec::with_capacity((points_x * points_y) as usize);
+        for x_idx in 0..points_x {
+            for y_idx in 0..points_y {
+                let vec = na::vector![
+                    x_idx as f64 / points_x as f64 * 2.0 * std::f64::consts::PI,
+                    y_idx as f64 / points_y as f64 * 2.0 * std::f64::consts::PI
+                ];
+
+                vertices.push(ClassicVertex::new(
+                    self.value(&vec).map(|c| c as f32),
+                    self.normal(&vec).map(|c| c as f32),
+                ));
+            }
+        }
+
+        let idx = |x: u32, y: u32| (x % points_x) * points_y + y % points_y;
+
+        let mut triangles = Vec::with_capacity(2 * vertices.len());
+        for x in 0..points_x {
+            for y in 0..points_y {
+                triangles.push(Triangle([idx(x, y), idx(x + 1, y), idx(x + 1, y + 1)]));
+                triangles.push(Triangle([idx(x, y), idx(x + 1, y + 1), idx(x, y + 1)]));
+            }
+        }
+
+        (vertices, triangles)
+    }
+}
@@ -1,10 +1,15 @@
 use super::{Presenter, PresenterBuilder};
 use crate::{
-    controls::{camera::Camera, mouse::MouseState},
+    controls::{
+        camera::{Camera, CameraSettings},
+        keyboard::KeyboardState,
+        mouse::MouseState,
+    },
     render::{
         gl_drawable::GlDrawable, gl_mesh::GlTriangleMesh, gl_program::GlProgram,
         gl_texture::GlCubeTexture, models, texture::Texture,
     },
+    ui::widgets,
 };
 use egui::Ui;
 use glow::HasContext;
@@ -25,8 +30,8 @@ pub struct BlackHole {
 impl BlackHole {
     const ROOM_SCALE: f32 = 1000.0;
 
-    pub fn new(gl: Arc<glow::Context>) -> Self {
-        let mut camera = Camera::new();
+    pub fn new(gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Self {
+        let mut camera = Camera::new(camera_settings);
         camera.azimuth = std::f32::consts::FRAC_PI_2;
         camera.altitude = 0.0;
 
@@ -58,6 +63,7 @@ impl BlackHole {
 
 impl Presenter for BlackHole {
     fn show_side_ui(&mut self, ui: &mut Ui) {
+        ui.collapsing("Camera", |ui| widgets::camera_ui(ui, &mut self.camera));
         ui.label("Mass");
         ui.add(egui::widgets::Slider::new(&mut self.mass, 0.001..=10.0).logarithmic(true));
     }
@@ -99,6 +105,10 @@ impl Presenter for BlackHole {
 
     fn update(&mut self, _delta: std::time::Duration) {}
 
+    fn clear_color(&self) -> [f32; 4] {
+        [0.0, 0.0, 0.0, 1.0]
+    }
+
     fn name(&self) -> &'static str {
         "Black Hole"
     }
@@ -106,6 +116,10 @@ impl Presenter for BlackHole {
     fn update_mouse(&mut self, state: MouseState) {
         self.camera.update_from_mouse(state);
     }
+
+    fn update_keyboard(&mut self, state: KeyboardState, delta: std::time::Duration) {
+        self.camera.update_from_keyboard(&state, delta);
+    }
 }
 
 pub struct BlackHoleBuilder {}
@@ -121,8 +135,8 @@ impl PresenterBuilder for BlackHoleBuilder {
         ui.label("Black Hole")
     }
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
-        Box::new(BlackHole::new(gl))
+    fn build(&self, gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Box<dyn Presenter> {
+        Box::new(BlackHole::new(gl, camera_settings))
     }
 }
 
@@ -1,6 +1,8 @@
 use super::{Presenter, PresenterBuilder};
 use crate::{
-    controls::mouse::MouseState,
+    controls::{camera::CameraSettings, keyboard::KeyboardState, mouse::MouseState},
+    numerics::fft,
+    recording::{Recording, Replay, Replayable},
     render::{
         gl_drawable::GlDrawable,
         gl_mesh::{GlLineStrip, GlLines, GlTriangleMesh},
@@ -8,16 +10,30 @@ use crate::{
         mesh::{Mesh, Triangle},
         models,
     },
+    ui::widgets::PathAction,
 };
 use egui::{Rgba, Ui};
-use egui_plot::{Line, Plot};
+use egui_plot::{Legend, Line, LineStyle, Plot};
 use itertools::Itertools;
 use nalgebra as na;
+use rand::{rngs::StdRng, SeedableRng};
 use rand_distr::Distribution;
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::sync::Arc;
 
+/// Time series shown by `Hodograph::plot`, `variable` starts `shift` samples after `argument`
+struct PlotSeries<'a> {
+    color: Rgba,
+    name: &'a str,
+    argument: &'a VecDeque<f64>,
+    variable: &'a VecDeque<f64>,
+    shift: usize,
+    /// Analytic values drawn over the numerical ones, aligned with `argument`
+    exact: Option<&'a VecDeque<f64>>,
+    aspect: &'a RefCell<f32>,
+}
+
 pub struct Hodograph {
     gl_program: GlProgram,
     rect_mesh: GlTriangleMesh,
@@ -27,24 +43,34 @@ pub struct Hodograph {
 
     stddev: f64,
     dist: rand_distr::Normal<f64>,
-    rng: rand::rngs::ThreadRng,
+    seed: u64,
+    rng: StdRng,
 
     angular_speed: f64,
     arm_length: f64,
     wheel_radius: f64,
     delta: f64,
     work_left: f64,
+    step_count: usize,
     error: f64,
 
     x: VecDeque<f64>,
     xp: VecDeque<f64>,
     xpp: VecDeque<f64>,
     time: VecDeque<f64>,
+    /// Closed-form derivatives of the noiseless slide, aligned with `time`
+    xp_exact: VecDeque<f64>,
+    xpp_exact: VecDeque<f64>,
+    trajectory: Recording<4>,
+    trajectory_export: PathAction,
+    /// Crank angle and arm length error of every frame
+    replay: Replay<(f64, f64)>,
 
     xaspect: RefCell<f32>,
     xpaspect: RefCell<f32>,
     xppaspect: RefCell<f32>,
     xxxxaspect: RefCell<f32>,
+    log_spectrum: bool,
 
     angle: f64,
 
@@ -70,23 +96,31 @@ impl Hodograph {
 
             stddev: 0.000001,
             dist: rand_distr::Normal::new(0.0, 0.000001).unwrap(),
-            rng: rand::thread_rng(),
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
 
             angular_speed: 1.0,
             arm_length: 0.8,
             wheel_radius: 0.25,
             delta: 0.01,
             work_left: 0.0,
+            step_count: 0,
             error: 0.0,
 
             time: VecDeque::from([0.0]),
             x: VecDeque::new(), // Assigned later
             xp: VecDeque::new(),
             xpp: VecDeque::new(),
+            xp_exact: VecDeque::new(),
+            xpp_exact: VecDeque::new(),
+            trajectory: Recording::new(["t", "x", "xp", "xpp"], Self::MAX_HISTORY),
+            trajectory_export: PathAction::new("hodograph_trajectory.csv"),
+            replay: Replay::new(Self::MAX_HISTORY),
             xaspect: RefCell::new(1.0),
             xpaspect: RefCell::new(1.0),
             xppaspect: RefCell::new(1.0),
             xxxxaspect: RefCell::new(1.0),
+            log_spectrum: true,
 
             angle: 0.0,
 
@@ -96,6 +130,7 @@ impl Hodograph {
         me.radius_mesh.update_points(&me.radius_points());
         me.arm_mesh.update_points(&me.arm_points());
         me.x.push_back(me.slide());
+        me.push_exact_derivatives();
         me
     }
 
@@ -112,6 +147,28 @@ impl Hodograph {
             + self.angle.cos() * self.wheel_radius
     }
 
+    /// Velocity and acceleration of the slide without the arm length error
+    fn slide_derivatives(&self) -> (f64, f64) {
+        let (s, c) = self.angle.sin_cos();
+        let r = self.wheel_radius;
+        let root = (self.arm_length.powi(2) - (r * s).powi(2)).sqrt();
+
+        let dx = -r * r * s * c / root - r * s;
+        let ddx =
+            -r * r * (c * c - s * s) / root - r.powi(4) * (s * c).powi(2) / root.powi(3) - r * c;
+
+        (
+            dx * self.angular_speed,
+            ddx * self.angular_speed * self.angular_speed,
+        )
+    }
+
+    fn push_exact_derivatives(&mut self) {
+        let (xp, xpp) = self.slide_derivatives();
+        self.xp_exact.push_back(xp);
+        self.xpp_exact.push_back(xpp);
+    }
+
     fn create_rect_mesh(gl: Arc<glow::Context>) -> GlTriangleMesh {
         // 0 1
         // 3 2
@@ -149,16 +206,17 @@ impl Hodograph {
         ]
     }
 
-    fn plot(
-        &self,
-        ui: &mut Ui,
-        color: Rgba,
-        name: &str,
-        argument: &VecDeque<f64>,
-        variable: &VecDeque<f64>,
-        shift: usize,
-        aspect: &RefCell<f32>,
-    ) {
+    fn plot(&self, ui: &mut Ui, series: PlotSeries) {
+        let PlotSeries {
+            color,
+            name,
+            argument,
+            variable,
+            shift,
+            exact,
+            aspect,
+        } = series;
+
         let line = Line::new(
             argument
                 .iter()
@@ -168,13 +226,73 @@ impl Hodograph {
                 .collect_vec(),
         )
         .color(color)
-        .name(name);
+        .name("Numerical");
+
+        let exact = exact.map(|exact| {
+            Line::new(
+                argument
+                    .iter()
+                    .zip(exact.iter())
+                    .map(|(&t, &x)| [t, x])
+                    .collect_vec(),
+            )
+            .color(Rgba::from_rgb(0.8, 0.8, 0.8))
+            .style(LineStyle::dashed_dense())
+            .name("Analytic")
+        });
 
         ui.vertical(|ui| {
             ui.label(name);
             ui.add(egui::Slider::new(&mut *aspect.borrow_mut(), 0.01..=100.0).logarithmic(true));
             Plot::new(name)
                 .data_aspect(*aspect.borrow())
+                .view_aspect(1.0)
+                .width(350.0)
+                .height(350.0)
+                .legend(Legend::default())
+                .show(ui, |plot_ui| {
+                    plot_ui.line(line);
+                    if let Some(exact) = exact {
+                        plot_ui.line(exact);
+                    }
+                });
+        });
+    }
+
+    /// Spectrum of the newest power of two samples of the position
+    fn spectrum(&self) -> Vec<[f64; 2]> {
+        if self.x.len() < 2 {
+            return Vec::new();
+        }
+
+        let window = 1 << self.x.len().ilog2();
+        let samples: Vec<_> = self.x.iter().skip(self.x.len() - window).copied().collect();
+        let resolution = 1.0 / (window as f64 * self.delta);
+
+        fft::magnitude_spectrum(&samples)
+            .into_iter()
+            .enumerate()
+            .map(|(k, magnitude)| {
+                let magnitude = if self.log_spectrum {
+                    magnitude.max(f64::MIN_POSITIVE).log10()
+                } else {
+                    magnitude
+                };
+
+                [k as f64 * resolution, magnitude]
+            })
+            .collect()
+    }
+
+    fn spectrum_plot(&mut self, ui: &mut Ui) {
+        let line = Line::new(self.spectrum())
+            .color(Rgba::from_rgb(1.0, 0.8, 0.1))
+            .name("Spectrum");
+
+        ui.vertical(|ui| {
+            ui.label("Position spectrum [Hz]");
+            ui.checkbox(&mut self.log_spectrum, "Logarithmic magnitude");
+            Plot::new("Spectrum")
                 .view_aspect(1.0)
                 .width(350.0)
                 .height(350.0)
@@ -185,8 +303,32 @@ impl Hodograph {
     }
 }
 
+impl Replayable for Hodograph {
+    type Snapshot = (f64, f64);
+
+    fn snapshot(&self) -> Self::Snapshot {
+        (self.angle, self.error)
+    }
+
+    fn restore(&mut self, &(angle, error): &Self::Snapshot) {
+        self.angle = angle;
+        self.error = error;
+        self.radius_mesh.update_points(&self.radius_points());
+        self.arm_mesh.update_points(&self.arm_points());
+    }
+}
+
 impl Presenter for Hodograph {
     fn show_side_ui(&mut self, ui: &mut Ui) {
+        if let Some(frame) = self.replay.ui(ui) {
+            self.restore(&frame);
+        }
+
+        if let Some(path) = self.trajectory_export.ui(ui, "Export trajectory CSV") {
+            let result = self.trajectory.save_csv(&path);
+            self.trajectory_export.report(result);
+        }
+
         ui.label("ε0");
         if ui
             .add(
@@ -199,6 +341,18 @@ impl Presenter for Hodograph {
             self.dist = rand_distr::Normal::new(0.0, self.stddev).unwrap();
         }
 
+        ui.label("Noise seed");
+        let reseed = ui
+            .horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.seed)).changed()
+                    | ui.button("Restart sequence").clicked()
+            })
+            .inner;
+
+        if reseed {
+            self.rng = StdRng::seed_from_u64(self.seed);
+        }
+
         ui.label("ω");
         ui.add(
             egui::DragValue::new(&mut self.angular_speed)
@@ -236,12 +390,15 @@ impl Presenter for Hodograph {
 
         self.plot(
             ui,
-            Rgba::from_rgb(0.8, 0.8, 0.8),
-            "State",
-            &self.x,
-            &self.xp,
-            0,
-            &self.xxxxaspect,
+            PlotSeries {
+                color: Rgba::from_rgb(0.8, 0.8, 0.8),
+                name: "State",
+                argument: &self.x,
+                variable: &self.xp,
+                shift: 0,
+                exact: None,
+                aspect: &self.xxxxaspect,
+            },
         );
     }
 
@@ -249,31 +406,41 @@ impl Presenter for Hodograph {
         ui.horizontal(|ui| {
             self.plot(
                 ui,
-                Rgba::from_rgb(0.1, 0.1, 1.0),
-                "Position",
-                &self.time,
-                &self.x,
-                0,
-                &self.xaspect,
+                PlotSeries {
+                    color: Rgba::from_rgb(0.1, 0.1, 1.0),
+                    name: "Position",
+                    argument: &self.time,
+                    variable: &self.x,
+                    shift: 0,
+                    exact: None,
+                    aspect: &self.xaspect,
+                },
             );
             self.plot(
                 ui,
-                Rgba::from_rgb(0.1, 1.0, 0.1),
-                "Velocity",
-                &self.time,
-                &self.xp,
-                1,
-                &self.xpaspect,
+                PlotSeries {
+                    color: Rgba::from_rgb(0.1, 1.0, 0.1),
+                    name: "Velocity",
+                    argument: &self.time,
+                    variable: &self.xp,
+                    shift: 1,
+                    exact: Some(&self.xp_exact),
+                    aspect: &self.xpaspect,
+                },
             );
             self.plot(
                 ui,
-                Rgba::from_rgb(1.0, 0.1, 0.1),
-                "Acceleration",
-                &self.time,
-                &self.xpp,
-                1,
-                &self.xppaspect,
+                PlotSeries {
+                    color: Rgba::from_rgb(1.0, 0.1, 0.1),
+                    name: "Acceleration",
+                    argument: &self.time,
+                    variable: &self.xpp,
+                    shift: 2,
+                    exact: Some(&self.xpp_exact),
+                    aspect: &self.xppaspect,
+                },
             );
+            self.spectrum_plot(ui);
         });
     }
 
@@ -322,13 +489,19 @@ impl Presenter for Hodograph {
     }
 
     fn update(&mut self, delta: std::time::Duration) {
+        if let Some(live) = self.replay.resume() {
+            self.restore(&live);
+        }
+
         self.work_left += delta.as_secs_f64() * self.simulation_speed;
         while self.work_left >= self.delta {
+            self.step_count += 1;
             self.error = self.error();
             self.angle += self.angular_speed * self.delta;
 
             self.time.push_back(self.delta + self.time.back().unwrap());
             self.x.push_back(self.slide());
+            self.push_exact_derivatives();
 
             if self.x.len() >= 3 {
                 self.xp.push_back(
@@ -340,6 +513,15 @@ impl Presenter for Hodograph {
                 self.xpp.push_back(
                     (self.xp[self.xp.len() - 1] - self.xp[self.xp.len() - 3]) / 2.0 / self.delta,
                 );
+
+                // Central differences lag behind, rows are aligned to the newest acceleration
+                let x_index = self.x.len() - 3;
+                self.trajectory.push([
+                    self.time[x_index],
+                    self.x[x_index],
+                    self.xp[self.xp.len() - 2],
+                    self.xpp[self.xpp.len() - 1],
+                ]);
             }
 
             if self.xpp.len() > Self::MAX_HISTORY {
@@ -348,6 +530,8 @@ impl Presenter for Hodograph {
                 self.x.drain(0..=to_remove);
                 self.xp.drain(0..=to_remove);
                 self.xpp.drain(0..=to_remove);
+                self.xp_exact.drain(0..=to_remove);
+                self.xpp_exact.drain(0..=to_remove);
             }
 
             self.work_left -= self.delta;
@@ -355,6 +539,11 @@ impl Presenter for Hodograph {
 
         self.radius_mesh.update_points(&self.radius_points());
         self.arm_mesh.update_points(&self.arm_points());
+        self.replay.record(self.snapshot());
+    }
+
+    fn step_count(&self) -> Option<usize> {
+        Some(self.step_count)
     }
 
     fn name(&self) -> &'static str {
@@ -362,6 +551,8 @@ impl Presenter for Hodograph {
     }
 
     fn update_mouse(&mut self, _state: MouseState) {}
+
+    fn update_keyboard(&mut self, _state: KeyboardState, _delta: std::time::Duration) {}
 }
 
 pub struct HodographBuilder {}
@@ -377,7 +568,11 @@ impl PresenterBuilder for HodographBuilder {
         ui.label("Hodograph")
     }
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
+    fn build(
+        &self,
+        gl: Arc<glow::Context>,
+        _camera_settings: CameraSettings,
+    ) -> Box<dyn Presenter> {
         Box::new(Hodograph::new(gl))
     }
 }
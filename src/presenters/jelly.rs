@@ -1,29 +1,44 @@
+use super::parametrizable_function::{
+    constant_function, sine, step_sine, ConstantFunction, ParametrizableFunction, Sine, StepSine,
+};
 use super::Presenter;
 use super::PresenterBuilder;
-use crate::controls::{camera::Camera, mouse::MouseState};
-use crate::numerics::{bezier, ode};
+use crate::controls::{
+    camera::{Camera, CameraSettings},
+    keyboard::KeyboardState,
+    mouse::MouseState,
+};
+use crate::json;
+use crate::numerics::{
+    bezier,
+    ode::{self, PlainODE, State},
+};
 use crate::render::{
+    export,
     gl_drawable::GlDrawable,
     gl_mesh::{GlLineStrip, GlLines, GlPointCloud, GlTesselationBicubicPatch, GlTriangleMesh},
     gl_program::GlProgram,
-    mesh::Mesh,
+    light::Light,
+    mesh::{ClassicVertex, Mesh, ParseError, Triangle, Vertex},
     models,
 };
-use crate::simulators::jelly::{self, JellyODE, JellyState};
-use crate::ui::widgets::vector_drag;
-use egui::{DragValue, Ui};
+use crate::simulators::jelly::{self, JellyODE};
+use crate::ui::widgets::{self, vector_drag, PathAction};
+use egui::{ComboBox, DragValue, Slider, Ui};
+use egui_plot::{Line, Plot};
+use egui_winit::winit::dpi::PhysicalSize;
 use glow::HasContext;
 use nalgebra as na;
-use rand::Rng;
-use std::cell::RefCell;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
 use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
 
-const LIGHT_POSITION: na::Vector3<f32> = na::vector![-2.0, 4.0, -2.0];
-const LIGHT_COLOR: na::Vector3<f32> = na::vector![1.0, 1.0, 1.0];
-const LIGHT_AMBIENT: na::Vector3<f32> = na::vector![0.4, 0.4, 0.4];
-
 struct Room {
     program: GlProgram,
     mesh: GlTriangleMesh,
@@ -52,7 +67,7 @@ impl Room {
         ui.checkbox(&mut self.show, "Show the room");
     }
 
-    fn draw(&self, aspect_ratio: f32, camera: &Camera) {
+    fn draw(&self, aspect_ratio: f32, camera: &Camera, light: &Light) {
         if !self.show {
             return;
         }
@@ -67,12 +82,7 @@ impl Room {
 
         self.program
             .uniform_3_f32_slice("eye_position", camera.position().coords.as_slice());
-        self.program
-            .uniform_3_f32_slice("light_position", LIGHT_POSITION.as_slice());
-        self.program
-            .uniform_3_f32_slice("light_color", LIGHT_COLOR.as_slice());
-        self.program
-            .uniform_3_f32_slice("ambient", LIGHT_AMBIENT.as_slice());
+        light.set_uniforms(&self.program);
 
         self.program
             .uniform_4_f32_slice("material_color", Self::COLOR.as_slice());
@@ -167,40 +177,174 @@ impl ControlFrame {
     }
 }
 
+struct SpringNetwork {
+    program: GlProgram,
+    stretched: GlLines,
+    compressed: GlLines,
+    show: bool,
+}
+
+impl SpringNetwork {
+    const STRETCHED_COLOR: na::Vector4<f32> = na::vector![0.9, 0.1, 0.1, 1.0];
+    const COMPRESSED_COLOR: na::Vector4<f32> = na::vector![0.1, 0.2, 0.9, 1.0];
+
+    fn new(gl: Arc<glow::Context>) -> Self {
+        Self {
+            program: GlProgram::vertex_fragment(Arc::clone(&gl), "perspective_vert", "color_frag"),
+            stretched: GlLines::new(Arc::clone(&gl), &[]),
+            compressed: GlLines::new(Arc::clone(&gl), &[]),
+            show: false,
+        }
+    }
+
+    /// `true` when the network has to be updated
+    fn ui(&mut self, ui: &mut Ui) -> bool {
+        ui.checkbox(
+            &mut self.show,
+            "Show springs (red stretched, blue compressed)",
+        )
+        .changed()
+    }
+
+    fn update<const N: usize, const D: usize>(&mut self, simulation: &Simulation<N, D>)
+    where
+        JellyODE<N>: PlainODE<D>,
+    {
+        if !self.show {
+            return;
+        }
+
+        let ode = simulation.solver.ode();
+        let (stretched, compressed): (Vec<_>, Vec<_>) = ode
+            .spring_extensions(&simulation.state, &ode.frame_transform())
+            .into_iter()
+            .partition(|(_, _, extension)| *extension >= 0.0);
+
+        let points = |springs: Vec<(na::Point3<f64>, na::Point3<f64>, f64)>| {
+            springs
+                .into_iter()
+                .flat_map(|(first, second, _)| [first.cast::<f32>(), second.cast::<f32>()])
+                .collect::<Vec<_>>()
+        };
+
+        self.stretched.update_points(&points(stretched));
+        self.compressed.update_points(&points(compressed));
+    }
+
+    fn draw(&self, aspect_ratio: f32, camera: &Camera) {
+        if !self.show {
+            return;
+        }
+
+        self.program.enable();
+        self.program
+            .uniform_matrix_4_f32_slice("view_transform", camera.view_transform().as_slice());
+        self.program.uniform_matrix_4_f32_slice(
+            "projection_transform",
+            camera.projection_transform(aspect_ratio).as_slice(),
+        );
+        self.program
+            .uniform_matrix_4_f32_slice("model_transform", na::Matrix4::identity().as_slice());
+
+        self.program
+            .uniform_4_f32_slice("color", Self::STRETCHED_COLOR.as_slice());
+        self.stretched.draw();
+
+        self.program
+            .uniform_4_f32_slice("color", Self::COMPRESSED_COLOR.as_slice());
+        self.compressed.draw();
+    }
+}
+
 struct Model {
     program: GlProgram,
-    mesh: GlTriangleMesh,
+    mesh: Result<GlTriangleMesh, ParseError>,
     transform: na::Matrix4<f32>,
     show: bool,
+    show_wireframe: bool,
+    load: PathAction,
+    gl: Arc<glow::Context>,
 }
 
 impl Model {
     const MODEL_COLOR: [f32; 4] = [0.1, 0.4, 1.0, 1.0];
+    const MODEL_PATH: &'static str = "models/duck.txt";
+
     fn new(gl: Arc<glow::Context>) -> Self {
-        Self {
+        let mut me = Self {
             program: GlProgram::vertex_fragment(
                 Arc::clone(&gl),
                 "bezier_deformed_vert",
                 "phong_frag",
             ),
-            mesh: GlTriangleMesh::new(
-                Arc::clone(&gl),
-                &Mesh::from_file(Path::new("models/duck.txt")),
-            ),
-            transform: na::Translation3::new(0.5, 0.0, 0.5).to_homogeneous()
-                * na::Scale3::new(0.005, 0.005, 0.005).to_homogeneous(),
+            mesh: Err(ParseError::Format),
+            transform: na::Matrix4::identity(),
             show: true,
-        }
+            show_wireframe: false,
+            load: PathAction::new(Self::MODEL_PATH),
+            gl,
+        };
+
+        me.load(Path::new(Self::MODEL_PATH));
+
+        me
+    }
+
+    /// Maps the bounding box of the mesh onto the center of the unit cube,
+    /// which is the parameter space of the deformation
+    fn fit_transform<V: Vertex>(mesh: &Mesh<V>) -> na::Matrix4<f32> {
+        let (min, max) = mesh.bounding_box();
+        let extent = (max - min).max();
+        let scale = if extent > 0.0 { 1.0 / extent } else { 1.0 };
+
+        na::Translation3::new(0.5, 0.5, 0.5).to_homogeneous()
+            * na::Scale3::new(scale, scale, scale).to_homogeneous()
+            * na::Translation3::from(-na::center(&min, &max).coords).to_homogeneous()
+    }
+
+    /// OBJ files are recognized by the extension, other files are read in the duck format.
+    /// The previous model is kept if loading fails.
+    fn load(&mut self, path: &Path) {
+        let is_obj = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("obj"));
+
+        let loaded = if is_obj {
+            Mesh::from_obj(path).map(|mesh| {
+                (
+                    GlTriangleMesh::new(Arc::clone(&self.gl), &mesh),
+                    Self::fit_transform(&mesh),
+                )
+            })
+        } else {
+            Mesh::from_file(path).map(|mesh| {
+                (
+                    GlTriangleMesh::new(Arc::clone(&self.gl), &mesh),
+                    Self::fit_transform(&mesh),
+                )
+            })
+        };
+
+        let result = loaded.map(|(mesh, transform)| {
+            self.mesh = Ok(mesh);
+            self.transform = transform;
+        });
+
+        self.load.report(result);
     }
 
     fn ui(&mut self, ui: &mut Ui) {
         ui.checkbox(&mut self.show, "Show model");
+        ui.checkbox(&mut self.show_wireframe, "Model wireframe");
+        if let Some(path) = self.load.ui(ui, "Load model") {
+            self.load(&path);
+        }
     }
 
-    fn draw(&self, aspect_ratio: f32, camera: &Camera, cube: &[f32; 3 * 64]) {
-        if !self.show {
+    fn draw(&self, aspect_ratio: f32, camera: &Camera, cube: &[f32; 3 * 64], light: &Light) {
+        let (true, Ok(mesh)) = (self.show, &self.mesh) else {
             return;
-        }
+        };
 
         self.program.enable();
         self.program
@@ -215,12 +359,7 @@ impl Model {
 
         self.program
             .uniform_3_f32_slice("eye_position", camera.position().coords.as_slice());
-        self.program
-            .uniform_3_f32_slice("light_position", LIGHT_POSITION.as_slice());
-        self.program
-            .uniform_3_f32_slice("light_color", LIGHT_COLOR.as_slice());
-        self.program
-            .uniform_3_f32_slice("ambient", LIGHT_AMBIENT.as_slice());
+        light.set_uniforms(&self.program);
 
         self.program
             .uniform_4_f32_slice("material_color", Self::MODEL_COLOR.as_slice());
@@ -228,11 +367,17 @@ impl Model {
         self.program.uniform_f32("material_specular", 0.4);
         self.program.uniform_f32("material_specular_exp", 10.0);
 
-        self.mesh.draw();
+        if self.show_wireframe {
+            mesh.draw_wireframe();
+        } else {
+            mesh.draw();
+        }
     }
 }
 
-struct BezierCube {
+/// Simulated control lattice, it is drawn as is and converted to the tricubic
+/// lattice used by the shaders
+struct BezierCube<const N: usize> {
     point_program: GlProgram,
     point_cloud: GlPointCloud,
     show_points: bool,
@@ -242,20 +387,22 @@ struct BezierCube {
     grid_transform: na::Matrix4<f32>, // Cached identity
     show_grid: bool,
 
-    cube: bezier::Cube<f64>,
+    cube: bezier::Cube<f64, N>,
+    cubic: bezier::Cube<f64>,
     flat_cube: [f32; 3 * 64],
     gl: Arc<glow::Context>,
 }
 
-impl BezierCube {
+impl<const N: usize> BezierCube<N> {
     const POINT_SIZE: f32 = 6.0;
     const POINT_COLOR: [f32; 4] = [0.4, 1.0, 0.4, 1.0];
 
     fn new(gl: Arc<glow::Context>) -> Self {
         let cube = bezier::Cube::new();
+        let cubic = cube.to_cubic();
         Self {
             point_program: GlProgram::vertex_fragment(Arc::clone(&gl), "point_vert", "color_frag"),
-            point_cloud: GlPointCloud::new(Arc::clone(&gl), &cube.as_f32_array()),
+            point_cloud: GlPointCloud::new(Arc::clone(&gl), &Self::points(&cube)),
             show_points: true,
 
             grid_program: GlProgram::vertex_fragment(
@@ -263,16 +410,23 @@ impl BezierCube {
                 "perspective_vert",
                 "color_frag",
             ),
-            grid_lines: GlLines::new(Arc::clone(&gl), &models::wire_grid()),
+            grid_lines: GlLines::new(Arc::clone(&gl), &models::wire_lattice(N)),
             grid_transform: na::Matrix4::identity(),
             show_grid: true,
 
-            flat_cube: cube.as_f32_flat(),
+            flat_cube: cubic.as_f32_flat(),
             cube,
+            cubic,
             gl,
         }
     }
 
+    fn points(cube: &bezier::Cube<f64, N>) -> Vec<na::Point3<f32>> {
+        (0..bezier::Cube::<f64, N>::POINT_COUNT)
+            .map(|i| cube.flat(i).cast())
+            .collect()
+    }
+
     fn ui(&mut self, ui: &mut Ui) {
         ui.checkbox(&mut self.show_points, "Show bezier points");
         ui.checkbox(&mut self.show_grid, "Show bezier grid");
@@ -322,12 +476,25 @@ impl BezierCube {
         }
     }
 
+    fn bounding_sphere(&self) -> (na::Point3<f32>, f32) {
+        let points = Self::points(&self.cube);
+        let center = na::Point3::from(
+            points.iter().map(|p| p.coords).sum::<na::Vector3<f32>>() / points.len() as f32,
+        );
+        let radius = points
+            .iter()
+            .map(|p| na::distance(p, &center))
+            .fold(0.0, f32::max);
+
+        (center, radius)
+    }
+
     fn update_cube(&mut self) {
-        self.flat_cube = self.cube.as_f32_flat();
-        let cube_array = self.cube.as_f32_array();
-        self.point_cloud.update_points(&cube_array);
+        self.cubic = self.cube.to_cubic();
+        self.flat_cube = self.cubic.as_f32_flat();
+        self.point_cloud.update_points(&Self::points(&self.cube));
         self.grid_lines
-            .update_points(&models::wire_grid_from_fn(|u, v, w| {
+            .update_points(&models::wire_lattice_from_fn(N, |u, v, w| {
                 self.cube.0[u][v][w].map(|c| (c + 1.0) as f32 * 0.5)
             }))
     }
@@ -335,8 +502,10 @@ impl BezierCube {
 
 struct BezierPatches {
     program: GlProgram,
+    patches: [bezier::Patch<f32>; 6],
     surfaces: [GlTesselationBicubicPatch; 6],
     show: bool,
+    show_wireframe: bool,
     gl: Arc<glow::Context>,
 }
 
@@ -345,6 +514,8 @@ impl BezierPatches {
     const COLOR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
 
     fn new(gl: Arc<glow::Context>, cube: &bezier::Cube<f64>) -> Self {
+        let patches = cube.patches_f32();
+
         Self {
             program: GlProgram::with_shader_names(
                 Arc::clone(&gl),
@@ -355,15 +526,15 @@ impl BezierPatches {
                     ("phong_frag", glow::FRAGMENT_SHADER),
                 ],
             ),
-            surfaces: cube
-                .patches_f32()
-                .map(|p| GlTesselationBicubicPatch::new(Arc::clone(&gl), &p)),
+            surfaces: patches.map(|p| GlTesselationBicubicPatch::new(Arc::clone(&gl), &p)),
+            patches,
             show: true,
+            show_wireframe: false,
             gl,
         }
     }
 
-    fn draw(&self, aspect_ratio: f32, camera: &Camera) {
+    fn draw(&self, aspect_ratio: f32, camera: &Camera, light: &Light) {
         if !self.show {
             return;
         }
@@ -383,12 +554,7 @@ impl BezierPatches {
 
         self.program
             .uniform_3_f32_slice("eye_position", camera.position().coords.as_slice());
-        self.program
-            .uniform_3_f32_slice("light_position", LIGHT_POSITION.as_slice());
-        self.program
-            .uniform_3_f32_slice("light_color", LIGHT_COLOR.as_slice());
-        self.program
-            .uniform_3_f32_slice("ambient", LIGHT_AMBIENT.as_slice());
+        light.set_uniforms(&self.program);
 
         self.program
             .uniform_4_f32_slice("material_color", Self::COLOR.as_slice());
@@ -396,55 +562,180 @@ impl BezierPatches {
         self.program.uniform_f32("material_specular", 0.4);
         self.program.uniform_f32("material_specular_exp", 10.0);
 
-        self.program.uniform_u32("invert_normals", 0);
-        for surface in self.surfaces.iter().take(3) {
+        // Patch drawing restores the fill mode, so the wireframe mode has to be set for each one
+        let draw_surface = |surface: &GlTesselationBicubicPatch| {
+            if self.show_wireframe {
+                unsafe { self.gl.polygon_mode(glow::FRONT_AND_BACK, glow::LINE) };
+            }
+
             surface.draw();
-        }
+        };
+
+        self.program.uniform_u32("invert_normals", 0);
+        self.surfaces.iter().take(3).for_each(draw_surface);
 
         unsafe { self.gl.cull_face(glow::FRONT) };
         self.program.uniform_u32("invert_normals", 1);
-        for surface in self.surfaces.iter().skip(3).take(3) {
-            surface.draw();
-        }
+        self.surfaces.iter().skip(3).take(3).for_each(draw_surface);
         unsafe { self.gl.cull_face(glow::BACK) };
     }
 
     fn ui(&mut self, ui: &mut Ui) {
         ui.checkbox(&mut self.show, "Show bezier patches");
+        ui.checkbox(&mut self.show_wireframe, "Bezier patches wireframe");
     }
 
     fn update_cube(&mut self, cube: &bezier::Cube<f64>) {
-        self.surfaces = cube
-            .patches_f32()
-            .map(|p| GlTesselationBicubicPatch::new(Arc::clone(&self.gl), &p));
+        self.patches = cube.patches_f32();
+        for (surface, patch) in self.surfaces.iter_mut().zip(&self.patches) {
+            surface.update_points(patch);
+        }
+    }
+
+    /// Tessellates the patches on the CPU with the same density as the GPU
+    fn mesh(&self) -> Mesh<ClassicVertex> {
+        let n = Self::SUBDIVISIONS;
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+
+        for (k, patch) in self.patches.iter().enumerate() {
+            let offset = vertices.len() as u32;
+            let idx = |i: u32, j: u32| offset + i * (n + 1) + j;
+
+            for i in 0..=n {
+                for j in 0..=n {
+                    let position = bezier::bicubic(patch, i as f32 / n as f32, j as f32 / n as f32);
+                    vertices.push(ClassicVertex::new(position, na::Vector3::zeros()));
+                }
+            }
+
+            for i in 0..n {
+                for j in 0..n {
+                    // The last three patches are the opposite faces with the same parametrization
+                    let mut quad = [
+                        [idx(i, j), idx(i + 1, j), idx(i, j + 1)],
+                        [idx(i + 1, j), idx(i + 1, j + 1), idx(i, j + 1)],
+                    ];
+
+                    if k >= 3 {
+                        quad.iter_mut().for_each(|t| t.swap(1, 2));
+                    }
+
+                    triangles.extend(quad.map(Triangle));
+                }
+            }
+        }
+
+        let mut mesh = Mesh::new(vertices, triangles);
+        mesh.recompute_normals();
+        mesh
     }
 }
 
-struct Simulation {
-    state: JellyState,
-    solver: Box<dyn ode::SolverWithDelta<{ jelly::ODE_DIM }, JellyODE>>,
+struct Simulation<const N: usize, const D: usize> {
+    state: State<D>,
+    /// Point index and position it is held at, overrides the simulated motion of the point
+    held_point: Option<(usize, na::Point3<f64>)>,
+    solver: Box<dyn ode::SolverWithDelta<D, JellyODE<N>>>,
     disruption_strength: f64,
+    /// Disruptions are reproducible for a given seed
+    seed: u64,
+    rng: StdRng,
     simulation_speed: f64,
     exact_t: f64,
+    step_count: usize,
+    energy_history: VecDeque<[f64; 2]>,
+    wind_modulations: Vec<Box<dyn ParametrizableFunction<F = f64>>>,
+    selected_wind_modulation_idx: usize,
 }
 
-impl Simulation {
+impl<const N: usize, const D: usize> Simulation<N, D>
+where
+    JellyODE<N>: PlainODE<D>,
+{
+    const MAX_ENERGY_HISTORY: usize = 10000;
+
     fn new(control_frame_transform: Rc<RefCell<jelly::ControlFrameTransform>>) -> Self {
         Self {
-            state: JellyODE::default_state(),
+            state: JellyODE::<N>::initial_state(),
+            held_point: None,
             solver: Box::new(ode::RungeKuttaIV::new(
                 0.01,
                 JellyODE::new(control_frame_transform),
             )),
             disruption_strength: 1.0,
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
             simulation_speed: 1.0,
             exact_t: 0.0,
+            step_count: 0,
+            energy_history: VecDeque::new(),
+            wind_modulations: vec![
+                Box::new(ConstantFunction::new(
+                    1.0,
+                    constant_function::Ranges::new(-5.0..=5.0),
+                )),
+                Box::new(Sine::new(
+                    1.0,
+                    1.0,
+                    0.0,
+                    sine::Ranges::new(-5.0..=5.0, -10.0..=10.0, -PI..=PI),
+                )),
+                Box::new(StepSine::new(
+                    1.0,
+                    1.0,
+                    0.0,
+                    step_sine::Ranges::new(-5.0..=5.0, -10.0..=10.0, -PI..=PI),
+                )),
+            ],
+            selected_wind_modulation_idx: 0,
+        }
+    }
+
+    /// Returns the lattice to rest and restarts the disruption sequence, parameters are kept
+    fn reset(&mut self) {
+        self.state = JellyODE::<N>::initial_state();
+        self.held_point = None;
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.exact_t = 0.0;
+        self.step_count = 0;
+        self.energy_history.clear();
+    }
+
+    fn wind_ui(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.solver.ode_mut().enable_wind, "Wind");
+        vector_drag(
+            ui,
+            &mut self.solver.ode_mut().wind,
+            -100.0,
+            100.0,
+            "",
+            0.05,
+            &["x", "y", "z"],
+        );
+
+        let mut changed =
+            self.wind_modulations[self.selected_wind_modulation_idx].manipulation_ui(ui);
+
+        ComboBox::from_label("Wind modulation")
+            .selected_text(self.wind_modulations[self.selected_wind_modulation_idx].name())
+            .show_ui(ui, |ui| {
+                for (i, f) in self.wind_modulations.iter().enumerate() {
+                    changed |= ui
+                        .selectable_value(&mut self.selected_wind_modulation_idx, i, f.name())
+                        .clicked();
+                }
+            });
+
+        if changed {
+            self.solver.ode_mut().wind_modulation =
+                self.wind_modulations[self.selected_wind_modulation_idx].produce_closure();
         }
     }
 
     fn update(
         &mut self,
-        cube: &mut BezierCube,
+        cube: &mut BezierCube<N>,
         patches: &mut BezierPatches,
         delta: std::time::Duration,
     ) {
@@ -456,33 +747,70 @@ impl Simulation {
         }
     }
 
-    fn step_update(&mut self, cube: &mut BezierCube, patches: &mut BezierPatches) {
+    fn step_update(&mut self, cube: &mut BezierCube<N>, patches: &mut BezierPatches) {
+        self.step_count += 1;
         self.state = self
             .solver
             .ode()
             .apply_collisions(self.solver.step(&self.state));
+        self.apply_held_point();
+        self.sync_cube(cube, patches);
 
-        for idx in 0..jelly::POINT_COUNT {
-            let point = cube.cube.flat_mut(idx);
-            point.x = self.state.y[idx * 3 + 0];
-            point.y = self.state.y[idx * 3 + 1];
-            point.z = self.state.y[idx * 3 + 2];
+        if self.energy_history.len() == Self::MAX_ENERGY_HISTORY {
+            self.energy_history.pop_front();
+        }
+
+        let ode = self.solver.ode();
+        self.energy_history.push_back([
+            self.state.t,
+            ode.potential_energy(&self.state, &ode.frame_transform()),
+        ]);
+    }
+
+    fn sync_cube(&self, cube: &mut BezierCube<N>, patches: &mut BezierPatches) {
+        for idx in 0..jelly::point_count(N) {
+            *cube.cube.flat_mut(idx) = self.point(idx);
         }
 
         cube.update_cube();
-        patches.update_cube(&cube.cube);
+        patches.update_cube(&cube.cubic);
+    }
+
+    fn point(&self, index: usize) -> na::Point3<f64> {
+        jelly::position(self.state.y.as_slice(), index)
+    }
+
+    /// Control point closest to the ray, if it is within `radius` from it
+    fn pick(
+        &self,
+        origin: &na::Point3<f32>,
+        direction: &na::Vector3<f32>,
+        radius: f32,
+    ) -> Option<usize> {
+        (0..jelly::point_count(N))
+            .map(|index| {
+                let offset = self.point(index).cast::<f32>() - origin;
+                let distance = (offset - direction * offset.dot(direction)).norm();
+                (index, distance)
+            })
+            .filter(|&(_, distance)| distance <= radius)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    fn apply_held_point(&mut self) {
+        let Some((index, position)) = self.held_point else {
+            return;
+        };
+
+        let y = self.state.y.as_mut_slice();
+        jelly::set_position(y, index, &position);
+        jelly::set_velocity(y, index, &na::Vector3::zeros());
     }
 
     fn apply_random_disruption(&mut self) {
-        let mut rng = rand::thread_rng();
-        for y in self
-            .state
-            .y
-            .iter_mut()
-            .skip(jelly::SPACE_DIM)
-            .take(jelly::ODE_DIM)
-        {
-            *y += (rng.gen::<f64>() * 2.0 - 1.0) * self.disruption_strength;
+        for y in jelly::velocities_mut(self.state.y.as_mut_slice()) {
+            *y += (self.rng.gen::<f64>() * 2.0 - 1.0) * self.disruption_strength;
         }
     }
 
@@ -508,6 +836,20 @@ impl Simulation {
                 .speed(0.25),
         );
 
+        ui.label("Disruption seed");
+        let reseed = ui
+            .horizontal(|ui| {
+                ui.add(DragValue::new(&mut self.seed)).changed()
+                    | ui.button("Restart sequence").clicked()
+            })
+            .inner;
+
+        if reseed {
+            self.rng = StdRng::seed_from_u64(self.seed);
+        }
+
+        ui.label("Drag control points with the left mouse button");
+
         if ui.button("Random disruption").clicked() {
             self.apply_random_disruption();
         }
@@ -527,11 +869,10 @@ impl Simulation {
         }
 
         ui.label("Elasticity coefficient");
-        ui.add(
-            DragValue::new(&mut self.solver.ode_mut().elasticity_coefficient)
-                .clamp_range(0.0..=1.0)
-                .speed(0.01),
-        );
+        let elasticity = &mut self.solver.ode_mut().elasticity_coefficient;
+        for (i, axis) in ["x walls", "y walls", "z walls"].into_iter().enumerate() {
+            ui.add(Slider::new(&mut elasticity[i], 0.0..=1.0).text(axis));
+        }
 
         ui.label("Mass connection spring constant");
         ui.add(
@@ -553,73 +894,304 @@ impl Simulation {
                 .clamp_range(0.0..=100.0)
                 .speed(0.05),
         );
+
+        self.wind_ui(ui);
     }
 }
 
-pub struct Jelly {
+/// Full simulation state, the wind modulation function is not stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JellyScene {
+    pub t: f64,
+    /// Positions of all control points followed by their velocities
+    pub y: Vec<f64>,
+    pub control_frame: jelly::ControlFrameTransform,
+    pub point_mass: f64,
+    pub corner_spring_constant: f64,
+    pub inner_spring_constant: f64,
+    pub damping_factor: f64,
+    pub elasticity_coefficient: na::Vector3<f64>,
+    pub wind: na::Vector3<f64>,
+    pub enable_wind: bool,
+}
+
+/// Control point dragged along the plane perpendicular to the ray it was picked with
+struct Grab {
+    point: usize,
+    plane_point: na::Point3<f32>,
+    plane_normal: na::Vector3<f32>,
+}
+
+/// Jelly with an `N`×`N`×`N` control lattice, `D` is the dimension of its state
+pub struct Jelly<const N: usize = { jelly::LATTICE_SIZE }, const D: usize = { jelly::ODE_DIM }> {
     camera: Camera,
+    light: Light,
+    window_size: Cell<Option<PhysicalSize<u32>>>,
+    grab: Option<Grab>,
 
-    bezier_cube: BezierCube,
+    bezier_cube: BezierCube<N>,
     bezier_patches: BezierPatches,
     model: Model,
     room: Room,
     control_frame: ControlFrame,
-    simulation: Simulation,
+    spring_network: SpringNetwork,
+    simulation: Simulation<N, D>,
+    stl_export: PathAction,
+    gltf_export: PathAction,
+    scene_save: PathAction,
+    scene_load: PathAction,
 }
 
-impl Jelly {
-    pub fn new(gl: Arc<glow::Context>) -> Self {
+impl<const N: usize, const D: usize> Jelly<N, D>
+where
+    JellyODE<N>: PlainODE<D>,
+{
+    const PICK_RADIUS: f32 = 0.15;
+
+    pub fn new(gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Self {
         let control_frame_transform = Rc::new(RefCell::new(jelly::ControlFrameTransform::new()));
         let bezier_cube = BezierCube::new(Arc::clone(&gl));
 
         Self {
-            camera: Camera::new(),
+            camera: Camera::new(camera_settings),
+            light: Light::new(
+                na::vector![-2.0, 4.0, -2.0],
+                na::vector![1.0, 1.0, 1.0],
+                na::vector![0.4, 0.4, 0.4],
+            ),
+            window_size: Cell::new(None),
+            grab: None,
 
-            bezier_patches: BezierPatches::new(Arc::clone(&gl), &bezier_cube.cube),
+            bezier_patches: BezierPatches::new(Arc::clone(&gl), &bezier_cube.cubic),
             bezier_cube,
             model: Model::new(Arc::clone(&gl)),
             room: Room::new(Arc::clone(&gl)),
             control_frame: ControlFrame::new(Arc::clone(&gl), Rc::clone(&control_frame_transform)),
+            spring_network: SpringNetwork::new(Arc::clone(&gl)),
             simulation: Simulation::new(control_frame_transform),
+            stl_export: PathAction::new("jelly.stl"),
+            gltf_export: PathAction::new("jelly.gltf"),
+            scene_save: PathAction::new("jelly_scene.json"),
+            scene_load: PathAction::new("jelly_scene.json"),
         }
     }
+
+    /// Restarts the simulation, keeping GL resources, the model and the view settings
+    pub fn reset(&mut self) {
+        self.simulation.reset();
+        self.grab = None;
+
+        *self.control_frame.transform.borrow_mut() = jelly::ControlFrameTransform::new();
+        self.control_frame.recalculate_transform();
+
+        self.simulation
+            .sync_cube(&mut self.bezier_cube, &mut self.bezier_patches);
+        self.spring_network.update(&self.simulation);
+    }
+
+    pub fn to_scene(&self) -> JellyScene {
+        let ode = self.simulation.solver.ode();
+
+        JellyScene {
+            t: self.simulation.state.t,
+            y: self.simulation.state.y.as_slice().to_vec(),
+            control_frame: self.control_frame.transform.borrow().clone(),
+            point_mass: ode.point_mass(),
+            corner_spring_constant: ode.corner_spring_constant,
+            inner_spring_constant: ode.inner_spring_constant,
+            damping_factor: ode.damping_factor,
+            elasticity_coefficient: ode.elasticity_coefficient,
+            wind: ode.wind,
+            enable_wind: ode.enable_wind,
+        }
+    }
+
+    pub fn apply_scene(&mut self, scene: JellyScene) -> Result<(), json::Error> {
+        if scene.y.len() != D {
+            return Err(json::Error::Message(format!(
+                "Expected {} state entries for a {N}×{N}×{N} lattice, got {}",
+                D,
+                scene.y.len()
+            )));
+        }
+
+        *self.control_frame.transform.borrow_mut() = scene.control_frame;
+        self.control_frame.recalculate_transform();
+
+        let simulation = &mut self.simulation;
+        let ode = simulation.solver.ode_mut();
+        ode.set_point_mass(scene.point_mass);
+        ode.corner_spring_constant = scene.corner_spring_constant;
+        ode.inner_spring_constant = scene.inner_spring_constant;
+        ode.damping_factor = scene.damping_factor;
+        ode.elasticity_coefficient = scene.elasticity_coefficient;
+        ode.wind = scene.wind;
+        ode.enable_wind = scene.enable_wind;
+
+        simulation.state.t = scene.t;
+        simulation.state.y.copy_from_slice(&scene.y);
+        simulation.exact_t = scene.t;
+        simulation.held_point = None;
+        simulation.energy_history.clear();
+        simulation.sync_cube(&mut self.bezier_cube, &mut self.bezier_patches);
+        self.grab = None;
+
+        Ok(())
+    }
+
+    pub fn to_state_file(&self, path: &Path) -> Result<(), json::Error> {
+        json::save(path, &self.to_scene())
+    }
+
+    pub fn from_state_file(&mut self, path: &Path) -> Result<(), json::Error> {
+        self.apply_scene(json::load(path)?)
+    }
+
+    fn update_grab(&mut self, mut mouse: MouseState) {
+        let (Some(position), Some(size)) = (mouse.position(), self.window_size.get()) else {
+            return;
+        };
+
+        if !mouse.is_left_button_down() {
+            self.grab = None;
+            self.simulation.held_point = None;
+            return;
+        }
+
+        let (origin, direction) = self.camera.screen_ray(position, size);
+
+        if mouse.has_left_button_been_pressed() {
+            self.grab = self
+                .simulation
+                .pick(&origin, &direction, Self::PICK_RADIUS)
+                .map(|point| Grab {
+                    point,
+                    plane_point: self.simulation.point(point).cast(),
+                    plane_normal: direction,
+                });
+        }
+
+        let Some(grab) = &self.grab else { return };
+
+        let denominator = direction.dot(&grab.plane_normal);
+        if denominator.abs() < f32::EPSILON {
+            return;
+        }
+
+        let distance = (grab.plane_point - origin).dot(&grab.plane_normal) / denominator;
+        let target = origin + direction * distance;
+
+        self.simulation.held_point = Some((grab.point, target.cast()));
+        self.simulation.apply_held_point();
+    }
 }
 
-impl Presenter for Jelly {
+impl<const N: usize, const D: usize> Presenter for Jelly<N, D>
+where
+    JellyODE<N>: PlainODE<D>,
+{
     fn show_side_ui(&mut self, ui: &mut Ui) {
+        ui.collapsing("Camera", |ui| widgets::camera_ui(ui, &mut self.camera));
+        ui.collapsing("Light", |ui| self.light.ui(ui));
+        if ui.button("Fit to scene").clicked() {
+            let (center, radius) = self.bezier_cube.bounding_sphere();
+            let aspect = self
+                .window_size
+                .get()
+                .map_or(1.0, |size| size.width as f32 / size.height as f32);
+            self.camera.frame_bounding_sphere(center, radius, aspect);
+        }
+
         self.bezier_cube.ui(ui);
         self.model.ui(ui);
         self.bezier_patches.ui(ui);
         self.room.ui(ui);
+        if self.spring_network.ui(ui) {
+            self.spring_network.update(&self.simulation);
+        }
         ui.separator();
         self.control_frame.ui(ui);
         ui.separator();
         self.simulation.ui(ui);
+        ui.separator();
+        if let Some(path) = self.stl_export.ui(ui, "Export STL") {
+            let result = export::export_stl(&path, &self.bezier_patches.mesh());
+            self.stl_export.report(result);
+        }
+
+        if let Some(path) = self.gltf_export.ui(ui, "Export glTF") {
+            let mesh = self.bezier_patches.mesh();
+            let result = export::export_gltf(&path, &[(&mesh, na::Matrix4::identity())]);
+            self.gltf_export.report(result);
+        }
+
+        if let Some(path) = self.scene_save.ui(ui, "Save state") {
+            let result = self.to_state_file(&path);
+            self.scene_save.report(result);
+        }
+
+        if let Some(path) = self.scene_load.ui(ui, "Load state") {
+            let result = self.from_state_file(&path);
+            self.scene_load.report(result);
+        }
     }
 
     fn show_bottom_ui(&mut self, ui: &mut Ui) {
-        ui.label("Bottom text");
+        ui.label("Deformation energy");
+        Plot::new("Deformation energy graph")
+            .view_aspect(10.0)
+            .auto_bounds_x()
+            .auto_bounds_y()
+            .show(ui, |plot_ui| {
+                plot_ui.line(
+                    Line::new(
+                        self.simulation
+                            .energy_history
+                            .iter()
+                            .copied()
+                            .collect::<Vec<_>>(),
+                    )
+                    .name("Deformation energy"),
+                )
+            });
     }
 
     fn draw(&self, size: Option<egui_winit::winit::dpi::PhysicalSize<u32>>) {
         let Some(size) = size else { return };
         let aspect_ratio = size.width as f32 / size.height as f32;
+        self.window_size.set(Some(size));
 
         self.bezier_cube.draw(aspect_ratio, &self.camera);
-        self.model
-            .draw(aspect_ratio, &self.camera, &self.bezier_cube.flat_cube);
-        self.bezier_patches.draw(aspect_ratio, &self.camera);
+        self.model.draw(
+            aspect_ratio,
+            &self.camera,
+            &self.bezier_cube.flat_cube,
+            &self.light,
+        );
+        self.bezier_patches
+            .draw(aspect_ratio, &self.camera, &self.light);
         self.control_frame.draw(aspect_ratio, &self.camera);
-        self.room.draw(aspect_ratio, &self.camera);
+        self.spring_network.draw(aspect_ratio, &self.camera);
+        self.room.draw(aspect_ratio, &self.camera, &self.light);
     }
 
     fn update(&mut self, delta: std::time::Duration) {
         self.simulation
             .update(&mut self.bezier_cube, &mut self.bezier_patches, delta);
+        self.spring_network.update(&self.simulation);
     }
 
     fn update_mouse(&mut self, state: MouseState) {
         self.camera.update_from_mouse(state);
+        self.update_grab(state);
+    }
+
+    fn update_keyboard(&mut self, state: KeyboardState, delta: std::time::Duration) {
+        self.camera.update_from_keyboard(&state, delta);
+    }
+
+    fn step_count(&self) -> Option<usize> {
+        Some(self.simulation.step_count)
     }
 
     fn name(&self) -> &'static str {
@@ -627,8 +1199,10 @@ impl Presenter for Jelly {
     }
 }
 
-#[derive(Default)]
-pub struct JellyBuilder {}
+pub struct JellyBuilder {
+    /// Number of control points along each edge of the lattice
+    lattice_size: usize,
+}
 
 impl JellyBuilder {
     pub fn new() -> Self {
@@ -636,12 +1210,89 @@ impl JellyBuilder {
     }
 }
 
+impl Default for JellyBuilder {
+    fn default() -> Self {
+        Self {
+            lattice_size: jelly::LATTICE_SIZE,
+        }
+    }
+}
+
+impl JellyBuilder {
+    /// Resets `presenter` in place if it is a jelly with an `N`×`N`×`N` lattice
+    fn rebuild_lattice<const N: usize, const D: usize>(
+        presenter: &mut Box<dyn Presenter>,
+        gl: Arc<glow::Context>,
+        camera_settings: CameraSettings,
+    ) where
+        JellyODE<N>: PlainODE<D>,
+    {
+        match (presenter.as_mut() as &mut dyn Any).downcast_mut::<Jelly<N, D>>() {
+            Some(jelly) => jelly.reset(),
+            None => *presenter = Box::new(Jelly::<N, D>::new(gl, camera_settings)),
+        }
+    }
+}
+
 impl PresenterBuilder for JellyBuilder {
     fn build_ui(&mut self, ui: &mut Ui) -> egui::Response {
-        ui.label("")
+        ui.horizontal(|ui| {
+            ui.label("Lattice size");
+            ui.add(DragValue::new(&mut self.lattice_size).clamp_range(2..=6))
+        })
+        .inner
+    }
+
+    fn build(&self, gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Box<dyn Presenter> {
+        match self.lattice_size {
+            2 => Box::new(Jelly::<2, { jelly::ode_dim(2) }>::new(gl, camera_settings)),
+            3 => Box::new(Jelly::<3, { jelly::ode_dim(3) }>::new(gl, camera_settings)),
+            5 => Box::new(Jelly::<5, { jelly::ode_dim(5) }>::new(gl, camera_settings)),
+            6 => Box::new(Jelly::<6, { jelly::ode_dim(6) }>::new(gl, camera_settings)),
+            _ => Box::new(Jelly::<4, { jelly::ode_dim(4) }>::new(gl, camera_settings)),
+        }
+    }
+
+    fn rebuild(
+        &self,
+        presenter: &mut Box<dyn Presenter>,
+        gl: Arc<glow::Context>,
+        camera_settings: CameraSettings,
+    ) {
+        match self.lattice_size {
+            2 => Self::rebuild_lattice::<2, { jelly::ode_dim(2) }>(presenter, gl, camera_settings),
+            3 => Self::rebuild_lattice::<3, { jelly::ode_dim(3) }>(presenter, gl, camera_settings),
+            5 => Self::rebuild_lattice::<5, { jelly::ode_dim(5) }>(presenter, gl, camera_settings),
+            6 => Self::rebuild_lattice::<6, { jelly::ode_dim(6) }>(presenter, gl, camera_settings),
+            _ => Self::rebuild_lattice::<4, { jelly::ode_dim(4) }>(presenter, gl, camera_settings),
+        }
     }
+}
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
-        Box::new(Jelly::new(gl))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_round_trip() {
+        let mut state = JellyODE::default_state();
+        state.y[7] = -0.3125;
+        state.y[jelly::SPACE_DIM + 2] = 12.5;
+
+        json::assert_round_trip(&JellyScene {
+            t: 3.75,
+            y: state.y.as_slice().to_vec(),
+            control_frame: jelly::ControlFrameTransform {
+                translation: na::vector![0.5, -1.0, 2.0],
+                rotation: na::Quaternion::new(0.9, 0.1, -0.3, 0.2),
+            },
+            point_mass: 0.2,
+            corner_spring_constant: 15.0,
+            inner_spring_constant: 8.5,
+            damping_factor: 0.1,
+            elasticity_coefficient: na::vector![0.5, 0.75, 1.0],
+            wind: na::vector![1.0, 0.0, -0.5],
+            enable_wind: true,
+        });
     }
 }
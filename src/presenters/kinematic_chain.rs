@@ -1,6 +1,7 @@
 use super::{Presenter, PresenterBuilder};
-use crate::controls::mouse::MouseState;
-use crate::numerics::{kinematics::flat_chain, Rect};
+use crate::controls::{camera::CameraSettings, keyboard::KeyboardState, mouse::MouseState};
+use crate::json;
+use crate::numerics::{kinematics::flat_chain, Circle, Polygon, Rect};
 use crate::render::{
     gl_drawable::GlDrawable,
     gl_mesh::{GlLines, GlTriangleMesh},
@@ -9,33 +10,68 @@ use crate::render::{
     models,
 };
 use crate::simulators::kinematic_chain::*;
+use crate::ui::widgets::PathAction;
 use egui::{widgets::DragValue, Ui};
-use egui_winit::winit::dpi::PhysicalSize;
+use egui_winit::winit::{
+    dpi::PhysicalSize,
+    event::{MouseButton, VirtualKeyCode},
+};
 use glow::HasContext;
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ObstacleKind {
+    Rect,
+    Circle,
+    Polygon,
+}
+
 #[derive(Debug)]
-enum DrawingRectState {
-    Drawing(Rect),
+enum DrawingState {
+    Rect(Rect),
+    Circle(Circle),
+    Polygon(Vec<na::Point2<f64>>),
     NotDrawing,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainScene {
+    pub l_1: f64,
+    pub l_2: f64,
+    pub start: na::Point2<f64>,
+    pub end: na::Point2<f64>,
+    pub obstacles: Vec<Obstacle>,
+}
+
 pub struct KinematicChain {
     rect_program: GlProgram,
     texture_program: GlProgram,
     rect_mesh: GlTriangleMesh,
+    outline_mesh: GlLines,
 
-    drawing_rect: DrawingRectState,
-    rects: Vec<Rect>,
+    obstacle_kind: ObstacleKind,
+    drawing: DrawingState,
+    obstacles: Vec<Obstacle>,
+    undone_obstacles: Vec<Obstacle>,
 
     config_state_start: flat_chain::ReverseSolutions,
     start: na::Point2<f64>,
+    /// Whether the current left button drag moves the start, drags beginning on an obstacle
+    /// do not, so that double-clicking an obstacle only deletes it
+    moving_start: bool,
     start_arm_mesh: GlLines,
 
     current_arm_mesh: GlLines,
     current_path: Option<Vec<na::Point2<f64>>>,
 
+    both_branches: bool,
+    second_arm_mesh: GlLines,
+    second_path: Option<Vec<na::Point2<f64>>>,
+    second_map: BFSMap,
+    arm_program: GlProgram,
+
     config_state_end: flat_chain::ReverseSolutions,
     end: na::Point2<f64>,
     end_arm_mesh: GlLines,
@@ -43,6 +79,10 @@ pub struct KinematicChain {
     config_obstruction: ConfigObstuction,
     texture: GlTexture,
     map: BFSMap,
+    /// Set when the start or the obstruction changed since the BFS maps were computed
+    map_dirty: bool,
+    clearance: ClearanceMap,
+    clearance_weight: f64,
     system: flat_chain::System,
 
     start_with_second: bool,
@@ -51,16 +91,36 @@ pub struct KinematicChain {
     simulation_speed: f64,
     animation_progress: f64,
 
+    use_astar: bool,
+    heuristic: Heuristic,
+    smooth: bool,
+
+    map_export: PathAction,
+    scene_save: PathAction,
+    scene_load: PathAction,
+
     gl: Arc<glow::Context>,
 }
 
 impl KinematicChain {
     const ARM_ORIGIN: na::Point2<f64> = na::point![1000.0, 500.0];
+    const MAX_REDO: usize = 32;
+    const CIRCLE_SEGMENTS: usize = 64;
+    const FIRST_BRANCH_COLOR: [f32; 4] = [1.0, 0.6, 0.2, 1.0];
+    const SECOND_BRANCH_COLOR: [f32; 4] = [0.2, 0.8, 1.0, 1.0];
+    /// Animation step of smoothed paths, the same as a single grid cell
+    const SMOOTH_PATH_STEP: f64 = std::f64::consts::TAU / CONFIG_SIZE as f64;
 
     fn new(gl: Arc<glow::Context>) -> Self {
         let system = flat_chain::System::new(100.0, 100.0);
         let config_obstuction = ConfigObstuction::new(system, Self::ARM_ORIGIN);
-        let map = BFSMap::from_obstructions(&Some(na::point![0.0, 0.0]), &config_obstuction);
+        let clearance = ClearanceMap::from_obstructions(&config_obstuction);
+        let map = BFSMap::from_obstructions(
+            &Some(na::point![0.0, 0.0]),
+            &config_obstuction,
+            &clearance,
+            0.0,
+        );
         let texture = config_obstuction.texture(&map, None);
 
         let mut me = Self {
@@ -71,17 +131,27 @@ impl KinematicChain {
                 "texture_frag",
             ),
             rect_mesh: GlTriangleMesh::new(Arc::clone(&gl), &models::rect()),
+            outline_mesh: GlLines::new(Arc::clone(&gl), &[]),
 
-            drawing_rect: DrawingRectState::NotDrawing,
-            rects: Vec::new(),
+            obstacle_kind: ObstacleKind::Rect,
+            drawing: DrawingState::NotDrawing,
+            obstacles: Vec::new(),
+            undone_obstacles: Vec::new(),
 
             config_state_start: flat_chain::ReverseSolutions::One(na::Point2::origin()),
             start: Self::ARM_ORIGIN + na::vector![200.0, 0.0],
+            moving_start: false,
             start_arm_mesh: GlLines::new(Arc::clone(&gl), &[na::Point::origin(); 8]),
 
             current_path: None,
             current_arm_mesh: GlLines::new(Arc::clone(&gl), &[na::Point::origin(); 4]),
 
+            both_branches: false,
+            second_arm_mesh: GlLines::new(Arc::clone(&gl), &[na::Point::origin(); 4]),
+            second_path: None,
+            second_map: BFSMap::empty(),
+            arm_program: GlProgram::vertex_fragment(Arc::clone(&gl), "2d_vert", "color_frag"),
+
             config_state_end: flat_chain::ReverseSolutions::One(na::Point2::origin()),
             end: Self::ARM_ORIGIN + na::vector![200.0, 0.0],
             end_arm_mesh: GlLines::new(Arc::clone(&gl), &[na::Point::origin(); 8]),
@@ -89,6 +159,9 @@ impl KinematicChain {
             config_obstruction: config_obstuction,
             texture: GlTexture::new(Arc::clone(&gl), &texture),
             map,
+            map_dirty: false,
+            clearance,
+            clearance_weight: 0.0,
             system,
 
             start_with_second: false,
@@ -97,6 +170,14 @@ impl KinematicChain {
             simulation_speed: 100.0,
             animation_progress: 0.0,
 
+            use_astar: false,
+            heuristic: Heuristic::Euclidean,
+            smooth: false,
+
+            map_export: PathAction::new("config_map.png"),
+            scene_save: PathAction::new("chain_scene.json"),
+            scene_load: PathAction::new("chain_scene.json"),
+
             gl,
         };
 
@@ -105,6 +186,33 @@ impl KinematicChain {
         me
     }
 
+    pub fn from_scene(gl: Arc<glow::Context>, scene: ChainScene) -> Self {
+        let mut me = Self::new(gl);
+        me.apply_scene(scene);
+        me
+    }
+
+    pub fn to_scene(&self) -> ChainScene {
+        ChainScene {
+            l_1: self.system.l_1,
+            l_2: self.system.l_2,
+            start: self.start,
+            end: self.end,
+            obstacles: self.obstacles.clone(),
+        }
+    }
+
+    pub fn apply_scene(&mut self, scene: ChainScene) {
+        self.system = flat_chain::System::new(scene.l_1, scene.l_2);
+        self.start = scene.start;
+        self.end = scene.end;
+        self.obstacles = scene.obstacles;
+        self.undone_obstacles.clear();
+        self.drawing = DrawingState::NotDrawing;
+
+        self.reset_obstruction();
+    }
+
     fn reset_all(&mut self) {
         self.config_state_start = self.config_obstruction.correct_solution(
             &self
@@ -117,7 +225,13 @@ impl KinematicChain {
                 .inverse_kinematics(&(self.end - Self::ARM_ORIGIN).into()),
         );
 
-        self.update_map();
+        if self.map_dirty {
+            self.update_map();
+        } else {
+            self.update_path();
+            self.update_obstruction_texture();
+        }
+
         self.update_arm_mesh();
     }
 
@@ -178,14 +292,116 @@ impl KinematicChain {
         ]
     }
 
+    fn undo_obstacle(&mut self) {
+        let Some(obstacle) = self.obstacles.pop() else {
+            return;
+        };
+
+        if self.undone_obstacles.len() == Self::MAX_REDO {
+            self.undone_obstacles.remove(0);
+        }
+
+        self.undone_obstacles.push(obstacle);
+        self.reset_obstruction();
+    }
+
+    fn redo_obstacle(&mut self) {
+        let Some(obstacle) = self.undone_obstacles.pop() else {
+            return;
+        };
+
+        self.obstacles.push(obstacle);
+        self.reset_obstruction();
+    }
+
+    /// Removes the most recently added obstacle containing `point`
+    fn remove_obstacle_at(&mut self, point: &na::Point2<f64>) {
+        let Some(index) = self
+            .obstacles
+            .iter()
+            .rposition(|obstacle| obstacle.contains_point(point))
+        else {
+            return;
+        };
+
+        self.obstacles.remove(index);
+        self.reset_obstruction();
+    }
+
+    fn push_obstacle(&mut self, obstacle: Obstacle) {
+        self.config_obstruction.add_obstacle(&obstacle);
+        self.clearance = ClearanceMap::from_obstructions(&self.config_obstruction);
+        self.obstacles.push(obstacle);
+        self.undone_obstacles.clear();
+        self.update_outline_mesh();
+        self.map_dirty = true;
+        self.reset_all();
+    }
+
     fn reset_obstruction(&mut self) {
-        self.config_obstruction = ConfigObstuction::new(self.system, Self::ARM_ORIGIN);
+        self.config_obstruction.reset(self.system);
 
-        for rect in &self.rects {
-            self.config_obstruction.add_rect(rect);
+        for obstacle in &self.obstacles {
+            self.config_obstruction.add_obstacle(obstacle);
         }
 
-        self.update_map();
+        self.clearance = ClearanceMap::from_obstructions(&self.config_obstruction);
+
+        self.update_outline_mesh();
+        self.map_dirty = true;
+        self.reset_all();
+    }
+
+    fn to_screen(point: &na::Point2<f64>) -> na::Point3<f32> {
+        na::point![point.x as f32, point.y as f32, 0.0]
+    }
+
+    fn circle_outline(circle: &Circle) -> Vec<na::Point3<f32>> {
+        let point = |i: usize| {
+            let angle = i as f64 / Self::CIRCLE_SEGMENTS as f64 * std::f64::consts::TAU;
+            Self::to_screen(
+                &(circle.center + circle.radius * na::vector![angle.cos(), angle.sin()]),
+            )
+        };
+
+        (0..Self::CIRCLE_SEGMENTS)
+            .flat_map(|i| [point(i), point(i + 1)])
+            .collect()
+    }
+
+    fn polyline_outline(vertices: &[na::Point2<f64>], closed: bool) -> Vec<na::Point3<f32>> {
+        let mut points: Vec<_> = vertices
+            .windows(2)
+            .flat_map(|edge| [Self::to_screen(&edge[0]), Self::to_screen(&edge[1])])
+            .collect();
+
+        if let (true, Some(first), Some(last)) = (closed, vertices.first(), vertices.last()) {
+            points.extend([Self::to_screen(last), Self::to_screen(first)]);
+        }
+
+        points
+    }
+
+    fn update_outline_mesh(&mut self) {
+        let mut points: Vec<_> = self
+            .obstacles
+            .iter()
+            .flat_map(|obstacle| match obstacle {
+                Obstacle::Rect(_) => Vec::new(),
+                Obstacle::Circle(circle) => Self::circle_outline(circle),
+                Obstacle::Polygon(polygon) => Self::polyline_outline(&polygon.vertices, true),
+            })
+            .collect();
+
+        match &self.drawing {
+            DrawingState::Circle(circle) => points.extend(Self::circle_outline(circle)),
+            DrawingState::Polygon(vertices) => {
+                points.extend(Self::polyline_outline(vertices, false))
+            }
+            DrawingState::Rect(_) | DrawingState::NotDrawing => {}
+        }
+
+        self.outline_mesh.update_points(&points);
     }
 
     fn draw_arm(&self, size: Option<PhysicalSize<u32>>) {
@@ -204,16 +420,30 @@ impl KinematicChain {
             flat_chain::ReverseSolutions::None | flat_chain::ReverseSolutions::InfinitelyMany => {}
         }
 
-        if self.current_path.is_some() {
-            self.current_arm_mesh.draw();
-        }
-
         match self.config_state_end {
             flat_chain::ReverseSolutions::Two(_, _) | flat_chain::ReverseSolutions::One(_) => {
                 self.end_arm_mesh.draw();
             }
             flat_chain::ReverseSolutions::None | flat_chain::ReverseSolutions::InfinitelyMany => {}
         }
+
+        self.arm_program.enable();
+        self.arm_program
+            .uniform_matrix_4_f32_slice("view_transform", Self::view_matrix(size).as_slice());
+        self.arm_program
+            .uniform_matrix_4_f32_slice("model_transform", na::Matrix4::identity().as_slice());
+
+        if self.current_path.is_some() {
+            let [r, g, b, a] = Self::FIRST_BRANCH_COLOR;
+            self.arm_program.uniform_4_f32("color", r, g, b, a);
+            self.current_arm_mesh.draw();
+        }
+
+        if self.second_path.is_some() {
+            let [r, g, b, a] = Self::SECOND_BRANCH_COLOR;
+            self.arm_program.uniform_4_f32("color", r, g, b, a);
+            self.second_arm_mesh.draw();
+        }
     }
 
     fn draw_texture(&self, size: Option<PhysicalSize<u32>>) {
@@ -241,7 +471,7 @@ impl KinematicChain {
         self.rect_mesh.draw();
     }
 
-    fn draw_rects(&self, size: Option<PhysicalSize<u32>>) {
+    fn draw_obstacles(&self, size: Option<PhysicalSize<u32>>) {
         let Some(size) = size else { return };
 
         self.rect_program.enable();
@@ -250,15 +480,21 @@ impl KinematicChain {
 
         unsafe { self.gl.disable(glow::CULL_FACE) };
 
-        for rect in &self.rects {
-            self.draw_rect(rect);
+        for obstacle in &self.obstacles {
+            if let Obstacle::Rect(rect) = obstacle {
+                self.draw_rect(rect);
+            }
         }
 
-        if let DrawingRectState::Drawing(rect) = self.drawing_rect {
-            self.draw_rect(&rect);
+        if let DrawingState::Rect(rect) = &self.drawing {
+            self.draw_rect(rect);
         }
 
         unsafe { self.gl.enable(glow::CULL_FACE) };
+
+        self.rect_program
+            .uniform_matrix_4_f32_slice("model_transform", na::Matrix4::identity().as_slice());
+        self.outline_mesh.draw();
     }
 
     fn draw_rect(&self, rect: &Rect) {
@@ -275,19 +511,43 @@ impl KinematicChain {
         self.rect_mesh.draw();
     }
 
-    fn update_map(&mut self) {
-        let start = match self.config_state_start {
+    fn chosen_solution(
+        solutions: &flat_chain::ReverseSolutions,
+        with_second: bool,
+    ) -> Option<na::Point2<f64>> {
+        match *solutions {
             flat_chain::ReverseSolutions::InfinitelyMany => None,
-            flat_chain::ReverseSolutions::Two(first, second) => Some(if self.start_with_second {
-                second
-            } else {
-                first
-            }),
+            flat_chain::ReverseSolutions::Two(first, second) => {
+                Some(if with_second { second } else { first })
+            }
             flat_chain::ReverseSolutions::One(sol) => Some(sol),
             flat_chain::ReverseSolutions::None => None,
-        };
+        }
+    }
 
-        self.map = BFSMap::from_obstructions(&start, &self.config_obstruction);
+    fn update_map(&mut self) {
+        let start = Self::chosen_solution(&self.config_state_start, self.start_with_second);
+
+        self.map = BFSMap::from_obstructions(
+            &start,
+            &self.config_obstruction,
+            &self.clearance,
+            self.clearance_weight,
+        );
+
+        self.second_map = if self.both_branches {
+            let second_start =
+                Self::chosen_solution(&self.config_state_start, !self.start_with_second);
+            BFSMap::from_obstructions(
+                &second_start,
+                &self.config_obstruction,
+                &self.clearance,
+                self.clearance_weight,
+            )
+        } else {
+            BFSMap::empty()
+        };
+        self.map_dirty = false;
 
         self.update_path();
         self.update_obstruction_texture();
@@ -295,76 +555,209 @@ impl KinematicChain {
 
     fn update_path(&mut self) {
         self.animation_progress = 0.0;
-        self.current_path = match self.config_state_end {
-            flat_chain::ReverseSolutions::InfinitelyMany => None,
-            flat_chain::ReverseSolutions::Two(t_1, t_2) => self
-                .map
-                .path_to(if self.end_with_second { &t_2 } else { &t_1 }),
-            flat_chain::ReverseSolutions::One(target) => self.map.path_to(&target),
-            flat_chain::ReverseSolutions::None => None,
+
+        self.current_path = self.find_path(&self.map, self.start_with_second, self.end_with_second);
+        self.second_path = if self.both_branches {
+            self.find_path(
+                &self.second_map,
+                !self.start_with_second,
+                !self.end_with_second,
+            )
+        } else {
+            None
+        };
+    }
+
+    /// `map` has to be computed from the start solution chosen with `start_with_second`
+    fn find_path(
+        &self,
+        map: &BFSMap,
+        start_with_second: bool,
+        end_with_second: bool,
+    ) -> Option<Vec<na::Point2<f64>>> {
+        let target = Self::chosen_solution(&self.config_state_end, end_with_second);
+
+        let path = match (self.use_astar, target) {
+            (_, None) => None,
+            (false, Some(target)) => map.path_to(&target),
+            (true, Some(target)) => {
+                Self::chosen_solution(&self.config_state_start, start_with_second).and_then(
+                    |start| {
+                        astar_path(
+                            &start,
+                            &target,
+                            &self.config_obstruction,
+                            self.heuristic,
+                            &self.clearance,
+                            self.clearance_weight,
+                        )
+                    },
+                )
+            }
+        };
+
+        if self.smooth {
+            path.map(|path| {
+                resample_path(
+                    &smooth_path(&path, &self.config_obstruction),
+                    Self::SMOOTH_PATH_STEP,
+                )
+            })
+        } else {
+            path
         }
     }
 
-    fn handle_rect_setting(&mut self, state: &MouseState) {
+    fn handle_obstacle_setting(&mut self, state: &mut MouseState) {
+        if self.obstacle_kind == ObstacleKind::Polygon {
+            self.handle_polygon_setting(state);
+            return;
+        }
+
         if state.is_middle_button_down() {
             if let Some(position) = state.position() {
                 let current_point = na::point![position.x, position.y];
-                self.drawing_rect = match self.drawing_rect {
-                    DrawingRectState::Drawing(Rect { p_1, .. }) => {
-                        DrawingRectState::Drawing(Rect {
-                            p_1,
-                            p_2: current_point,
-                        })
+                self.drawing = match (&self.drawing, self.obstacle_kind) {
+                    (DrawingState::Rect(Rect { p_1, .. }), _) => DrawingState::Rect(Rect {
+                        p_1: *p_1,
+                        p_2: current_point,
+                    }),
+                    (DrawingState::Circle(circle), _) => DrawingState::Circle(Circle::new(
+                        circle.center,
+                        na::distance(&circle.center, &current_point),
+                    )),
+                    (_, ObstacleKind::Circle) => {
+                        DrawingState::Circle(Circle::new(current_point, 0.0))
                     }
-                    DrawingRectState::NotDrawing => DrawingRectState::Drawing(Rect {
+                    (_, _) => DrawingState::Rect(Rect {
                         p_1: current_point,
                         p_2: current_point,
                     }),
                 };
+
+                if let DrawingState::Circle(_) = self.drawing {
+                    self.update_outline_mesh();
+                }
             }
         } else {
-            if let DrawingRectState::Drawing(rect) = &self.drawing_rect {
-                self.config_obstruction.add_rect(rect);
-                self.rects.push(*rect);
-                self.drawing_rect = DrawingRectState::NotDrawing;
-                self.reset_all();
+            match std::mem::replace(&mut self.drawing, DrawingState::NotDrawing) {
+                DrawingState::Rect(rect) => self.push_obstacle(Obstacle::Rect(rect)),
+                DrawingState::Circle(circle) => self.push_obstacle(Obstacle::Circle(circle)),
+                DrawingState::Polygon(_) | DrawingState::NotDrawing => {}
             }
         }
     }
 
-    fn handle_target_setting(&mut self, state: &MouseState) {
+    fn handle_polygon_setting(&mut self, state: &mut MouseState) {
+        if !state.has_middle_button_been_pressed() {
+            return;
+        }
+
+        let Some(position) = state.position() else {
+            return;
+        };
+
+        let vertex = na::point![position.x, position.y];
+        match &mut self.drawing {
+            DrawingState::Polygon(vertices) => vertices.push(vertex),
+            _ => self.drawing = DrawingState::Polygon(vec![vertex]),
+        }
+
+        self.update_outline_mesh();
+    }
+
+    fn close_polygon(&mut self) {
+        if let DrawingState::Polygon(vertices) =
+            std::mem::replace(&mut self.drawing, DrawingState::NotDrawing)
+        {
+            self.push_obstacle(Obstacle::Polygon(Polygon::new(vertices)));
+        }
+    }
+
+    fn drawn_polygon_vertices(&self) -> usize {
+        match &self.drawing {
+            DrawingState::Polygon(vertices) => vertices.len(),
+            _ => 0,
+        }
+    }
+
+    fn obstacle_ui(ui: &mut Ui, obstacle: &mut Obstacle) -> bool {
+        let coordinate = |ui: &mut Ui, value: &mut f64| {
+            ui.add(DragValue::new(value).speed(1.0).clamp_range(0.0..=2000.0))
+                .changed()
+        };
+
+        let mut change = false;
+        ui.horizontal(|ui| match obstacle {
+            Obstacle::Rect(rect) => {
+                change |= coordinate(ui, &mut rect.p_1.x);
+                ui.label("x");
+                change |= coordinate(ui, &mut rect.p_1.y);
+                ui.label(",");
+                change |= coordinate(ui, &mut rect.p_2.x);
+                ui.label("x");
+                change |= coordinate(ui, &mut rect.p_2.y);
+            }
+            Obstacle::Circle(circle) => {
+                change |= coordinate(ui, &mut circle.center.x);
+                ui.label("x");
+                change |= coordinate(ui, &mut circle.center.y);
+                ui.label("r");
+                change |= coordinate(ui, &mut circle.radius);
+            }
+            Obstacle::Polygon(polygon) => {
+                ui.label(format!("Polygon, {} vertices", polygon.vertices.len()));
+            }
+        });
+
+        change
+    }
+
+    fn handle_target_setting(&mut self, state: &mut MouseState) {
         let Some(position) = state.position() else {
             return;
         };
+        let point = na::point![position.x, position.y];
+
+        if state.has_left_button_been_pressed() {
+            self.moving_start = !self
+                .obstacles
+                .iter()
+                .any(|obstacle| obstacle.contains_point(&point));
+        }
 
-        if state.is_left_button_down() {
-            self.start = na::point![position.x, position.y];
-            self.config_state_start = self.config_obstruction.correct_solution(
-                &self
-                    .system
-                    .inverse_kinematics(&(self.start - Self::ARM_ORIGIN).into()),
-            );
+        if state.is_left_button_down() && self.moving_start {
+            self.start = point;
+            self.map_dirty = true;
             self.reset_all();
         }
 
+        // Only the paths depend on the end, the maps stay valid
         if state.is_right_button_down() {
-            self.end = na::point![position.x, position.y];
-            self.config_state_end = self.config_obstruction.correct_solution(
-                &self
-                    .system
-                    .inverse_kinematics(&(self.end - Self::ARM_ORIGIN).into()),
-            );
+            self.end = point;
             self.reset_all();
         }
     }
 
+    /// Paths of different lengths are animated together, the shorter one waits at its end
     fn update_current_mesh(&mut self, frame: usize) {
-        let Some(path) = &self.current_path else {
-            return;
-        };
+        if let Some(path) = &self.current_path {
+            self.current_arm_mesh
+                .update_points(&self.state_to_points(&path[frame.min(path.len() - 1)]));
+        }
 
-        self.current_arm_mesh
-            .update_points(&self.state_to_points(&path[frame]));
+        if let Some(path) = &self.second_path {
+            self.second_arm_mesh
+                .update_points(&self.state_to_points(&path[frame.min(path.len() - 1)]));
+        }
+    }
+
+    fn animation_length(&self) -> Option<usize> {
+        self.current_path
+            .iter()
+            .chain(&self.second_path)
+            .map(Vec::len)
+            .max()
     }
 }
 
@@ -378,7 +771,7 @@ impl Presenter for KinematicChain {
 
         let mut reset = false;
 
-        reset |= ui
+        if ui
             .add_enabled(
                 matches!(
                     self.config_state_start,
@@ -386,7 +779,11 @@ impl Presenter for KinematicChain {
                 ),
                 egui::Checkbox::new(&mut self.start_with_second, "Start with second solution"),
             )
-            .changed();
+            .changed()
+        {
+            self.map_dirty = true;
+            reset = true;
+        }
 
         reset |= ui
             .add_enabled(
@@ -409,55 +806,118 @@ impl Presenter for KinematicChain {
         .changed()
         {
             self.reset_obstruction();
+        }
 
-            self.config_state_start = self
-                .system
-                .inverse_kinematics(&(self.start - Self::ARM_ORIGIN).into());
-            self.config_state_end = self
-                .system
-                .inverse_kinematics(&(self.end - Self::ARM_ORIGIN).into());
+        let mut path_changed = ui
+            .checkbox(&mut self.use_astar, "Find path with A*")
+            .changed();
+        ui.add_enabled_ui(self.use_astar, |ui| {
+            egui::ComboBox::from_label("Heuristic")
+                .selected_text(format!("{:?}", self.heuristic))
+                .show_ui(ui, |ui| {
+                    for heuristic in [Heuristic::Zero, Heuristic::Euclidean, Heuristic::Octile] {
+                        path_changed |= ui
+                            .selectable_value(
+                                &mut self.heuristic,
+                                heuristic,
+                                format!("{:?}", heuristic),
+                            )
+                            .changed();
+                    }
+                });
+        });
 
-            self.reset_all();
+        path_changed |= ui.checkbox(&mut self.smooth, "Smooth path").changed();
+
+        if path_changed {
+            self.update_path();
+            self.update_obstruction_texture();
+        }
+
+        if ui
+            .checkbox(&mut self.both_branches, "Animate both solution branches")
+            .changed()
+        {
+            self.update_map();
+        }
+
+        if ui
+            .add(egui::Slider::new(&mut self.clearance_weight, 0.0..=50.0).text("Clearance weight"))
+            .changed()
+        {
+            self.update_map();
+        }
+
+        if let Some(path) = self.map_export.ui(ui, "Save config map") {
+            let texture = self
+                .config_obstruction
+                .texture(&self.map, self.current_path.as_deref());
+            self.map_export.report(texture.save(&path));
         }
 
-        ui.label("Rects");
+        if let Some(path) = self.scene_save.ui(ui, "Save scene") {
+            self.scene_save.report(json::save(&path, &self.to_scene()));
+        }
+
+        if let Some(path) = self.scene_load.ui(ui, "Load scene") {
+            let result = json::load(&path).map(|scene| self.apply_scene(scene));
+            self.scene_load.report(result);
+        }
+
+        ui.label("Double-click an obstacle to delete it");
+        ui.horizontal(|ui| {
+            ui.label("Obstacle (middle click)");
+            for (kind, text) in [
+                (ObstacleKind::Rect, "Rectangle"),
+                (ObstacleKind::Circle, "Circle"),
+                (ObstacleKind::Polygon, "Polygon"),
+            ] {
+                if ui
+                    .selectable_value(&mut self.obstacle_kind, kind, text)
+                    .changed()
+                {
+                    self.drawing = DrawingState::NotDrawing;
+                    self.update_outline_mesh();
+                }
+            }
+        });
+
+        if self.obstacle_kind == ObstacleKind::Polygon
+            && ui
+                .add_enabled(
+                    self.drawn_polygon_vertices() >= 3,
+                    egui::Button::new("Close polygon"),
+                )
+                .clicked()
+        {
+            self.close_polygon();
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !self.obstacles.is_empty(),
+                    egui::Button::new("Undo last obstacle"),
+                )
+                .clicked()
+            {
+                self.undo_obstacle();
+            }
+
+            if ui
+                .add_enabled(!self.undone_obstacles.is_empty(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                self.redo_obstacle();
+            }
+        });
+
+        ui.label("Obstacles");
         egui::ScrollArea::vertical().show(ui, |ui| {
             let mut change = false;
-            self.rects.retain_mut(|rect| {
+            self.obstacles.retain_mut(|obstacle| {
                 ui.horizontal(|ui| {
-                    ui.horizontal(|ui| {
-                        change |= ui
-                            .add(
-                                DragValue::new(&mut rect.p_1.x)
-                                    .speed(1.0)
-                                    .clamp_range(0.0..=2000.0),
-                            )
-                            .changed();
-                        ui.label("x");
-                        change |= ui
-                            .add(
-                                DragValue::new(&mut rect.p_1.y)
-                                    .speed(1.0)
-                                    .clamp_range(0.0..=2000.0),
-                            )
-                            .changed();
-                        ui.label(",");
-                        change |= ui
-                            .add(
-                                DragValue::new(&mut rect.p_2.x)
-                                    .speed(1.0)
-                                    .clamp_range(0.0..=2000.0),
-                            )
-                            .changed();
-                        ui.label("x");
-                        change |= ui
-                            .add(
-                                DragValue::new(&mut rect.p_2.y)
-                                    .speed(1.0)
-                                    .clamp_range(0.0..=2000.0),
-                            )
-                            .changed();
-                    });
+                    change |= Self::obstacle_ui(ui, obstacle);
 
                     let stays = !ui.button("X").clicked();
                     change |= !stays;
@@ -477,29 +937,52 @@ impl Presenter for KinematicChain {
     }
 
     fn draw(&self, size: Option<egui_winit::winit::dpi::PhysicalSize<u32>>) {
-        self.draw_rects(size);
+        self.draw_obstacles(size);
         self.draw_arm(size);
         self.draw_texture(size);
     }
 
     fn update(&mut self, delta: std::time::Duration) {
-        let Some(path) = &self.current_path else {
+        let Some(length) = self.animation_length() else {
             return;
         };
 
         let animation_progress_old = self.animation_progress;
         self.animation_progress = (self.animation_progress
             + delta.as_secs_f64() * self.simulation_speed)
-            .rem_euclid(path.len() as f64);
+            .rem_euclid(length as f64);
 
         if animation_progress_old.floor() != self.animation_progress.floor() {
             self.update_current_mesh(self.animation_progress.floor() as usize);
         }
     }
 
-    fn update_mouse(&mut self, state: MouseState) {
-        self.handle_rect_setting(&state);
-        self.handle_target_setting(&state);
+    fn update_mouse(&mut self, mut state: MouseState) {
+        self.handle_obstacle_setting(&mut state);
+
+        if let Some(position) = state.position() {
+            if state.double_clicked(MouseButton::Left) {
+                self.moving_start = false;
+                self.remove_obstacle_at(&na::point![position.x, position.y]);
+                return;
+            }
+        }
+
+        self.handle_target_setting(&mut state);
+    }
+
+    fn update_keyboard(&mut self, mut state: KeyboardState, _delta: std::time::Duration) {
+        if !state.is_key_down(VirtualKeyCode::LControl)
+            && !state.is_key_down(VirtualKeyCode::RControl)
+        {
+            return;
+        }
+
+        if state.has_key_been_pressed(VirtualKeyCode::Z) {
+            self.undo_obstacle();
+        } else if state.has_key_been_pressed(VirtualKeyCode::Y) {
+            self.redo_obstacle();
+        }
     }
 
     fn name(&self) -> &'static str {
@@ -521,7 +1004,38 @@ impl PresenterBuilder for KinematicChainBuilder {
         ui.label("")
     }
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
+    fn build(
+        &self,
+        gl: Arc<glow::Context>,
+        _camera_settings: CameraSettings,
+    ) -> Box<dyn Presenter> {
         Box::new(KinematicChain::new(gl))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_round_trip() {
+        json::assert_round_trip(&ChainScene {
+            l_1: 120.0,
+            l_2: 80.5,
+            start: na::point![1200.0, 480.0],
+            end: na::point![850.25, 610.0],
+            obstacles: vec![
+                Obstacle::Rect(Rect {
+                    p_1: na::point![10.0, 20.0],
+                    p_2: na::point![30.0, 45.0],
+                }),
+                Obstacle::Circle(Circle::new(na::point![900.0, 400.0], 35.0)),
+                Obstacle::Polygon(Polygon::new(vec![
+                    na::point![0.0, 0.0],
+                    na::point![10.0, 0.0],
+                    na::point![5.0, 8.0],
+                ])),
+            ],
+        });
+    }
+}
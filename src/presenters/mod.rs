@@ -1,27 +1,71 @@
-use crate::controls::mouse::MouseState;
+use crate::controls::{camera::CameraSettings, keyboard::KeyboardState, mouse::MouseState};
+use crate::json;
+use crate::window::Window;
 use egui_winit::winit::dpi::PhysicalSize;
+use std::any::Any;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub mod jelly;
 pub mod kinematic_chain;
+pub mod multi_link_chain;
 pub mod parametrizable_function;
 pub mod puma;
 pub mod quaternions;
 pub mod spinning_top;
 pub mod spring;
+pub mod spring_chain;
 pub mod hodograph;
 pub mod black_hole;
 
-pub trait Presenter {
+pub trait Presenter: Any {
     fn show_bottom_ui(&mut self, ui: &mut egui::Ui);
     fn show_side_ui(&mut self, ui: &mut egui::Ui);
     fn draw(&self, window_size: Option<PhysicalSize<u32>>);
     fn update(&mut self, delta: Duration);
     fn update_mouse(&mut self, state: MouseState);
+    /// `delta` is the real time since the previous frame, also when the simulation is paused
+    fn update_keyboard(&mut self, state: KeyboardState, delta: Duration);
     fn name(&self) -> &'static str;
+
+    /// Total number of simulation steps taken, `None` for presenters without a stepped simulation
+    fn step_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Background of the window while the presenter is shown
+    fn clear_color(&self) -> [f32; 4] {
+        Window::CLEAR_COLOR
+    }
 }
 
 pub trait PresenterBuilder {
     fn build_ui(&mut self, ui: &mut egui::Ui) -> egui::Response;
-    fn build(&self, gl: std::sync::Arc<glow::Context>) -> Box<dyn Presenter>;
+    /// `camera_settings` are the saved defaults for the cameras of the presenter
+    fn build(&self, gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Box<dyn Presenter>;
+
+    /// Reinitializes `presenter` with the parameters,
+    /// presenters which can't be reset in place are built anew
+    fn rebuild(
+        &self,
+        presenter: &mut Box<dyn Presenter>,
+        gl: Arc<glow::Context>,
+        camera_settings: CameraSettings,
+    ) {
+        *presenter = self.build(gl, camera_settings);
+    }
+
+    /// Writes the parameters as JSON, builders without parameters worth saving refuse
+    fn save(&self, _path: &Path) -> Result<(), json::Error> {
+        Err(json::Error::Message(String::from(
+            "This presenter has no parameters to save",
+        )))
+    }
+
+    fn load(&mut self, _path: &Path) -> Result<(), json::Error> {
+        Err(json::Error::Message(String::from(
+            "This presenter has no parameters to load",
+        )))
+    }
 }
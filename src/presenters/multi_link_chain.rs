@@ -0,0 +1,217 @@
+use super::{Presenter, PresenterBuilder};
+use crate::controls::{camera::CameraSettings, keyboard::KeyboardState, mouse::MouseState};
+use crate::numerics::kinematics::multi_link::MultiLinkSystem;
+use crate::render::{gl_drawable::GlDrawable, gl_mesh::GlLines, gl_program::GlProgram};
+use egui::{widgets::DragValue, Ui};
+use egui_winit::winit::dpi::PhysicalSize;
+use nalgebra as na;
+use std::sync::Arc;
+
+pub struct MultiLinkChain {
+    program: GlProgram,
+    arm_mesh: GlLines,
+    target_mesh: GlLines,
+
+    system: MultiLinkSystem,
+    angles: Vec<f64>,
+    target: na::Point2<f64>,
+
+    gl: Arc<glow::Context>,
+}
+
+impl MultiLinkChain {
+    const ARM_ORIGIN: na::Point2<f64> = na::point![800.0, 500.0];
+    const TARGET_MARKER_SIZE: f32 = 10.0;
+    const DEFAULT_LINK_LENGTH: f64 = 80.0;
+    const MAX_LINK_LENGTH: f64 = 500.0;
+
+    fn new(gl: Arc<glow::Context>, lengths: Vec<f64>) -> Self {
+        let angles = vec![0.0; lengths.len()];
+        let system = MultiLinkSystem::new(lengths);
+        let target = Self::ARM_ORIGIN + system.effector(&angles).coords;
+
+        let mut me = Self {
+            program: GlProgram::vertex_fragment(Arc::clone(&gl), "2d_vert", "pass_frag"),
+            arm_mesh: GlLines::new(Arc::clone(&gl), &[]),
+            target_mesh: GlLines::new(Arc::clone(&gl), &[]),
+
+            system,
+            angles,
+            target,
+
+            gl,
+        };
+
+        me.update_meshes();
+
+        me
+    }
+
+    fn solve(&mut self) {
+        self.angles = self
+            .system
+            .inverse_kinematics(&(self.target - Self::ARM_ORIGIN.coords), &self.angles);
+        self.update_meshes();
+    }
+
+    fn to_screen(point: &na::Point2<f64>) -> na::Point3<f32> {
+        na::point![
+            (point.x + Self::ARM_ORIGIN.x) as f32,
+            (point.y + Self::ARM_ORIGIN.y) as f32,
+            0.0
+        ]
+    }
+
+    fn update_meshes(&mut self) {
+        let joints = self.system.forward_kinematics(&self.angles);
+        let arm_points: Vec<_> = joints
+            .windows(2)
+            .flat_map(|link| [Self::to_screen(&link[0]), Self::to_screen(&link[1])])
+            .collect();
+
+        let target = na::point![self.target.x as f32, self.target.y as f32, 0.0];
+        let size = Self::TARGET_MARKER_SIZE;
+        let target_points = [
+            target - na::vector![size, 0.0, 0.0],
+            target + na::vector![size, 0.0, 0.0],
+            target - na::vector![0.0, size, 0.0],
+            target + na::vector![0.0, size, 0.0],
+        ];
+
+        // Point count of `GlLines` is fixed, so the meshes are recreated
+        self.arm_mesh = GlLines::new(Arc::clone(&self.gl), &arm_points);
+        self.target_mesh = GlLines::new(Arc::clone(&self.gl), &target_points);
+    }
+
+    fn view_matrix(size: PhysicalSize<u32>) -> na::Matrix4<f32> {
+        let width = size.width as f32;
+        let height = size.height as f32;
+
+        na::matrix![
+            2.0 / width, 0.0, 0.0, -1.0;
+            0.0, -2.0 / height, 0.0, 1.0;
+            0.0, 0.0, 1.0, 0.0;
+            0.0, 0.0, 0.0, 1.0;
+        ]
+    }
+}
+
+impl Presenter for MultiLinkChain {
+    fn show_side_ui(&mut self, ui: &mut Ui) {
+        ui.label("Left click sets the target");
+        ui.label(format!(
+            "Effector error: {:.3}",
+            (self.system.effector(&self.angles) + Self::ARM_ORIGIN.coords - self.target).norm()
+        ));
+
+        let mut changed = false;
+
+        ui.label("Link lengths");
+        let mut index = 0;
+        self.system.lengths.retain_mut(|length| {
+            let stays = ui
+                .horizontal(|ui| {
+                    changed |= ui
+                        .add(
+                            DragValue::new(length)
+                                .clamp_range(1.0..=Self::MAX_LINK_LENGTH)
+                                .speed(1.0),
+                        )
+                        .changed();
+
+                    !ui.button("X").clicked()
+                })
+                .inner;
+
+            if !stays {
+                self.angles.remove(index);
+                changed = true;
+            } else {
+                index += 1;
+            }
+
+            stays
+        });
+
+        if ui.button("Add link").clicked() {
+            self.system.lengths.push(Self::DEFAULT_LINK_LENGTH);
+            self.angles.push(0.0);
+            changed = true;
+        }
+
+        if changed {
+            self.solve();
+        }
+    }
+
+    fn show_bottom_ui(&mut self, _ui: &mut Ui) {}
+
+    fn draw(&self, size: Option<PhysicalSize<u32>>) {
+        let Some(size) = size else { return };
+
+        self.program.enable();
+        self.program
+            .uniform_matrix_4_f32_slice("view_transform", Self::view_matrix(size).as_slice());
+        self.program
+            .uniform_matrix_4_f32_slice("model_transform", na::Matrix4::identity().as_slice());
+
+        self.arm_mesh.draw();
+        self.target_mesh.draw();
+    }
+
+    fn update(&mut self, _delta: std::time::Duration) {}
+
+    fn update_mouse(&mut self, state: MouseState) {
+        let Some(position) = state.position() else {
+            return;
+        };
+
+        if state.is_left_button_down() {
+            self.target = na::point![position.x, position.y];
+            self.solve();
+        }
+    }
+
+    fn update_keyboard(&mut self, _state: KeyboardState, _delta: std::time::Duration) {}
+
+    fn name(&self) -> &'static str {
+        "Multi-link chain"
+    }
+}
+
+pub struct MultiLinkChainBuilder {
+    link_count: usize,
+}
+
+impl MultiLinkChainBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for MultiLinkChainBuilder {
+    fn default() -> Self {
+        Self { link_count: 4 }
+    }
+}
+
+impl PresenterBuilder for MultiLinkChainBuilder {
+    fn build_ui(&mut self, ui: &mut Ui) -> egui::Response {
+        ui.horizontal(|ui| {
+            ui.label("Links");
+            ui.add(DragValue::new(&mut self.link_count).clamp_range(1..=16))
+        })
+        .inner
+    }
+
+    fn build(
+        &self,
+        gl: Arc<glow::Context>,
+        _camera_settings: CameraSettings,
+    ) -> Box<dyn Presenter> {
+        Box::new(MultiLinkChain::new(
+            gl,
+            vec![MultiLinkChain::DEFAULT_LINK_LENGTH; self.link_count],
+        ))
+    }
+}
@@ -1,29 +1,58 @@
 use super::{Presenter, PresenterBuilder};
 use crate::{
-    controls::{camera::Camera, mouse::MouseState},
+    controls::{
+        camera::{Camera, CameraSettings},
+        keyboard::KeyboardState,
+        mouse::MouseState,
+    },
+    json,
     numerics::{cylinder::Cylinder, rotations::*},
+    recording::{Replay, Replayable},
     render::{
-        drawbuffer::Drawbuffer, gl_drawable::GlDrawable, gl_mesh::GlTriangleMesh,
-        gl_program::GlProgram, gridable::Triangable, mesh::Mesh, models,
+        drawbuffer::Drawbuffer,
+        gl_drawable::GlDrawable,
+        gl_mesh::{GlLineStrip, GlTriangleMesh},
+        gl_program::GlProgram,
+        gridable::Triangable,
+        light::Light,
+        mesh::Mesh,
+        models,
+    },
+    simulators::puma::{
+        Branch, ConfigState, CylindersTransforms, Params, SceneState, Singularity, JOINT_COUNT,
+        JOINT_NAMES,
     },
-    simulators::puma::{ConfigState, CylindersTransforms, Params, SceneState},
     ui::widgets,
 };
-use egui::{widgets::DragValue, Ui};
+use egui::{widgets::DragValue, ComboBox, Grid, Ui};
+use egui_plot::{Corner, Legend, Line, Plot};
 use egui_winit::winit::dpi::PhysicalSize;
 use na::SimdPartialOrd;
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::path::Path;
 use std::sync::Arc;
 
-const LIGHT_POSITION: na::Vector3<f32> = na::vector![2.0, 4.0, 2.0];
-const LIGHT_COLOR: na::Vector3<f32> = na::vector![2.0, 2.0, 2.0];
-const LIGHT_AMBIENT: na::Vector3<f32> = na::vector![0.4, 0.4, 0.4];
+const LIMITED_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const CONTACT_COLOR: [f32; 4] = [1.0, 0.5, 0.0, 1.0];
+const FLOOR_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const FLOOR_SCALE: f32 = 10.0;
+
+/// Parts of the robot drawn in warning colors
+#[derive(Clone, Copy, Default)]
+struct Highlights {
+    limited: [bool; JOINT_COUNT],
+    /// Bones below the floor
+    contacts: [bool; 4],
+}
 
 struct PumaModel {
     program: GlProgram,
     cylinder: GlTriangleMesh,
     cube: GlTriangleMesh,
+    floor: GlTriangleMesh,
     pretransform: na::Matrix4<f32>,
 }
 
@@ -35,6 +64,7 @@ impl PumaModel {
             program: GlProgram::vertex_fragment(Arc::clone(&gl), "perspective_vert", "phong_frag"),
             cylinder: GlTriangleMesh::new(Arc::clone(&gl), &Mesh::new(vertices, triangles)),
             cube: GlTriangleMesh::new(Arc::clone(&gl), &models::cube()),
+            floor: GlTriangleMesh::new(Arc::clone(&gl), &models::double_plane()),
             pretransform: rotate_x(-std::f64::consts::FRAC_PI_2).map(|c| c as f32),
         }
     }
@@ -77,15 +107,24 @@ impl PumaModel {
         );
     }
 
-    fn draw_puma(&self, transform: &CylindersTransforms) {
+    /// Joints `a1` to `a4` are highlighted on their cylinders,
+    /// `q2` on the extending bone and `a5` on the last bone which it rolls
+    fn draw_puma(&self, transform: &CylindersTransforms, highlights: &Highlights) {
+        let limited = &highlights.limited;
+
         self.program.uniform_f32("material_diffuse", 0.5);
         self.program.uniform_f32("material_specular", 0.8);
         self.program.uniform_f32("material_specular_exp", 20.0);
 
-        self.program
-            .uniform_4_f32_slice("material_color", &[1.0, 1.0, 0.0, 1.0]);
-
-        for transform in transform.joint_transforms {
+        for (index, transform) in transform.joint_transforms.iter().enumerate() {
+            self.program.uniform_4_f32_slice(
+                "material_color",
+                if limited[index] {
+                    &LIMITED_COLOR
+                } else {
+                    &[1.0, 1.0, 0.0, 1.0]
+                },
+            );
             self.program.uniform_matrix_4_f32_slice(
                 "model_transform",
                 (self.pretransform * transform.map(|c| c as f32)).as_slice(),
@@ -93,10 +132,17 @@ impl PumaModel {
             self.cylinder.draw();
         }
 
-        self.program
-            .uniform_4_f32_slice("material_color", &[0.2, 0.2, 0.8, 1.0]);
-
-        for transform in transform.bone_transforms.iter().take(4) {
+        for (index, transform) in transform.bone_transforms.iter().take(4).enumerate() {
+            self.program.uniform_4_f32_slice(
+                "material_color",
+                if highlights.contacts[index] {
+                    &CONTACT_COLOR
+                } else if index == 1 && limited[5] || index == 3 && limited[4] {
+                    &LIMITED_COLOR
+                } else {
+                    &[0.2, 0.2, 0.8, 1.0]
+                },
+            );
             self.program.uniform_matrix_4_f32_slice(
                 "model_transform",
                 (self.pretransform * transform.map(|c| c as f32)).as_slice(),
@@ -105,7 +151,15 @@ impl PumaModel {
         }
     }
 
-    fn draw(&self, camera: &Camera, aspect_ratio: f32, transform: &CylindersTransforms) {
+    fn draw(
+        &self,
+        camera: &Camera,
+        aspect_ratio: f32,
+        transform: &CylindersTransforms,
+        highlights: &Highlights,
+        floor_height: Option<f64>,
+        light: &Light,
+    ) {
         self.program.enable();
 
         self.program
@@ -117,24 +171,90 @@ impl PumaModel {
 
         self.program
             .uniform_3_f32_slice("eye_position", camera.position().coords.as_slice());
+        light.set_uniforms(&self.program);
+
+        self.draw_puma(transform, highlights);
+        self.draw_axes(&transform.bone_transforms[4].map(|c| c as f32));
+
+        if let Some(height) = floor_height {
+            self.draw_floor(height as f32);
+        }
+    }
+
+    /// The floor is the plane `z = height` of the robot
+    fn draw_floor(&self, height: f32) {
         self.program
-            .uniform_3_f32_slice("light_position", LIGHT_POSITION.as_slice());
+            .uniform_4_f32_slice("material_color", &FLOOR_COLOR);
+        self.program.uniform_f32("material_diffuse", 0.4);
+        self.program.uniform_f32("material_specular", 0.2);
+        self.program.uniform_f32("material_specular_exp", 50.0);
+
+        self.program.uniform_matrix_4_f32_slice(
+            "model_transform",
+            (na::Translation3::new(0.0, height, 0.0).to_homogeneous()
+                * na::Scale3::new(FLOOR_SCALE, FLOOR_SCALE, FLOOR_SCALE).to_homogeneous())
+            .as_slice(),
+        );
+
+        self.floor.draw();
+    }
+
+    fn effector_position(&self, transform: &CylindersTransforms) -> na::Point3<f32> {
+        (self.pretransform * transform.bone_transforms[4].map(|c| c as f32))
+            .transform_point(&na::Point3::origin())
+    }
+}
+
+struct Trajectories {
+    program: GlProgram,
+    left: GlLineStrip,
+    right: GlLineStrip,
+}
+
+impl Trajectories {
+    fn new(gl: Arc<glow::Context>, capacity: usize) -> Self {
+        Self {
+            program: GlProgram::vertex_fragment(Arc::clone(&gl), "perspective_vert", "color_frag"),
+            left: GlLineStrip::with_capacity(Arc::clone(&gl), capacity),
+            right: GlLineStrip::with_capacity(gl, capacity),
+        }
+    }
+
+    fn recapacitate(&mut self, capacity: usize) {
+        self.left.recapacitate(capacity);
+        self.right.recapacitate(capacity);
+    }
+
+    fn draw(&self, camera: &Camera, aspect_ratio: f32, strip: &GlLineStrip) {
+        self.program.enable();
         self.program
-            .uniform_3_f32_slice("light_color", LIGHT_COLOR.as_slice());
+            .uniform_matrix_4_f32_slice("view_transform", camera.view_transform().as_slice());
+        self.program.uniform_matrix_4_f32_slice(
+            "projection_transform",
+            camera.projection_transform(aspect_ratio).as_slice(),
+        );
         self.program
-            .uniform_3_f32_slice("ambient", LIGHT_AMBIENT.as_slice());
-
-        self.draw_puma(transform);
-        self.draw_axes(&transform.bone_transforms[4].map(|c| c as f32));
+            .uniform_matrix_4_f32_slice("model_transform", na::Matrix4::identity().as_slice());
+        self.program.uniform_4_f32("color", 1.0, 1.0, 1.0, 1.0);
+        strip.draw();
     }
 }
 
 pub struct Puma {
     puma_model: PumaModel,
     camera: Camera,
+    light: Light,
+
+    trajectories: Trajectories,
+    show_trajectories: bool,
+    max_trajectory_points: usize,
 
     transform_left: CylindersTransforms,
     transform_right: CylindersTransforms,
+    highlights_left: Highlights,
+    highlights_right: Highlights,
+    show_floor: bool,
+    floor_height: f64,
     params: Params,
 
     drawbuffer: RefCell<Option<Drawbuffer>>,
@@ -145,37 +265,74 @@ pub struct Puma {
 
     left_start: ConfigState,
     left_end: ConfigState,
+    left_current: ConfigState,
     right_prev: ConfigState,
 
     animation_time: f64,
     current_time: f64,
     reverse: bool,
+
+    /// Animation time with the left and right configurations
+    config_history: VecDeque<(f64, ConfigState, ConfigState)>,
+    plotted_joint: usize,
+
+    /// Animation time, direction and both configurations of every frame
+    replay: Replay<(f64, bool, ConfigState, ConfigState)>,
+
+    /// Singularities of the start and end frames
+    endpoint_singularities: Vec<Singularity>,
+    /// Singularities passed through during the last update of the right robot
+    right_singularities: Vec<Singularity>,
 }
 
 impl Puma {
     const RIGHT_SAMPLING: f64 = 0.0001;
+    const DEFAULT_MAX_TRAJECTORY_POINTS: usize = 1000;
+    const MAX_TRAJECTORY_POINTS_LIMIT: usize = 100000;
+    const MAX_HISTORY: usize = 10000;
 
     fn new(
         gl: Arc<glow::Context>,
+        camera_settings: CameraSettings,
         start_scene: SceneState,
         end_scene: SceneState,
+        start_branch: Branch,
+        end_branch: Branch,
         params: Params,
     ) -> Self {
-        let start_state = start_scene.inverse_kinematics(&ConfigState::new(), &params);
-        let end_state = end_scene.inverse_kinematics(&ConfigState::new(), &params);
-        let default_transform = start_state.forward_kinematics(&params);
+        let start =
+            start_scene.inverse_kinematics_branch(&start_branch, &ConfigState::new(), &params);
+        let end = end_scene.inverse_kinematics_branch(&end_branch, &ConfigState::new(), &params);
+        let default_transform = start.config.forward_kinematics(&params);
+
+        let mut endpoint_singularities = start.singularities;
+        for singularity in end.singularities {
+            if !endpoint_singularities.contains(&singularity) {
+                endpoint_singularities.push(singularity);
+            }
+        }
 
         Self {
             puma_model: PumaModel::new(Arc::clone(&gl)),
-            camera: Camera::new(),
+            camera: Camera::new(camera_settings),
+            light: Light::default(),
+
+            trajectories: Trajectories::new(Arc::clone(&gl), Self::DEFAULT_MAX_TRAJECTORY_POINTS),
+            show_trajectories: true,
+            max_trajectory_points: Self::DEFAULT_MAX_TRAJECTORY_POINTS,
 
             transform_left: default_transform.clone(),
             transform_right: default_transform,
+            highlights_left: Highlights::default(),
+            highlights_right: Highlights::default(),
+            show_floor: false,
+            floor_height: 0.0,
             params,
 
-            left_start: start_state,
-            left_end: end_state,
-            right_prev: ConfigState::new(),
+            left_start: start.config,
+            left_end: end.config,
+            left_current: start.config,
+            right_prev: start.config,
 
             drawbuffer: RefCell::new(None),
             gl,
@@ -186,9 +343,71 @@ impl Puma {
             animation_time: 2.0,
             current_time: 0.0,
             reverse: false,
+
+            config_history: VecDeque::with_capacity(Self::MAX_HISTORY),
+            plotted_joint: 0,
+            replay: Replay::new(Self::MAX_HISTORY),
+
+            endpoint_singularities,
+            right_singularities: Vec::new(),
+        }
+    }
+
+    fn singularities_ui(ui: &mut Ui, text: &str, singularities: &[Singularity]) {
+        if singularities.is_empty() {
+            return;
+        }
+
+        ui.colored_label(egui::Color32::YELLOW, text);
+        for singularity in singularities {
+            ui.colored_label(egui::Color32::YELLOW, singularity.description());
+        }
+    }
+
+    fn joints_ui(&self, ui: &mut Ui) {
+        Grid::new("puma_joints")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("Left");
+                ui.label("Right");
+                ui.end_row();
+
+                let left = self.left_current.joint_values();
+                let right = self.right_prev.joint_values();
+                for (index, name) in JOINT_NAMES.iter().enumerate() {
+                    let unit = if index == 5 { "" } else { "°" };
+                    ui.label(*name);
+                    ui.monospace(format!("{:.2}{unit}", left[index]));
+                    ui.monospace(format!("{:.2}{unit}", right[index]));
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn set_configs(&mut self, left: ConfigState, right: ConfigState) {
+        self.transform_left = left.forward_kinematics(&self.params);
+        self.transform_right = right.forward_kinematics(&self.params);
+        self.left_current = left;
+        self.right_prev = right;
+
+        let floor = self.floor();
+        for (highlights, transform) in [
+            (&mut self.highlights_left, &self.transform_left),
+            (&mut self.highlights_right, &self.transform_right),
+        ] {
+            highlights.contacts = match floor {
+                Some(height) => transform.bones_below(height),
+                None => [false; 4],
+            };
         }
     }
 
+    fn floor(&self) -> Option<f64> {
+        self.show_floor.then_some(self.floor_height)
+    }
+
     fn drawbuffer_size_matches(&self, size: Option<PhysicalSize<u32>>) -> bool {
         match (size, self.drawbuffer.borrow().as_ref()) {
             (None, None) => true,
@@ -217,32 +436,151 @@ impl Puma {
 
         drawbuffer.clear();
         drawbuffer.draw_with(|| {
-            self.puma_model
-                .draw(&self.camera, aspect_ratio, &self.transform_left);
+            self.puma_model.draw(
+                &self.camera,
+                aspect_ratio,
+                &self.transform_left,
+                &self.highlights_left,
+                self.floor(),
+                &self.light,
+            );
+
+            if self.show_trajectories {
+                self.trajectories
+                    .draw(&self.camera, aspect_ratio, &self.trajectories.left);
+            }
         });
         drawbuffer.blit(0, 0);
 
         drawbuffer.clear();
         drawbuffer.draw_with(|| {
-            self.puma_model
-                .draw(&self.camera, aspect_ratio, &self.transform_right);
+            self.puma_model.draw(
+                &self.camera,
+                aspect_ratio,
+                &self.transform_right,
+                &self.highlights_right,
+                self.floor(),
+                &self.light,
+            );
+
+            if self.show_trajectories {
+                self.trajectories
+                    .draw(&self.camera, aspect_ratio, &self.trajectories.right);
+            }
         });
         drawbuffer.blit(drawbuffer.size().width, 0);
     }
 }
 
+impl Replayable for Puma {
+    type Snapshot = (f64, bool, ConfigState, ConfigState);
+
+    fn snapshot(&self) -> Self::Snapshot {
+        (
+            self.current_time,
+            self.reverse,
+            self.left_current,
+            self.right_prev,
+        )
+    }
+
+    fn restore(&mut self, &(time, reverse, left, right): &Self::Snapshot) {
+        self.current_time = time;
+        self.reverse = reverse;
+        self.set_configs(left, right);
+    }
+}
+
 impl Presenter for Puma {
     fn show_side_ui(&mut self, ui: &mut Ui) {
+        if let Some(frame) = self.replay.ui(ui) {
+            self.restore(&frame);
+        }
+
+        ui.collapsing("Camera", |ui| widgets::camera_ui(ui, &mut self.camera));
+        ui.collapsing("Light", |ui| self.light.ui(ui));
         ui.label("Animation time");
         ui.add(
             DragValue::new(&mut self.animation_time)
                 .clamp_range(0.1..=10.0)
                 .speed(0.1),
         );
+
+        ui.collapsing("Joints", |ui| self.joints_ui(ui));
+
+        ui.checkbox(&mut self.show_floor, "Show floor");
+        ui.add_enabled(
+            self.show_floor,
+            DragValue::new(&mut self.floor_height)
+                .clamp_range(-10.0..=10.0)
+                .speed(0.1)
+                .prefix("Floor height: "),
+        );
+        for (side, highlights) in [
+            ("Left", &self.highlights_left),
+            ("Right", &self.highlights_right),
+        ] {
+            if highlights.contacts.contains(&true) {
+                ui.colored_label(
+                    egui::Color32::YELLOW,
+                    format!("{side} robot goes through the floor"),
+                );
+            }
+        }
+
+        ui.checkbox(&mut self.show_trajectories, "Show effector trajectories");
+        ui.label("Maximum trajectory points visible");
+        if ui
+            .add(
+                DragValue::new(&mut self.max_trajectory_points)
+                    .clamp_range(2..=Self::MAX_TRAJECTORY_POINTS_LIMIT),
+            )
+            .changed()
+        {
+            self.trajectories.recapacitate(self.max_trajectory_points);
+        }
+
+        Self::singularities_ui(
+            ui,
+            "Start or end frame is singular",
+            &self.endpoint_singularities,
+        );
+        Self::singularities_ui(
+            ui,
+            "Right robot passes through a singularity",
+            &self.right_singularities,
+        );
     }
 
     fn show_bottom_ui(&mut self, ui: &mut Ui) {
-        ui.label("Bottom text");
+        ComboBox::from_label("Plotted joint")
+            .selected_text(JOINT_NAMES[self.plotted_joint])
+            .show_ui(ui, |ui| {
+                for (index, name) in JOINT_NAMES.iter().enumerate() {
+                    ui.selectable_value(&mut self.plotted_joint, index, *name);
+                }
+            });
+
+        let joint = self.plotted_joint;
+        let series = |right: bool| {
+            self.config_history
+                .iter()
+                .map(|(t, left_config, right_config)| {
+                    let config = if right { right_config } else { left_config };
+                    [*t, config.joint_values()[joint]]
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Plot::new("Joint values graph")
+            .view_aspect(10.0)
+            .auto_bounds_x()
+            .auto_bounds_y()
+            .legend(Legend::default().position(Corner::LeftTop))
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(series(false)).name("Left"));
+                plot_ui.line(Line::new(series(true)).name("Right"));
+            });
     }
 
     fn draw(&self, size: Option<egui_winit::winit::dpi::PhysicalSize<u32>>) {
@@ -256,6 +594,10 @@ impl Presenter for Puma {
     }
 
     fn update(&mut self, delta: std::time::Duration) {
+        if let Some(live) = self.replay.resume() {
+            self.restore(&live);
+        }
+
         let prev_time = self.current_time;
         self.current_time +=
             if self.reverse { -1.0 } else { 1.0 } * delta.as_secs_f64() / self.animation_time;
@@ -268,17 +610,34 @@ impl Presenter for Puma {
             self.reverse = false;
         }
 
-        let mut new_right = self
-            .start_scene
-            .interpolate(&self.end_scene, self.current_time)
-            .inverse_kinematics(&self.right_prev, &self.params);
+        self.right_singularities.clear();
+        self.highlights_right.limited = [false; JOINT_COUNT];
+        let mut solve = |time: f64, guide: &ConfigState| {
+            let solution = self
+                .start_scene
+                .interpolate(&self.end_scene, time)
+                .inverse_kinematics(guide, &self.params);
+
+            for singularity in solution.singularities {
+                if !self.right_singularities.contains(&singularity) {
+                    self.right_singularities.push(singularity);
+                }
+            }
+
+            let mut config = solution.config;
+            let limited = config.clamp_to_limits(&self.params);
+            for (any, limited) in self.highlights_right.limited.iter_mut().zip(limited) {
+                *any |= limited;
+            }
+
+            config
+        };
+
+        let mut new_right = solve(self.current_time, &self.right_prev);
 
         let mut catchup_time = prev_time;
         while (catchup_time - self.current_time).abs() >= Self::RIGHT_SAMPLING {
-            new_right = self
-                .start_scene
-                .interpolate(&self.end_scene, catchup_time)
-                .inverse_kinematics(&new_right, &self.params);
+            new_right = solve(catchup_time, &new_right);
 
             catchup_time += if catchup_time < self.current_time {
                 Self::RIGHT_SAMPLING
@@ -287,30 +646,49 @@ impl Presenter for Puma {
             };
         }
 
-        self.transform_left = self
-            .left_start
-            .lerp(&self.left_end, self.current_time)
-            .forward_kinematics(&self.params);
+        let mut new_left = self.left_start.lerp(&self.left_end, self.current_time);
+        self.highlights_left.limited = new_left.clamp_to_limits(&self.params);
+
+        if self.config_history.len() == Self::MAX_HISTORY {
+            self.config_history.pop_front();
+        }
+
+        self.config_history
+            .push_back((self.current_time, new_left, new_right));
 
-        self.transform_right = new_right.forward_kinematics(&self.params);
-        self.right_prev = new_right;
+        self.set_configs(new_left, new_right);
+
+        self.trajectories
+            .left
+            .push_vertex(&self.puma_model.effector_position(&self.transform_left));
+        self.trajectories
+            .right
+            .push_vertex(&self.puma_model.effector_position(&self.transform_right));
+
+        self.replay.record(self.snapshot());
     }
 
     fn update_mouse(&mut self, state: MouseState) {
         self.camera.update_from_mouse(state);
     }
 
+    fn update_keyboard(&mut self, state: KeyboardState, delta: std::time::Duration) {
+        self.camera.update_from_keyboard(&state, delta);
+    }
+
     fn name(&self) -> &'static str {
         "Puma"
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct PumaBuilder {
     start_rotation: Rotation,
     start_position: na::Point3<f64>,
     end_rotation: Rotation,
     end_position: na::Point3<f64>,
+    start_branch: Branch,
+    end_branch: Branch,
     params: Params,
 }
 
@@ -338,6 +716,46 @@ impl PumaBuilder {
                     .clamp_range(0.0..=5.0)
                     .speed(0.1),
             )
+            | ui.label("Joint limits")
+            | self.limits_ui(ui)
+    }
+
+    fn limits_ui(&mut self, ui: &mut Ui) -> egui::Response {
+        Grid::new("puma_joint_limits")
+            .num_columns(3)
+            .show(ui, |ui| {
+                let mut response = ui.label("") | ui.label("Min") | ui.label("Max");
+                ui.end_row();
+
+                for (index, limit) in self.params.limits.iter_mut().enumerate() {
+                    let (range, speed) = if index == 5 {
+                        (0.0..=Params::MAX_Q2, 0.1)
+                    } else {
+                        (-180.0..=180.0, 1.0)
+                    };
+
+                    response |= ui.label(JOINT_NAMES[index])
+                        | ui.add(
+                            DragValue::new(&mut limit.min)
+                                .clamp_range(*range.start()..=limit.max)
+                                .speed(speed),
+                        )
+                        | ui.add(
+                            DragValue::new(&mut limit.max)
+                                .clamp_range(limit.min..=*range.end())
+                                .speed(speed),
+                        );
+                    ui.end_row();
+                }
+
+                response
+            })
+            .inner
+    }
+
+    fn branch_ui(ui: &mut Ui, branch: &mut Branch) -> egui::Response {
+        ui.checkbox(&mut branch.flip_a1, "Second a1 solution")
+            | ui.checkbox(&mut branch.flip_a23, "Second a2 + a3 solution")
     }
 
     fn frame_ui(
@@ -385,18 +803,69 @@ impl PresenterBuilder for PumaBuilder {
                 &mut self.start_rotation,
                 &mut self.start_position.coords,
             )
+            | Self::branch_ui(ui, &mut self.start_branch)
             | ui.separator()
             | ui.label("End frame")
             | Self::frame_ui(ui, &mut self.end_rotation, &mut self.end_position.coords)
+            | Self::branch_ui(ui, &mut self.end_branch)
     }
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
+    fn build(&self, gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Box<dyn Presenter> {
         let start_rotation = self.start_rotation.normalize().to_quaternion().normalize();
         let end_rotation = self.end_rotation.normalize().to_quaternion().normalize();
 
         let start_scene = SceneState::new(self.start_position, start_rotation);
         let end_scene = SceneState::new(self.end_position, end_rotation);
 
-        Box::new(Puma::new(gl, start_scene, end_scene, self.params))
+        Box::new(Puma::new(
+            gl,
+            camera_settings,
+            start_scene,
+            end_scene,
+            self.start_branch,
+            self.end_branch,
+            self.params,
+        ))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), json::Error> {
+        json::save(path, self)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), json::Error> {
+        *self = json::load(path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_round_trip() {
+        let mut limits = Params::default().limits;
+        limits[2].min = -90.0;
+        let params = Params {
+            l3: 4.5,
+            limits,
+            ..Default::default()
+        };
+
+        json::assert_round_trip(&PumaBuilder {
+            start_rotation: Rotation::EulerAngles(EulerAngles(na::vector![10.0, 20.0, 30.0])),
+            start_position: na::point![2.0, 1.0, 0.5],
+            end_rotation: Rotation::Quaternion(Quaternion(na::vector![0.0, 1.0, 0.0, 0.0])),
+            end_position: na::point![-1.5, 2.0, 3.0],
+            start_branch: Branch {
+                flip_a1: true,
+                flip_a23: false,
+            },
+            end_branch: Branch {
+                flip_a1: false,
+                flip_a23: true,
+            },
+            params,
+        });
     }
 }
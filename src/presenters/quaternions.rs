@@ -1,10 +1,19 @@
 use super::{Presenter, PresenterBuilder};
 use crate::{
-    controls::{camera::Camera, mouse::MouseState},
+    controls::{
+        camera::{Camera, CameraSettings},
+        keyboard::KeyboardState,
+        mouse::MouseState,
+    },
+    json,
     numerics::rotations::*,
     render::{
-        drawbuffer::Drawbuffer, gl_drawable::GlDrawable, gl_mesh::GlTriangleMesh,
-        gl_program::GlProgram, models,
+        drawbuffer::Drawbuffer,
+        gl_drawable::GlDrawable,
+        gl_mesh::{GlInstancedTriangleMesh, GlTriangleMesh},
+        gl_program::GlProgram,
+        light::Light,
+        models,
     },
     ui::widgets,
 };
@@ -12,15 +21,22 @@ use egui::{widgets::DragValue, Ui};
 use egui_winit::winit::dpi::PhysicalSize;
 use na::SimdPartialOrd;
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::path::Path;
 use std::sync::Arc;
 
 pub struct Quaternions {
     camera: Camera,
+    light: Light,
 
     drawbuffer: RefCell<Option<Drawbuffer>>,
     meshes_program: GlProgram,
+    instanced_program: GlProgram,
     cube_mesh: GlTriangleMesh,
+    keyframe_cubes_euler: GlInstancedTriangleMesh,
+    keyframe_cubes_quaternion: GlInstancedTriangleMesh,
+    instanced: bool,
     gl: Arc<glow::Context>,
 
     start_rotation_euler: EulerAngles,
@@ -44,19 +60,21 @@ pub struct Quaternions {
 }
 
 impl Quaternions {
-    const LIGHT_POSITION: na::Vector3<f32> = na::vector![2.0, 4.0, 2.0];
-    const LIGHT_COLOR: na::Vector3<f32> = na::vector![2.0, 2.0, 2.0];
-    const LIGHT_AMBIENT: na::Vector3<f32> = na::vector![0.4, 0.4, 0.4];
-
     fn new(
         gl: Arc<glow::Context>,
-        start_rotation: Rotation,
-        start_position: na::Vector3<f64>,
-        end_rotation: Rotation,
-        end_position: na::Vector3<f64>,
-        slerp: bool,
-        keyframes: usize,
+        camera_settings: CameraSettings,
+        parameters: &QuaternionsBuilder,
     ) -> Self {
+        let QuaternionsBuilder {
+            start_position,
+            end_position,
+            slerp,
+            keyframes,
+            ..
+        } = *parameters;
+        let start_rotation = parameters.start_rotation.normalize();
+        let end_rotation = parameters.end_rotation.normalize();
+
         let start_rotation_euler = start_rotation.normalize().to_euler_angles().normalize();
         let start_rotation_quaternion = start_rotation.normalize().to_quaternion().normalize();
         let end_rotation_euler = end_rotation.normalize().to_euler_angles().normalize();
@@ -80,7 +98,8 @@ impl Quaternions {
         );
 
         Self {
-            camera: Camera::new(),
+            camera: Camera::new(camera_settings),
+            light: Light::default(),
 
             drawbuffer: RefCell::new(None),
             meshes_program: GlProgram::vertex_fragment(
@@ -88,7 +107,23 @@ impl Quaternions {
                 "perspective_vert",
                 "phong_frag",
             ),
+            instanced_program: GlProgram::vertex_fragment(
+                Arc::clone(&gl),
+                "perspective_instanced_vert",
+                "phong_frag",
+            ),
             cube_mesh: GlTriangleMesh::new(Arc::clone(&gl), &models::cube()),
+            keyframe_cubes_euler: GlInstancedTriangleMesh::new(
+                Arc::clone(&gl),
+                &models::cube(),
+                &keyframes_euler,
+            ),
+            keyframe_cubes_quaternion: GlInstancedTriangleMesh::new(
+                Arc::clone(&gl),
+                &models::cube(),
+                &keyframes_quaternion,
+            ),
+            instanced: true,
             gl,
 
             animation_time: 5.0,
@@ -208,6 +243,7 @@ impl Quaternions {
     fn draw_axis(
         &self,
         keyframes: &[na::Matrix4<f32>],
+        keyframe_cubes: &GlInstancedTriangleMesh,
         current_frame: &na::Matrix4<f32>,
         vector: &na::Vector3<f32>,
         color: &[f32; 4],
@@ -218,15 +254,28 @@ impl Quaternions {
 
         let base_transform = na::Translation3::from(translation).to_homogeneous()
             * na::Scale3::from(scale).to_homogeneous();
+
+        if self.instanced {
+            self.instanced_program.enable();
+            self.instanced_program
+                .uniform_4_f32_slice("material_color", color);
+            self.instanced_program
+                .uniform_matrix_4_f32_slice("model_transform", base_transform.as_slice());
+            keyframe_cubes.draw();
+        }
+
+        self.meshes_program.enable();
         self.meshes_program
             .uniform_4_f32_slice("material_color", color);
 
-        for transform in keyframes {
-            self.meshes_program.uniform_matrix_4_f32_slice(
-                "model_transform",
-                (transform * base_transform).as_slice(),
-            );
-            self.cube_mesh.draw();
+        if !self.instanced {
+            for transform in keyframes {
+                self.meshes_program.uniform_matrix_4_f32_slice(
+                    "model_transform",
+                    (transform * base_transform).as_slice(),
+                );
+                self.cube_mesh.draw();
+            }
         }
 
         self.meshes_program.uniform_matrix_4_f32_slice(
@@ -236,32 +285,52 @@ impl Quaternions {
         self.cube_mesh.draw();
     }
 
-    fn draw_axes(&self, current_frame: &na::Matrix4<f32>, keyframes: &[na::Matrix4<f32>]) {
-        self.meshes_program.uniform_f32("material_diffuse", 0.8);
-        self.meshes_program.uniform_f32("material_specular", 0.4);
-        self.meshes_program
-            .uniform_f32("material_specular_exp", 10.0);
-
+    fn draw_axes(
+        &self,
+        current_frame: &na::Matrix4<f32>,
+        keyframes: &[na::Matrix4<f32>],
+        keyframe_cubes: &GlInstancedTriangleMesh,
+    ) {
         self.draw_axis(
             keyframes,
+            keyframe_cubes,
             current_frame,
             &na::vector![1.0, 0.0, 0.0],
             &[1.0, 0.0, 0.0, 1.0],
         );
         self.draw_axis(
             keyframes,
+            keyframe_cubes,
             current_frame,
             &na::vector![0.0, 1.0, 0.0],
             &[0.0, 1.0, 0.0, 1.0],
         );
         self.draw_axis(
             keyframes,
+            keyframe_cubes,
             current_frame,
             &na::vector![0.0, 0.0, 1.0],
             &[0.0, 0.0, 1.0, 1.0],
         );
     }
 
+    fn set_scene_uniforms(&self, program: &GlProgram, aspect_ratio: f32) {
+        program.enable();
+        program
+            .uniform_matrix_4_f32_slice("view_transform", self.camera.view_transform().as_slice());
+        program.uniform_matrix_4_f32_slice(
+            "projection_transform",
+            self.camera.projection_transform(aspect_ratio).as_slice(),
+        );
+
+        program.uniform_3_f32_slice("eye_position", self.camera.position().coords.as_slice());
+        self.light.set_uniforms(program);
+
+        program.uniform_f32("material_diffuse", 0.8);
+        program.uniform_f32("material_specular", 0.4);
+        program.uniform_f32("material_specular_exp", 10.0);
+    }
+
     fn draw_meshes(&self, size: PhysicalSize<u32>) {
         let aspect_ratio = 0.5 * size.width as f32 / size.height as f32;
         let drawbuffer = self.drawbuffer.borrow();
@@ -269,32 +338,27 @@ impl Quaternions {
             return;
         };
 
-        self.meshes_program.enable();
-        self.meshes_program
-            .uniform_matrix_4_f32_slice("view_transform", self.camera.view_transform().as_slice());
-        self.meshes_program.uniform_matrix_4_f32_slice(
-            "projection_transform",
-            self.camera.projection_transform(aspect_ratio).as_slice(),
-        );
-
-        self.meshes_program
-            .uniform_3_f32_slice("eye_position", self.camera.position().coords.as_slice());
-        self.meshes_program
-            .uniform_3_f32_slice("light_position", Self::LIGHT_POSITION.as_slice());
-        self.meshes_program
-            .uniform_3_f32_slice("light_color", Self::LIGHT_COLOR.as_slice());
-        self.meshes_program
-            .uniform_3_f32_slice("ambient", Self::LIGHT_AMBIENT.as_slice());
+        for program in [&self.meshes_program, &self.instanced_program] {
+            self.set_scene_uniforms(program, aspect_ratio);
+        }
 
         drawbuffer.clear();
         drawbuffer.draw_with(|| {
-            self.draw_axes(&self.current_euler, &self.keyframes_euler);
+            self.draw_axes(
+                &self.current_euler,
+                &self.keyframes_euler,
+                &self.keyframe_cubes_euler,
+            );
         });
         drawbuffer.blit(0, 0);
 
         drawbuffer.clear();
         drawbuffer.draw_with(|| {
-            self.draw_axes(&self.current_quaternion, &self.keyframes_quaternion);
+            self.draw_axes(
+                &self.current_quaternion,
+                &self.keyframes_quaternion,
+                &self.keyframe_cubes_quaternion,
+            );
         });
         drawbuffer.blit(drawbuffer.size().width, 0);
     }
@@ -302,12 +366,15 @@ impl Quaternions {
 
 impl Presenter for Quaternions {
     fn show_side_ui(&mut self, ui: &mut Ui) {
+        ui.collapsing("Camera", |ui| widgets::camera_ui(ui, &mut self.camera));
+        ui.collapsing("Light", |ui| self.light.ui(ui));
         ui.label("Animation time");
         ui.add(
             DragValue::new(&mut self.animation_time)
                 .clamp_range(0.0..=20.0)
                 .speed(0.5),
         );
+        ui.checkbox(&mut self.instanced, "Instanced keyframe rendering");
     }
 
     fn show_bottom_ui(&mut self, ui: &mut Ui) {
@@ -356,12 +423,20 @@ impl Presenter for Quaternions {
         self.camera.update_from_mouse(state);
     }
 
+    fn update_keyboard(&mut self, state: KeyboardState, delta: std::time::Duration) {
+        self.camera.update_from_keyboard(&state, delta);
+    }
+
+    fn clear_color(&self) -> [f32; 4] {
+        [0.0, 0.0, 0.0, 1.0]
+    }
+
     fn name(&self) -> &'static str {
         "Quaternions"
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct QuaternionsBuilder {
     start_rotation: Rotation,
     start_position: na::Vector3<f64>,
@@ -424,15 +499,33 @@ impl PresenterBuilder for QuaternionsBuilder {
             | ui.add(DragValue::new(&mut self.keyframes).clamp_range(0..=100))
     }
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
-        Box::new(Quaternions::new(
-            gl,
-            self.start_rotation.normalize(),
-            self.start_position,
-            self.end_rotation.normalize(),
-            self.end_position,
-            self.slerp,
-            self.keyframes,
-        ))
+    fn build(&self, gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Box<dyn Presenter> {
+        Box::new(Quaternions::new(gl, camera_settings, self))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), json::Error> {
+        json::save(path, self)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), json::Error> {
+        *self = json::load(path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_round_trip() {
+        json::assert_round_trip(&QuaternionsBuilder {
+            start_rotation: Rotation::Quaternion(Quaternion(na::vector![0.5, 0.5, -0.5, 0.5])),
+            start_position: na::vector![-1.0, 0.0, 2.5],
+            end_rotation: Rotation::EulerAngles(EulerAngles(na::vector![30.0, -45.0, 90.0])),
+            end_position: na::vector![3.0, 1.0, -0.75],
+            slerp: true,
+            keyframes: 17,
+        });
     }
 }
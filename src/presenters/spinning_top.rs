@@ -1,90 +1,108 @@
 use super::{Presenter, PresenterBuilder};
 use crate::{
-    controls::{camera::Camera, mouse::MouseState},
+    controls::{
+        camera::{Camera, CameraSettings},
+        keyboard::KeyboardState,
+        mouse::MouseState,
+    },
+    json,
     numerics::{
         ode::{self, Solver},
         RungeKuttaIV,
     },
+    recording::Recording,
     render::{
+        export,
         gl_drawable::GlDrawable,
-        gl_mesh::{GlLineStrip, GlTriangleMesh},
+        gl_mesh::{GlLineStrip, GlLines, GlTriangleMesh},
         gl_program::GlProgram,
+        light::Light,
+        mesh::{ClassicVertex, Mesh},
         models,
     },
-    simulators::spinning_top::SpinningTopODE,
+    simulators::spinning_top::{SpinningTopODE, TopBody},
+    ui::widgets::{self, PathAction},
 };
-use egui::{widgets::DragValue, Ui};
+use egui::{widgets::DragValue, Slider, Ui};
+use egui_plot::{Corner, Legend, Line, Plot};
+use egui_winit::winit::dpi::PhysicalSize;
 use glow::HasContext;
 use nalgebra as na;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::{any::Any, cell::Cell, collections::VecDeque, path::Path, sync::Arc};
 
 pub struct SpinningTop {
     meshes_program: GlProgram,
-    box_mesh: GlTriangleMesh,
+    body_mesh: GlTriangleMesh,
     plane_mesh: GlTriangleMesh,
 
     strips_program: GlProgram,
     gravity_strip: GlLineStrip,
     trajectory_strip: GlLineStrip,
     diagonal_strip: GlLineStrip,
+    angular_momentum_arrow: GlLines,
 
     camera: Camera,
+    light: Light,
+    window_size: Cell<Option<PhysicalSize<u32>>>,
 
     state: ode::State<7>,
     solver: RungeKuttaIV<7, SpinningTopODE>,
     simulation_speed: f64,
     exact_t: f64,
+    step_count: usize,
 
     show_trajectory: bool,
     show_plane: bool,
     show_gravity_vector: bool,
-    show_box: bool,
+    show_body: bool,
     show_diagonal: bool,
+    show_angular_momentum: bool,
 
     max_trajectory_points: usize,
+    energy_history: VecDeque<[f64; 2]>,
+    precession_history: VecDeque<[f64; 2]>,
+    nutation_history: VecDeque<[f64; 2]>,
+    trajectory: Recording<4>,
+
+    stl_export: PathAction,
+    trajectory_export: PathAction,
 
     gl: Arc<glow::Context>,
 }
 
 impl SpinningTop {
-    const LIGHT_POSITION: na::Vector3<f32> = na::vector![-2.0, 4.0, -2.0];
-    const LIGHT_COLOR: na::Vector3<f32> = na::vector![2.0, 2.0, 2.0];
-    const LIGHT_AMBIENT: na::Vector3<f32> = na::vector![0.4, 0.4, 0.4];
     const PLANE_SCALE: f32 = 3.0;
 
-    const BOX_COLOR: na::Vector4<f32> = na::vector![0.2, 0.4, 0.8, 0.7];
+    const BODY_COLOR: na::Vector4<f32> = na::vector![0.2, 0.4, 0.8, 0.7];
     const PLANE_COLOR: na::Vector4<f32> = na::vector![0.8, 0.4, 0.2, 0.4];
 
     const DEFAULT_DENSITY: f64 = 10.0;
     const DEFAULT_SIDE_LENGTH: f64 = 2.0;
+    const CYLINDER_SLICES: u32 = 32;
+    const SPHERE_STACKS: u32 = 16;
+    const SPHERE_SLICES: u32 = 32;
     const DEFAULT_MAX_TRAJECTORY_POINTS: usize = 10000;
     const MAX_TRAJECTORY_POINTS_LIMIT: usize = 1024 * 1024;
+    const MAX_HISTORY: usize = 10000;
+    const ARROW_HEAD_SIZE: f32 = 0.15;
 
     pub fn new(
         gl: Arc<glow::Context>,
+        camera_settings: CameraSettings,
         rotation: na::UnitQuaternion<f64>,
         angular_velocity: na::Vector3<f64>,
+        contact_point: na::Vector3<f64>,
     ) -> Self {
-        let mut state = ode::State::<7> {
-            t: 0.0,
-            y: na::SVector::<f64, 7>::zeros(),
-        };
-
-        state.y[0] = angular_velocity.x;
-        state.y[1] = angular_velocity.y;
-        state.y[2] = angular_velocity.z;
-        state.y[3] = rotation.w;
-        state.y[4] = rotation.i;
-        state.y[5] = rotation.j;
-        state.y[6] = rotation.k;
+        let state = Self::initial_state(rotation, angular_velocity);
 
-        Self {
+        let mut me = Self {
             meshes_program: GlProgram::vertex_fragment(
                 Arc::clone(&gl),
                 "perspective_vert",
                 "phong_frag",
             ),
-            box_mesh: GlTriangleMesh::new(Arc::clone(&gl), &models::cube()),
+            body_mesh: GlTriangleMesh::new(Arc::clone(&gl), &models::cube()),
             plane_mesh: GlTriangleMesh::new(Arc::clone(&gl), &models::double_plane()),
 
             strips_program: GlProgram::vertex_fragment(
@@ -101,31 +119,215 @@ impl SpinningTop {
                 Self::DEFAULT_MAX_TRAJECTORY_POINTS,
             ),
             diagonal_strip: Self::diagonal_strip(Arc::clone(&gl)),
+            angular_momentum_arrow: GlLines::new(Arc::clone(&gl), &[na::Point3::origin(); 6]),
 
-            camera: Camera::new(),
+            camera: Camera::new(camera_settings),
+            light: Light::new(
+                na::vector![-2.0, 4.0, -2.0],
+                na::vector![2.0, 2.0, 2.0],
+                na::vector![0.4, 0.4, 0.4],
+            ),
+            window_size: Cell::new(None),
 
             exact_t: 0.0,
+            step_count: 0,
             state,
             solver: RungeKuttaIV::new(
                 0.01,
-                SpinningTopODE::new(Self::DEFAULT_DENSITY, Self::DEFAULT_SIDE_LENGTH),
+                SpinningTopODE::new(
+                    Self::DEFAULT_DENSITY,
+                    TopBody::Cube {
+                        side_length: Self::DEFAULT_SIDE_LENGTH,
+                    },
+                ),
             ),
             simulation_speed: 1.0,
 
-            show_box: true,
+            show_body: true,
             show_plane: true,
             show_gravity_vector: false,
             show_trajectory: false,
             show_diagonal: false,
+            show_angular_momentum: false,
 
             max_trajectory_points: Self::DEFAULT_MAX_TRAJECTORY_POINTS,
+            energy_history: VecDeque::new(),
+            precession_history: VecDeque::new(),
+            nutation_history: VecDeque::new(),
+            trajectory: Recording::new(["t", "x", "y", "z"], Self::DEFAULT_MAX_TRAJECTORY_POINTS),
+
+            stl_export: PathAction::new("spinning_top.stl"),
+            trajectory_export: PathAction::new("spinning_top_trajectory.csv"),
 
             gl,
+        };
+
+        me.solver.ode_mut().set_contact_point(contact_point);
+
+        me
+    }
+
+    fn initial_state(
+        rotation: na::UnitQuaternion<f64>,
+        angular_velocity: na::Vector3<f64>,
+    ) -> ode::State<7> {
+        let mut state = ode::State::<7> {
+            t: 0.0,
+            y: na::SVector::<f64, 7>::zeros(),
+        };
+
+        state.y[0] = angular_velocity.x;
+        state.y[1] = angular_velocity.y;
+        state.y[2] = angular_velocity.z;
+        state.y[3] = rotation.w;
+        state.y[4] = rotation.i;
+        state.y[5] = rotation.j;
+        state.y[6] = rotation.k;
+
+        state
+    }
+
+    /// Restarts the simulation, keeping GL resources and the view settings
+    pub fn reset(
+        &mut self,
+        body: TopBody,
+        rotation: na::UnitQuaternion<f64>,
+        angular_velocity: na::Vector3<f64>,
+        contact_point: na::Vector3<f64>,
+    ) {
+        self.set_body(body);
+        self.solver.ode_mut().set_density(Self::DEFAULT_DENSITY);
+        self.solver.ode_mut().set_contact_point(contact_point);
+        self.state = Self::initial_state(rotation, angular_velocity);
+        self.exact_t = 0.0;
+        self.step_count = 0;
+
+        self.energy_history.clear();
+        self.precession_history.clear();
+        self.nutation_history.clear();
+        self.trajectory.clear();
+        self.trajectory_strip.clear();
+    }
+
+    /// Bounds the distance from the contact point to any point of the body
+    fn reach(&self) -> f64 {
+        let ode = self.solver.ode();
+        ode.body().reach() + ode.contact_point().norm()
+    }
+
+    fn set_body(&mut self, body: TopBody) {
+        if std::mem::discriminant(&body) != std::mem::discriminant(self.solver.ode().body()) {
+            self.body_mesh = GlTriangleMesh::new(Arc::clone(&self.gl), &Self::body_mesh(&body));
+        }
+
+        self.solver.ode_mut().set_body(body);
+    }
+
+    /// Mesh in the unit space transformed by `body_model_transform`
+    fn body_mesh(body: &TopBody) -> Mesh<ClassicVertex> {
+        match body {
+            TopBody::Cube { .. } | TopBody::Cuboid { .. } => models::cube(),
+            TopBody::Cylinder { .. } => models::cylinder(Self::CYLINDER_SLICES),
+            TopBody::Sphere { .. } => models::sphere(Self::SPHERE_STACKS, Self::SPHERE_SLICES),
+        }
+    }
+
+    fn body_model_transform(body: &TopBody) -> na::Matrix4<f32> {
+        let center = body.center().map(|c| c as f32);
+        let translation = na::Translation3::from(center).to_homogeneous();
+
+        match *body {
+            TopBody::Cube { .. } | TopBody::Cuboid { .. } => {
+                translation * na::Scale3::from(center).to_homogeneous()
+            }
+            TopBody::Cylinder { radius, height } => {
+                let axis = TopBody::AXIS.map(|c| c as f32);
+                translation
+                    * na::UnitQuaternion::rotation_between(&na::Vector3::y(), &axis)
+                        .unwrap()
+                        .to_homogeneous()
+                    * na::Scale3::new(radius as f32, 0.5 * height as f32, radius as f32)
+                        .to_homogeneous()
+            }
+            TopBody::Sphere { radius } => {
+                translation
+                    * na::Scale3::new(radius as f32, radius as f32, radius as f32).to_homogeneous()
+            }
+        }
+    }
+
+    fn body_ui(&mut self, ui: &mut Ui) {
+        let mut body = *self.solver.ode().body();
+        let reach = body.reach();
+
+        egui::ComboBox::from_label("Body")
+            .selected_text(body.name())
+            .show_ui(ui, |ui| {
+                for option in [
+                    TopBody::Cube {
+                        side_length: reach / 3.0_f64.sqrt(),
+                    },
+                    TopBody::Cuboid {
+                        sizes: reach / 3.0_f64.sqrt() * na::vector![1.0, 1.0, 1.0],
+                    },
+                    TopBody::Cylinder {
+                        radius: 0.25 * reach,
+                        height: 0.9 * reach,
+                    },
+                    TopBody::Sphere {
+                        radius: 0.5 * reach,
+                    },
+                ] {
+                    let selected = std::mem::discriminant(&option) == std::mem::discriminant(&body);
+                    if ui.selectable_label(selected, option.name()).clicked() && !selected {
+                        body = option;
+                    }
+                }
+            });
+
+        let length = |ui: &mut Ui, text: &str, value: &mut f64| {
+            ui.horizontal(|ui| {
+                ui.label(text);
+                ui.add(
+                    DragValue::new(value)
+                        .clamp_range(0.1..=f64::MAX)
+                        .speed(0.01),
+                )
+            })
+            .inner
+        };
+
+        match &mut body {
+            TopBody::Cube { side_length } => {
+                length(ui, "Side length", side_length);
+            }
+            TopBody::Cuboid { sizes } => {
+                length(ui, "Size x", &mut sizes.x);
+                length(ui, "Size y", &mut sizes.y);
+                length(ui, "Size z", &mut sizes.z);
+            }
+            TopBody::Cylinder { radius, height } => {
+                length(ui, "Radius", radius);
+                length(ui, "Height", height);
+            }
+            TopBody::Sphere { radius } => {
+                length(ui, "Radius", radius);
+            }
+        }
+
+        if body != *self.solver.ode().body() {
+            self.set_body(body);
         }
     }
 
-    fn set_side_length(&mut self, side_length: f64) {
-        self.solver.ode_mut().set_side_length(side_length);
+    fn fit_camera(&mut self) {
+        let radius = self.reach() as f32;
+        let aspect = self
+            .window_size
+            .get()
+            .map_or(1.0, |size| size.width as f32 / size.height as f32);
+        self.camera
+            .frame_bounding_sphere(na::Point3::origin(), radius, aspect);
     }
 
     fn diagonal_strip(gl: Arc<glow::Context>) -> GlLineStrip {
@@ -135,21 +337,29 @@ impl SpinningTop {
         )
     }
 
-    fn box_transform(&self) -> na::Matrix4<f32> {
-        let rotation = na::UnitQuaternion::new_normalize(na::Quaternion::new(
-            self.state.y[3] as f32,
-            self.state.y[4] as f32,
-            self.state.y[5] as f32,
-            self.state.y[6] as f32,
-        ));
+    fn rotation_transform(&self) -> na::Matrix4<f32> {
+        SpinningTopODE::rotation(&self.state)
+            .cast::<f32>()
+            .to_homogeneous()
+    }
+
+    /// The contact point is placed at the origin
+    fn body_transform(&self) -> na::Matrix4<f32> {
+        let contact_point = self.solver.ode().contact_point().map(|c| c as f32);
+
+        self.rotation_transform()
+            * na::Translation3::from(-contact_point).to_homogeneous()
+            * Self::body_model_transform(self.solver.ode().body())
+    }
 
-        let half_side_length = self.solver.ode().side_length() as f32 * 0.5;
+    /// Maps the diagonal strip onto the segment from the contact point through the center of mass
+    /// to the tip, which lies opposite to the contact point
+    fn diagonal_transform(&self) -> na::Matrix4<f32> {
+        let lever = self.solver.ode().lever().map(|c| c as f32);
 
-        let translation =
-            na::Translation3::new(half_side_length, half_side_length, half_side_length);
-        rotation.to_homogeneous()
-            * translation.to_homogeneous()
-            * na::Scale3::new(half_side_length, half_side_length, half_side_length).to_homogeneous()
+        self.rotation_transform()
+            * na::Translation3::from(lever).to_homogeneous()
+            * na::Scale3::from(lever).to_homogeneous()
     }
 
     fn draw_meshes(&self, aspect_ratio: f32) {
@@ -163,15 +373,10 @@ impl SpinningTop {
 
         self.meshes_program
             .uniform_3_f32_slice("eye_position", self.camera.position().coords.as_slice());
-        self.meshes_program
-            .uniform_3_f32_slice("light_position", Self::LIGHT_POSITION.as_slice());
-        self.meshes_program
-            .uniform_3_f32_slice("light_color", Self::LIGHT_COLOR.as_slice());
-        self.meshes_program
-            .uniform_3_f32_slice("ambient", Self::LIGHT_AMBIENT.as_slice());
+        self.light.set_uniforms(&self.meshes_program);
 
-        if self.show_box {
-            self.draw_box();
+        if self.show_body {
+            self.draw_body();
         }
 
         if self.show_plane {
@@ -179,18 +384,18 @@ impl SpinningTop {
         }
     }
 
-    fn draw_box(&self) {
+    fn draw_body(&self) {
         self.meshes_program
-            .uniform_4_f32_slice("material_color", Self::BOX_COLOR.as_slice());
+            .uniform_4_f32_slice("material_color", Self::BODY_COLOR.as_slice());
         self.meshes_program.uniform_f32("material_diffuse", 0.8);
         self.meshes_program.uniform_f32("material_specular", 0.4);
         self.meshes_program
             .uniform_f32("material_specular_exp", 10.0);
 
         self.meshes_program
-            .uniform_matrix_4_f32_slice("model_transform", self.box_transform().as_slice());
+            .uniform_matrix_4_f32_slice("model_transform", self.body_transform().as_slice());
 
-        self.box_mesh.draw();
+        self.body_mesh.draw();
     }
 
     fn draw_plane(&self) {
@@ -233,6 +438,10 @@ impl SpinningTop {
         if self.show_diagonal {
             self.draw_diagonal();
         }
+
+        if self.show_angular_momentum {
+            self.draw_angular_momentum();
+        }
     }
 
     fn draw_gravity_vector(&self) {
@@ -247,11 +456,50 @@ impl SpinningTop {
         self.trajectory_strip.draw();
     }
 
+    fn draw_angular_momentum(&self) {
+        self.strips_program
+            .uniform_4_f32("color", 1.0, 0.8, 0.2, 1.0);
+        self.angular_momentum_arrow.draw();
+    }
+
+    /// The arrow has the length of the body reach, only its direction follows `L`
+    fn update_angular_momentum_arrow(&mut self) {
+        let momentum = self
+            .solver
+            .ode()
+            .angular_momentum(&self.state)
+            .map(|c| c as f32);
+        let length = self.reach() as f32;
+
+        let Some(direction) = momentum.try_normalize(f32::EPSILON) else {
+            self.angular_momentum_arrow
+                .update_points(&[na::Point3::origin(); 6]);
+            return;
+        };
+
+        let tip = na::Point3::from(direction * length);
+        let side = direction
+            .cross(&na::Vector3::y())
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(na::Vector3::x());
+        let back = tip - direction * Self::ARROW_HEAD_SIZE * length;
+        let spread = side * 0.5 * Self::ARROW_HEAD_SIZE * length;
+
+        self.angular_momentum_arrow.update_points(&[
+            na::Point3::origin(),
+            tip,
+            tip,
+            back + spread,
+            tip,
+            back - spread,
+        ]);
+    }
+
     fn draw_diagonal(&self) {
         unsafe { self.gl.disable(glow::DEPTH_TEST) };
 
         self.strips_program
-            .uniform_matrix_4_f32_slice("model_transform", self.box_transform().as_slice());
+            .uniform_matrix_4_f32_slice("model_transform", self.diagonal_transform().as_slice());
         self.strips_program
             .uniform_4_f32("color", 0.5, 1.0, 0.5, 1.0);
         self.diagonal_strip.draw();
@@ -259,7 +507,9 @@ impl SpinningTop {
         unsafe { self.gl.enable(glow::DEPTH_TEST) };
     }
 
-    fn step_update(&mut self) {
+    /// Returns the new tip of the top, its trajectory is updated by the caller
+    fn step_update(&mut self) -> na::Point3<f32> {
+        self.step_count += 1;
         let mut new_state = self.solver.step(&self.state);
         let new_rotation = na::UnitQuaternion::new_normalize(na::Quaternion::new(
             new_state.y[3],
@@ -275,24 +525,80 @@ impl SpinningTop {
 
         self.state = new_state;
 
-        let new_tip = self
-            .box_transform()
-            .transform_point(&na::point![1.0, 1.0, 1.0]);
+        let new_tip = self.rotation_transform().transform_point(&na::Point3::from(
+            2.0 * self.solver.ode().lever().map(|c| c as f32),
+        ));
 
-        self.trajectory_strip.push_vertex(&new_tip);
+        self.trajectory.push([
+            self.state.t,
+            new_tip.x as f64,
+            new_tip.y as f64,
+            new_tip.z as f64,
+        ]);
+        self.update_angular_momentum_arrow();
+
+        let (precession, nutation) = self.solver.ode().axis_angles(&self.state);
+        let t = self.state.t;
+
+        for (history, value) in [
+            (
+                &mut self.energy_history,
+                self.solver.ode().energy(&self.state),
+            ),
+            (&mut self.precession_history, precession),
+            (&mut self.nutation_history, nutation),
+        ] {
+            if history.len() == Self::MAX_HISTORY {
+                history.pop_front();
+            }
+
+            history.push_back([t, value]);
+        }
+
+        new_tip
     }
 }
 
 impl Presenter for SpinningTop {
     fn show_side_ui(&mut self, ui: &mut Ui) {
+        ui.collapsing("Camera", |ui| widgets::camera_ui(ui, &mut self.camera));
+        ui.collapsing("Light", |ui| self.light.ui(ui));
+        if ui.button("Fit to scene").clicked() {
+            self.fit_camera();
+        }
+
+        if let Some(path) = self.stl_export.ui(ui, "Export STL") {
+            let mesh =
+                Self::body_mesh(self.solver.ode().body()).transformed(&self.body_transform());
+            let result = export::export_stl(&path, &mesh);
+            self.stl_export.report(result);
+        }
+
+        if let Some(path) = self.trajectory_export.ui(ui, "Export trajectory CSV") {
+            let result = self.trajectory.save_csv(&path);
+            self.trajectory_export.report(result);
+        }
+
         ui.checkbox(&mut self.solver.ode_mut().enable_gravity, "Gravity");
         ui.add(DragValue::new(&mut self.solver.ode_mut().gravity.y).clamp_range(f64::MIN..=0.0));
 
+        ui.checkbox(&mut self.solver.ode_mut().enable_friction, "Friction");
+        ui.add(
+            Slider::new(&mut self.solver.ode_mut().friction, 0.0..=10.0)
+                .logarithmic(true)
+                .text("Friction coefficient"),
+        );
+
         ui.checkbox(&mut self.show_plane, "Show plane");
         ui.checkbox(&mut self.show_gravity_vector, "Show gravity vector");
-        ui.checkbox(&mut self.show_box, "Show box");
+        ui.checkbox(&mut self.show_body, "Show body");
         ui.checkbox(&mut self.show_trajectory, "Show trajectory");
         ui.checkbox(&mut self.show_diagonal, "Show diagonal");
+        ui.checkbox(&mut self.show_angular_momentum, "Show angular momentum");
+        ui.label(format!(
+            "|L| = {:.3}",
+            self.solver.ode().angular_momentum(&self.state).norm()
+        ));
 
         ui.label("Maximum trajectory points visible");
         if ui
@@ -304,10 +610,11 @@ impl Presenter for SpinningTop {
         {
             self.trajectory_strip
                 .recapacitate(self.max_trajectory_points);
+            self.trajectory.set_capacity(self.max_trajectory_points);
         }
 
         let mut density = self.solver.ode().density();
-        ui.label("Box density");
+        ui.label("Density");
         if ui
             .add(DragValue::new(&mut density).clamp_range(0.1..=f32::MAX))
             .changed()
@@ -315,18 +622,17 @@ impl Presenter for SpinningTop {
             self.solver.ode.set_density(density);
         }
 
-        let mut side_length = self.solver.ode().side_length();
-        ui.label("Side length");
-        if ui
-            .add(
-                DragValue::new(&mut side_length)
-                    .clamp_range(0.1..=f64::MAX)
-                    .speed(0.01),
-            )
-            .changed()
-        {
-            self.set_side_length(side_length);
-        }
+        self.body_ui(ui);
+
+        ui.collapsing("Inertia tensor", |ui| {
+            widgets::matrix_grid(ui, "inertia_tensor", self.solver.ode().inertia().matrix());
+            ui.label("Inverse");
+            widgets::matrix_grid(
+                ui,
+                "inverse_inertia_tensor",
+                self.solver.ode().inertia().inverse_matrix(),
+            );
+        });
 
         ui.label("Simulation speed");
         ui.add(
@@ -344,11 +650,39 @@ impl Presenter for SpinningTop {
     }
 
     fn show_bottom_ui(&mut self, ui: &mut Ui) {
-        ui.label("Bottom text");
+        ui.label("Kinetic energy");
+        Plot::new("Kinetic energy graph")
+            .view_aspect(10.0)
+            .auto_bounds_x()
+            .auto_bounds_y()
+            .show(ui, |plot_ui| {
+                plot_ui.line(
+                    Line::new(self.energy_history.iter().copied().collect::<Vec<_>>())
+                        .name("Kinetic energy"),
+                )
+            });
+
+        ui.label("Precession and nutation [rad]");
+        Plot::new("Axis angles graph")
+            .view_aspect(10.0)
+            .auto_bounds_x()
+            .auto_bounds_y()
+            .legend(Legend::default().position(Corner::LeftTop))
+            .show(ui, |plot_ui| {
+                plot_ui.line(
+                    Line::new(self.precession_history.iter().copied().collect::<Vec<_>>())
+                        .name("Precession"),
+                );
+                plot_ui.line(
+                    Line::new(self.nutation_history.iter().copied().collect::<Vec<_>>())
+                        .name("Nutation"),
+                );
+            });
     }
 
     fn draw(&self, size: Option<egui_winit::winit::dpi::PhysicalSize<u32>>) {
         let Some(size) = size else { return };
+        self.window_size.set(Some(size));
         let aspect_ratio = size.width as f32 / size.height as f32;
 
         self.draw_meshes(aspect_ratio);
@@ -359,30 +693,143 @@ impl Presenter for SpinningTop {
         let elapsed_t = delta.as_secs_f64() * self.simulation_speed;
         self.exact_t += elapsed_t;
 
+        let mut new_tips = Vec::new();
         while self.exact_t > self.state.t {
-            self.step_update();
+            new_tips.push(self.step_update());
         }
+
+        self.trajectory_strip.push_vertices(&new_tips);
     }
 
     fn update_mouse(&mut self, state: MouseState) {
         self.camera.update_from_mouse(state);
     }
 
+    fn update_keyboard(&mut self, state: KeyboardState, delta: std::time::Duration) {
+        self.camera.update_from_keyboard(&state, delta);
+    }
+
+    fn step_count(&self) -> Option<usize> {
+        Some(self.step_count)
+    }
+
     fn name(&self) -> &'static str {
         "Spinning Top"
     }
 }
 
-#[derive(Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum ContactChoice {
+    /// Corner of the default cube, bits of the index select the far side on each axis
+    Corner(usize),
+    Custom,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct SpinningTopBuilder {
     tilt: f64,
     angular_velocity: f64,
+    contact: ContactChoice,
+    custom_contact: na::Vector3<f64>,
+}
+
+impl Default for SpinningTopBuilder {
+    fn default() -> Self {
+        Self {
+            tilt: 0.0,
+            angular_velocity: 0.0,
+            contact: ContactChoice::Corner(0),
+            custom_contact: na::Vector3::zeros(),
+        }
+    }
 }
 
 impl SpinningTopBuilder {
     pub fn new() -> Self {
         Self::default()
     }
+
+    fn default_body() -> TopBody {
+        TopBody::Cube {
+            side_length: SpinningTop::DEFAULT_SIDE_LENGTH,
+        }
+    }
+
+    fn corner(index: usize) -> na::Vector3<f64> {
+        na::Vector3::from_fn(|i, _| ((index >> i) & 1) as f64 * SpinningTop::DEFAULT_SIDE_LENGTH)
+    }
+
+    fn contact_point(&self) -> na::Vector3<f64> {
+        match self.contact {
+            ContactChoice::Corner(index) => Self::corner(index),
+            ContactChoice::Custom => self.custom_contact,
+        }
+    }
+
+    /// Rotation, angular velocity and contact point of the top
+    fn initial_conditions(&self) -> (na::UnitQuaternion<f64>, na::Vector3<f64>, na::Vector3<f64>) {
+        let body = Self::default_body();
+        let contact_point = Some(self.contact_point())
+            .filter(|point| body.contains(point))
+            .unwrap_or_else(na::Vector3::zeros);
+
+        // The center of mass is placed straight above the contact point before tilting
+        let up = (body.center() - contact_point)
+            .try_normalize(f64::EPSILON)
+            .unwrap_or(na::Vector3::y());
+        let upright_angle = up.angle(&na::Vector3::y());
+        let axis = na::UnitVector3::try_new(up.cross(&na::Vector3::y()), f64::EPSILON)
+            .unwrap_or(na::Vector3::x_axis());
+        let rotation =
+            na::UnitQuaternion::from_axis_angle(&axis, upright_angle + self.tilt.to_radians());
+
+        let angular_velocity = self.angular_velocity * up;
+
+        (rotation, angular_velocity, contact_point)
+    }
+
+    fn contact_ui(&mut self, ui: &mut Ui) -> egui::Response {
+        let format = |contact: ContactChoice| match contact {
+            ContactChoice::Corner(index) => {
+                let corner = Self::corner(index);
+                format!("Corner ({}, {}, {})", corner.x, corner.y, corner.z)
+            }
+            ContactChoice::Custom => String::from("Custom"),
+        };
+
+        let mut response = egui::ComboBox::from_label("Contact point")
+            .selected_text(format(self.contact))
+            .show_ui(ui, |ui| {
+                for contact in (0..8)
+                    .map(ContactChoice::Corner)
+                    .chain([ContactChoice::Custom])
+                {
+                    ui.selectable_value(&mut self.contact, contact, format(contact));
+                }
+            })
+            .response;
+
+        if self.contact == ContactChoice::Custom {
+            response |= widgets::vector_drag(
+                ui,
+                &mut self.custom_contact,
+                f64::MIN,
+                f64::MAX,
+                "",
+                0.01,
+                &["x", "y", "z"],
+            );
+
+            if !Self::default_body().contains(&self.custom_contact) {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    "The contact point has to lie within the body",
+                );
+            }
+        }
+
+        response
+    }
 }
 
 impl PresenterBuilder for SpinningTopBuilder {
@@ -399,18 +846,64 @@ impl PresenterBuilder for SpinningTopBuilder {
                     .clamp_range(0.0..=f64::MAX)
                     .speed(0.01),
             )
+            | self.contact_ui(ui)
     }
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
-        let diagonal_angle = std::f64::consts::FRAC_PI_2 - f64::atan2(1.0, f64::sqrt(2.0));
-        let axis = na::UnitVector3::new_normalize(na::vector![-1.0, 0.0, 1.0]);
-        let rotation =
-            na::UnitQuaternion::from_axis_angle(&axis, diagonal_angle + self.tilt.to_radians());
+    fn build(&self, gl: Arc<glow::Context>, camera_settings: CameraSettings) -> Box<dyn Presenter> {
+        let (rotation, angular_velocity, contact_point) = self.initial_conditions();
 
-        let angular_velocity = self.angular_velocity
-            * na::Rotation3::from_axis_angle(&axis, std::f64::consts::FRAC_PI_2 - diagonal_angle)
-                .transform_vector(&na::vector![1.0, 0.0, 1.0]);
+        Box::new(SpinningTop::new(
+            gl,
+            camera_settings,
+            rotation,
+            angular_velocity,
+            contact_point,
+        ))
+    }
 
-        Box::new(SpinningTop::new(gl, rotation, angular_velocity))
+    fn rebuild(
+        &self,
+        presenter: &mut Box<dyn Presenter>,
+        gl: Arc<glow::Context>,
+        camera_settings: CameraSettings,
+    ) {
+        match (presenter.as_mut() as &mut dyn Any).downcast_mut::<SpinningTop>() {
+            Some(top) => {
+                let (rotation, angular_velocity, contact_point) = self.initial_conditions();
+                top.reset(
+                    Self::default_body(),
+                    rotation,
+                    angular_velocity,
+                    contact_point,
+                );
+            }
+            None => *presenter = self.build(gl, camera_settings),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), json::Error> {
+        json::save(path, self)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), json::Error> {
+        *self = json::load(path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_round_trip() {
+        for contact in [ContactChoice::Corner(5), ContactChoice::Custom] {
+            json::assert_round_trip(&SpinningTopBuilder {
+                tilt: 0.4,
+                angular_velocity: 12.0,
+                contact,
+                custom_contact: na::vector![0.25, -0.5, 1.0],
+            });
+        }
     }
 }
@@ -6,8 +6,13 @@ use super::{
     Presenter, PresenterBuilder,
 };
 use crate::{
-    controls::mouse::MouseState,
-    numerics::EulerODESolver,
+    controls::{camera::CameraSettings, keyboard::KeyboardState, mouse::MouseState},
+    json,
+    numerics::{
+        ode::{SolverWithDelta, State},
+        EulerODESolver, Heun, RungeKuttaIV,
+    },
+    recording::{Replay, Replayable},
     render::{
         gl_drawable::GlDrawable,
         gl_mesh::GlTriangleMesh,
@@ -15,12 +20,14 @@ use crate::{
         mesh::{Mesh, Triangle},
     },
     simulators::spring::{self, SpringODE, SpringState},
+    ui::widgets::PathAction,
 };
-use egui::{containers::ComboBox, Rgba, Slider, Ui};
-use egui_plot::{Corner, Legend, Line, Plot, PlotPoints};
+use egui::{containers::ComboBox, Checkbox, Rgba, Slider, Ui};
+use egui_plot::{Corner, Legend, Line, LineStyle, Plot, PlotPoints};
 use itertools::Itertools;
 use nalgebra as na;
-use std::{f64::consts::PI, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{any::Any, f64::consts::PI, path::Path, sync::Arc};
 
 macro_rules! state_graph {
     ($states:expr, $field:ident) => {
@@ -31,48 +38,117 @@ macro_rules! state_graph {
     };
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Integrator {
+    Euler,
+    Heun,
+    RungeKutta,
+}
+
+impl Integrator {
+    const ALL: [Integrator; 3] = [Integrator::Euler, Integrator::Heun, Integrator::RungeKutta];
+
+    fn name(self) -> &'static str {
+        match self {
+            Integrator::Euler => "Euler",
+            Integrator::Heun => "Heun",
+            Integrator::RungeKutta => "Runge-Kutta IV",
+        }
+    }
+
+    fn solver(self, delta: f64, ode: SpringODE) -> Box<dyn SolverWithDelta<2, SpringODE>> {
+        match self {
+            Integrator::Euler => Box::new(EulerODESolver::new(delta, ode)),
+            Integrator::Heun => Box::new(Heun::new(delta, ode)),
+            Integrator::RungeKutta => Box::new(RungeKuttaIV::new(delta, ode)),
+        }
+    }
+}
+
 pub struct Spring {
     gl_program: GlProgram,
     rect_mesh: GlTriangleMesh,
 
     simulation_speed: spring::F,
     pending_steps: spring::F,
-    euler: EulerODESolver<spring::F, 2, SpringODE>,
+    step_count: usize,
+    integrator: Integrator,
+    solver: Box<dyn SolverWithDelta<2, SpringODE>>,
+    state: State<2>,
     states: Vec<SpringState>,
+    show_analytic: bool,
     selectable_external_forces: Vec<Box<dyn ParametrizableFunction<F = spring::F>>>,
     selectable_equilibriums: Vec<Box<dyn ParametrizableFunction<F = spring::F>>>,
     selected_external_force_idx: usize,
     selected_equilibrium_idx: usize,
     last_clear_t: spring::F,
+    csv_export: PathAction,
+    replay: Replay<(State<2>, SpringState)>,
+    /// State shown while scrubbing through the replay
+    replayed: Option<SpringState>,
 }
 
 impl Spring {
+    const MAX_REPLAY_FRAMES: usize = 10000;
+
     pub fn new(gl: Arc<glow::Context>, position: spring::F, velocity: spring::F) -> Self {
-        let ode = SpringODE::new(
-            1.0,
-            Box::new(|_| 0.0),
-            position,
-            velocity,
-            1.0,
-            0.2,
-            Box::new(|_| 0.0),
-        );
+        let ode = SpringODE::new(1.0, Box::new(|_| 0.0), 1.0, 0.2, Box::new(|_| 0.0));
+        let state = SpringODE::initial_state(position, velocity);
+        let integrator = Integrator::Euler;
 
         Spring {
-            states: vec![ode.state()],
+            states: vec![ode.state(&state)],
+            show_analytic: false,
             rect_mesh: Self::create_rect_mesh(Arc::clone(&gl)),
             gl_program: GlProgram::vertex_fragment(gl, "2d_vert", "pass_frag"),
-            simulation_speed: 0.1,
+            simulation_speed: 1.0,
             pending_steps: 1.0,
-            euler: EulerODESolver::new(0.01, ode),
+            step_count: 0,
+            integrator,
+            solver: integrator.solver(0.01, ode),
+            state,
             selectable_external_forces: Self::create_selectable_functions(),
             selectable_equilibriums: Self::create_selectable_functions(),
             selected_external_force_idx: 0,
             selected_equilibrium_idx: 0,
             last_clear_t: 0.0,
+            csv_export: PathAction::new("spring.csv"),
+            replay: Replay::new(Self::MAX_REPLAY_FRAMES),
+            replayed: None,
         }
     }
 
+    /// Restarts the simulation, keeping GL resources and the chosen functions
+    pub fn reset(&mut self, position: spring::F, velocity: spring::F) {
+        self.state = SpringODE::initial_state(position, velocity);
+        self.states = vec![self.solver.ode().state(&self.state)];
+        self.pending_steps = 1.0;
+        self.last_clear_t = 0.0;
+        self.step_count = 0;
+        self.replay.clear();
+        self.replayed = None;
+    }
+
+    /// One row per state, columns are named after the fields of `SpringState`
+    fn states_csv(&self) -> String {
+        let mut csv = String::new();
+
+        if let Some(state) = self.states.first() {
+            csv += &state.iter().iter().map(|(name, _)| *name).join(",");
+            csv.push('\n');
+        }
+
+        for state in &self.states {
+            csv += &state.iter().iter().map(|(_, val)| val).join(",");
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Index of `ConstantFunction` in `create_selectable_functions`
+    const CONSTANT_FUNCTION_IDX: usize = 0;
+
     fn create_selectable_functions() -> Vec<Box<dyn ParametrizableFunction<F = spring::F>>> {
         let functions: Vec<Box<dyn ParametrizableFunction<F = spring::F>>> = vec![
             Box::new(ConstantFunction::new(
@@ -130,6 +206,10 @@ impl Spring {
             .color(Rgba::from_rgb(0.75, 0.25, 0.25))
             .name("Acceleration");
 
+        let analytic = (self.show_analytic && self.analytic_available())
+            .then(|| self.analytic_graph())
+            .flatten();
+
         ui.label("Kinematics");
         Plot::new("Kinematics graph")
             .data_aspect(self.bottom_data_aspect())
@@ -141,6 +221,10 @@ impl Spring {
                 plot_ui.line(position);
                 plot_ui.line(velocity);
                 plot_ui.line(acceleration);
+
+                if let Some(analytic) = analytic {
+                    plot_ui.line(analytic);
+                }
             });
     }
 
@@ -225,7 +309,7 @@ impl Spring {
     }
 
     fn current_state(&self) -> Option<&SpringState> {
-        self.states.last()
+        self.replayed.as_ref().or(self.states.last())
     }
 
     fn show_info(&self, ui: &mut Ui) {
@@ -239,7 +323,7 @@ impl Spring {
     }
 
     fn parameters_ui(&mut self, ui: &mut Ui) {
-        let ode = &mut self.euler.ode;
+        let ode = self.solver.ode_mut();
         ui.add(
             Slider::new(&mut ode.mass, 0.01..=10.0)
                 .logarithmic(true)
@@ -252,6 +336,12 @@ impl Spring {
                 .text("Spring constant"),
         );
 
+        ui.checkbox(&mut ode.enable_cubic, "Cubic spring force");
+        ui.add_enabled(
+            ode.enable_cubic,
+            Slider::new(&mut ode.cubic_constant, -5.0..=5.0).text("Cubic constant"),
+        );
+
         ui.add(
             Slider::new(&mut ode.damping_factor, 0.01..=5.0)
                 .logarithmic(true)
@@ -259,7 +349,7 @@ impl Spring {
         );
 
         ui.add(
-            Slider::new(&mut self.euler.delta, 0.001..=0.1)
+            Slider::new(self.solver.delta_mut(), 0.001..=0.1)
                 .logarithmic(true)
                 .text("Delta"),
         );
@@ -271,6 +361,75 @@ impl Spring {
         );
     }
 
+    fn analysis_ui(&self, ui: &mut Ui) {
+        let ode = self.solver.ode();
+
+        ui.collapsing("Analysis", |ui| {
+            ui.label(format!(
+                "Natural frequency: {:.5} rad/s",
+                ode.natural_frequency()
+            ));
+            ui.label(format!("Damping ratio: {:.5}", ode.damping_ratio()));
+            ui.label(ode.damping_kind().name());
+        });
+    }
+
+    fn integrator_selection(&mut self, ui: &mut Ui) {
+        let previous = self.integrator;
+
+        ComboBox::from_label("Integrator")
+            .selected_text(self.integrator.name())
+            .show_ui(ui, |ui| {
+                for integrator in Integrator::ALL {
+                    ui.selectable_value(&mut self.integrator, integrator, integrator.name());
+                }
+            });
+
+        if self.integrator != previous {
+            let delta = self.solver.delta();
+            // Solvers own the ODE, the placeholder is dropped together with the old solver
+            let ode = self.solver.replace_ode(SpringODE::new(
+                1.0,
+                Box::new(|_| 0.0),
+                0.0,
+                0.0,
+                Box::new(|_| 0.0),
+            ));
+            self.solver = self.integrator.solver(delta, ode);
+        }
+
+        ui.add_enabled(
+            self.analytic_available(),
+            Checkbox::new(&mut self.show_analytic, "Show analytic solution"),
+        )
+        .on_disabled_hover_text("Requires a linear spring with constant force and equilibrium");
+    }
+
+    /// The closed-form solution only holds for the linear spring with constant forcing
+    fn analytic_available(&self) -> bool {
+        !self.solver.ode().enable_cubic
+            && self.selected_external_force_idx == Self::CONSTANT_FUNCTION_IDX
+            && self.selected_equilibrium_idx == Self::CONSTANT_FUNCTION_IDX
+    }
+
+    /// Closed-form solution starting from the oldest recorded state
+    fn analytic_graph(&self) -> Option<Line> {
+        let first = self.states.first()?;
+        let ode = self.solver.ode();
+
+        Some(
+            Line::new(
+                self.states
+                    .iter()
+                    .map(|s| [s.t, ode.analytic_position(first, s.t)])
+                    .collect_vec(),
+            )
+            .color(Rgba::from_rgb(0.75, 0.75, 0.75))
+            .style(LineStyle::dashed_dense())
+            .name("Analytic position"),
+        )
+    }
+
     fn current_external_force(&self) -> &dyn ParametrizableFunction<F = spring::F> {
         self.selectable_external_forces[self.selected_external_force_idx].as_ref()
     }
@@ -304,7 +463,7 @@ impl Spring {
             });
 
         if changed {
-            self.euler.ode.external_force = self.current_external_force().produce_closure();
+            self.solver.ode_mut().external_force = self.current_external_force().produce_closure();
         }
     }
 
@@ -325,7 +484,7 @@ impl Spring {
             });
 
         if changed {
-            self.euler.ode.equilibrium = self.current_equilibrium().produce_closure();
+            self.solver.ode_mut().equilibrium = self.current_equilibrium().produce_closure();
         }
     }
 
@@ -338,13 +497,43 @@ impl Spring {
     fn clear(&mut self) {
         self.states.clear()
     }
+
+    fn replay_ui(&mut self, ui: &mut Ui) {
+        if let Some(frame) = self.replay.ui(ui) {
+            self.restore(&frame);
+        }
+    }
+
+    fn export_ui(&mut self, ui: &mut Ui) {
+        if let Some(path) = self.csv_export.ui(ui, "Export CSV") {
+            let result = std::fs::write(path, self.states_csv());
+            self.csv_export.report(result);
+        }
+    }
+}
+
+impl Replayable for Spring {
+    type Snapshot = (State<2>, SpringState);
+
+    fn snapshot(&self) -> Self::Snapshot {
+        (self.state, self.solver.ode().state(&self.state))
+    }
+
+    fn restore(&mut self, (state, spring_state): &Self::Snapshot) {
+        self.state = *state;
+        self.replayed = Some(spring_state.clone());
+    }
 }
 
 impl Presenter for Spring {
     fn show_side_ui(&mut self, ui: &mut Ui) {
         self.clear_graphs_ui(ui);
+        self.replay_ui(ui);
+        self.export_ui(ui);
         self.show_info(ui);
         self.parameters_ui(ui);
+        self.analysis_ui(ui);
+        self.integrator_selection(ui);
         self.force_selection(ui);
         self.equilibrium_selection(ui);
         ui.vertical_centered(|ui| {
@@ -364,7 +553,7 @@ impl Presenter for Spring {
         let Some(size) = size else { return };
         let aspect_ratio = size.width as f32 / size.height as f32;
 
-        let Some(state) = self.states.last() else {
+        let Some(state) = self.current_state() else {
             return;
         };
 
@@ -411,17 +600,31 @@ impl Presenter for Spring {
         self.rect_mesh.draw();
     }
 
-    fn update(&mut self, _delta: std::time::Duration) {
-        self.pending_steps += self.simulation_speed / self.euler.delta;
+    fn update(&mut self, delta: std::time::Duration) {
+        if let Some(live) = self.replay.resume() {
+            self.restore(&live);
+        }
+        self.replayed = None;
+
+        // Simulation speed is simulated time per second
+        self.pending_steps += delta.as_secs_f64() * self.simulation_speed / self.solver.delta();
 
         let steps_to_do = self.pending_steps.trunc() as usize;
         self.pending_steps = self.pending_steps.fract();
+        self.step_count += steps_to_do;
 
         self.states.reserve(steps_to_do);
         for _ in 0..steps_to_do {
-            self.euler.step();
-            self.states.push(self.euler.ode.state());
+            self.state = self.solver.step(&self.state);
+            let state = self.solver.ode().state(&self.state);
+            self.states.push(state);
         }
+
+        self.replay.record(self.snapshot());
+    }
+
+    fn step_count(&self) -> Option<usize> {
+        Some(self.step_count)
     }
 
     fn name(&self) -> &'static str {
@@ -429,8 +632,11 @@ impl Presenter for Spring {
     }
 
     fn update_mouse(&mut self, _state: MouseState) {}
+
+    fn update_keyboard(&mut self, _state: KeyboardState, _delta: std::time::Duration) {}
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct SpringBuilder {
     velocity: spring::F,
     position: spring::F,
@@ -448,9 +654,34 @@ impl PresenterBuilder for SpringBuilder {
             | ui.add(Slider::new(&mut self.velocity, -10.0..=10.0).text("Velocity"))
     }
 
-    fn build(&self, gl: Arc<glow::Context>) -> Box<dyn Presenter> {
+    fn build(
+        &self,
+        gl: Arc<glow::Context>,
+        _camera_settings: CameraSettings,
+    ) -> Box<dyn Presenter> {
         Box::new(Spring::new(gl, self.position, self.velocity))
     }
+
+    fn rebuild(
+        &self,
+        presenter: &mut Box<dyn Presenter>,
+        gl: Arc<glow::Context>,
+        camera_settings: CameraSettings,
+    ) {
+        match (presenter.as_mut() as &mut dyn Any).downcast_mut::<Spring>() {
+            Some(spring) => spring.reset(self.position, self.velocity),
+            None => *presenter = self.build(gl, camera_settings),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), json::Error> {
+        json::save(path, self)
+    }
+
+    fn load(&mut self, path: &Path) -> Result<(), json::Error> {
+        *self = json::load(path)?;
+        Ok(())
+    }
 }
 
 impl Default for SpringBuilder {
@@ -461,3 +692,16 @@ impl Default for SpringBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_round_trip() {
+        json::assert_round_trip(&SpringBuilder {
+            velocity: -2.5,
+            position: 1.25,
+        });
+    }
+}
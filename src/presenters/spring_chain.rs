@@ -0,0 +1,318 @@
+use super::{Presenter, PresenterBuilder};
+use crate::{
+    controls::{camera::CameraSettings, keyboard::KeyboardState, mouse::MouseState},
+    numerics::{
+        ode::{Solver, State},
+        RungeKuttaIV,
+    },
+    render::{
+        gl_drawable::GlDrawable,
+        gl_mesh::GlTriangleMesh,
+        gl_program::GlProgram,
+        mesh::{Mesh, Triangle},
+    },
+    simulators::spring_chain::{SpringChainODE, DIM, MAX_MASSES},
+};
+use egui::{widgets::DragValue, Grid, Slider, Ui};
+use egui_plot::{Corner, Legend, Line, Plot};
+use nalgebra as na;
+use std::{collections::VecDeque, sync::Arc};
+
+pub struct SpringChain {
+    gl_program: GlProgram,
+    rect_mesh: GlTriangleMesh,
+
+    state: State<DIM>,
+    solver: RungeKuttaIV<DIM, SpringChainODE>,
+    simulation_speed: f64,
+    exact_t: f64,
+    step_count: usize,
+
+    position_history: Vec<VecDeque<[f64; 2]>>,
+}
+
+impl SpringChain {
+    const MAX_HISTORY: usize = 10000;
+    const WALL_X: f32 = -1.0;
+    const CHAIN_LENGTH: f32 = 1.8;
+    const BOX_SIZE: f32 = 0.1;
+    const SPRING_WIDTH: f32 = 0.02;
+    /// Displacement of one unit in screen coordinates
+    const DISPLACEMENT_SCALE: f32 = 0.1;
+
+    pub fn new(gl: Arc<glow::Context>, count: usize, displacement: f64) -> Self {
+        let ode = SpringChainODE::new(count);
+        let mut state = State {
+            t: 0.0,
+            y: na::SVector::zeros(),
+        };
+        state.y[ode.count() - 1] = displacement;
+
+        Self {
+            rect_mesh: Self::create_rect_mesh(Arc::clone(&gl)),
+            gl_program: GlProgram::vertex_fragment(gl, "2d_vert", "pass_frag"),
+
+            state,
+            solver: RungeKuttaIV::new(0.01, ode),
+            simulation_speed: 1.0,
+            exact_t: 0.0,
+            step_count: 0,
+
+            position_history: vec![VecDeque::new(); MAX_MASSES],
+        }
+    }
+
+    fn create_rect_mesh(gl: Arc<glow::Context>) -> GlTriangleMesh {
+        // 0 1
+        // 3 2
+        let mesh = Mesh::new(
+            vec![
+                na::point!(-0.5, 0.5, 0.0),
+                na::point!(0.5, 0.5, 0.0),
+                na::point!(0.5, -0.5, 0.0),
+                na::point!(-0.5, -0.5, 0.0),
+            ],
+            vec![Triangle([2, 1, 0]), Triangle([3, 2, 0])],
+        );
+        GlTriangleMesh::new(gl, &mesh)
+    }
+
+    fn spacing(&self) -> f32 {
+        Self::CHAIN_LENGTH / self.solver.ode().count() as f32
+    }
+
+    fn box_x(&self, index: usize) -> f32 {
+        Self::WALL_X
+            + (index + 1) as f32 * self.spacing()
+            + SpringChainODE::position(&self.state, index) as f32 * Self::DISPLACEMENT_SCALE
+    }
+
+    fn draw_rect(&self, center: na::Point2<f32>, size: na::Vector2<f32>) {
+        self.gl_program.uniform_matrix_4_f32_slice(
+            "model_transform",
+            (na::Translation3::new(center.x, center.y, 0.0).to_homogeneous()
+                * na::Scale3::new(size.x, size.y, 1.0).to_homogeneous())
+            .as_slice(),
+        );
+        self.rect_mesh.draw();
+    }
+
+    fn step_update(&mut self) {
+        self.step_count += 1;
+        self.state = self.solver.step(&self.state);
+
+        for (index, history) in self
+            .position_history
+            .iter_mut()
+            .enumerate()
+            .take(self.solver.ode().count())
+        {
+            if history.len() == Self::MAX_HISTORY {
+                history.pop_front();
+            }
+
+            history.push_back([self.state.t, SpringChainODE::position(&self.state, index)]);
+        }
+    }
+
+    fn set_count(&mut self, count: usize) {
+        // Masses beyond the count are not simulated, so new ones start at rest
+        for index in self.solver.ode().count()..MAX_MASSES {
+            self.state.y[index] = 0.0;
+            self.state.y[MAX_MASSES + index] = 0.0;
+        }
+
+        self.solver.ode_mut().set_count(count);
+        self.position_history.iter_mut().for_each(VecDeque::clear);
+    }
+
+    fn parameters_ui(&mut self, ui: &mut Ui) {
+        Grid::new("spring_chain_parameters")
+            .num_columns(3)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("Mass");
+                ui.label("Spring constant");
+                ui.end_row();
+
+                let ode = self.solver.ode_mut();
+                for index in 0..ode.count() {
+                    ui.label(format!("{}", index + 1));
+                    ui.add(
+                        DragValue::new(&mut ode.masses_mut()[index])
+                            .clamp_range(0.01..=100.0)
+                            .speed(0.01),
+                    );
+                    ui.add(
+                        DragValue::new(&mut ode.spring_constants_mut()[index])
+                            .clamp_range(0.0..=100.0)
+                            .speed(0.01),
+                    );
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+impl Presenter for SpringChain {
+    fn show_side_ui(&mut self, ui: &mut Ui) {
+        let mut count = self.solver.ode().count();
+        ui.label("Masses");
+        if ui
+            .add(DragValue::new(&mut count).clamp_range(1..=MAX_MASSES))
+            .changed()
+        {
+            self.set_count(count);
+        }
+
+        self.parameters_ui(ui);
+
+        ui.add(
+            Slider::new(&mut self.solver.ode_mut().damping_factor, 0.0..=5.0)
+                .text("Damping factor"),
+        );
+
+        ui.label(format!(
+            "Energy: {:.4}",
+            self.solver.ode().energy(&self.state)
+        ));
+
+        ui.label("Simulation speed");
+        ui.add(
+            DragValue::new(&mut self.simulation_speed)
+                .clamp_range(0.0..=f64::MAX)
+                .speed(0.01),
+        );
+
+        ui.label("Integration step");
+        ui.add(
+            DragValue::new(&mut self.solver.delta)
+                .clamp_range(0.001..=f64::MAX)
+                .speed(0.001),
+        );
+    }
+
+    fn show_bottom_ui(&mut self, ui: &mut Ui) {
+        ui.label("Displacements");
+        Plot::new("Displacements graph")
+            .view_aspect(10.0)
+            .auto_bounds_x()
+            .auto_bounds_y()
+            .legend(Legend::default().position(Corner::LeftTop))
+            .show(ui, |plot_ui| {
+                for (index, history) in self
+                    .position_history
+                    .iter()
+                    .enumerate()
+                    .take(self.solver.ode().count())
+                {
+                    plot_ui.line(
+                        Line::new(history.iter().copied().collect::<Vec<_>>())
+                            .name(format!("Mass {}", index + 1)),
+                    );
+                }
+            });
+    }
+
+    fn draw(&self, size: Option<egui_winit::winit::dpi::PhysicalSize<u32>>) {
+        let Some(size) = size else { return };
+        let aspect_ratio = size.width as f32 / size.height as f32;
+
+        self.gl_program.enable();
+        self.gl_program.uniform_matrix_4_f32_slice(
+            "view_transform",
+            na::matrix![
+                1.0 / aspect_ratio, 0.0, 0.0, 0.0;
+                0.0, 1.0, 0.0, 0.0;
+                0.0, 0.0, 1.0, 0.0;
+                0.0, 0.0, 0.0, 1.0;
+            ]
+            .as_slice(),
+        );
+
+        // Wall
+        self.draw_rect(na::point![Self::WALL_X - 0.05, 0.0], na::vector![0.1, 2.0]);
+
+        let mut attachment = Self::WALL_X;
+        for index in 0..self.solver.ode().count() {
+            let x = self.box_x(index);
+            let left = x - 0.5 * Self::BOX_SIZE;
+
+            // Spring
+            self.draw_rect(
+                na::point![0.5 * (attachment + left), 0.0],
+                na::vector![left - attachment, Self::SPRING_WIDTH],
+            );
+
+            // Box
+            self.draw_rect(
+                na::point![x, 0.0],
+                na::vector![Self::BOX_SIZE, Self::BOX_SIZE],
+            );
+
+            attachment = x + 0.5 * Self::BOX_SIZE;
+        }
+    }
+
+    fn update(&mut self, delta: std::time::Duration) {
+        self.exact_t += delta.as_secs_f64() * self.simulation_speed;
+
+        while self.exact_t > self.state.t {
+            self.step_update();
+        }
+    }
+
+    fn step_count(&self) -> Option<usize> {
+        Some(self.step_count)
+    }
+
+    fn name(&self) -> &'static str {
+        "Spring chain"
+    }
+
+    fn update_mouse(&mut self, _state: MouseState) {}
+
+    fn update_keyboard(&mut self, _state: KeyboardState, _delta: std::time::Duration) {}
+}
+
+pub struct SpringChainBuilder {
+    count: usize,
+    displacement: f64,
+}
+
+impl SpringChainBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PresenterBuilder for SpringChainBuilder {
+    fn build_ui(&mut self, ui: &mut Ui) -> egui::Response {
+        ui.horizontal(|ui| {
+            ui.label("Masses");
+            ui.add(DragValue::new(&mut self.count).clamp_range(1..=MAX_MASSES))
+        })
+        .inner
+            | ui.add(
+                Slider::new(&mut self.displacement, -5.0..=5.0)
+                    .text("Displacement of the last mass"),
+            )
+    }
+
+    fn build(
+        &self,
+        gl: Arc<glow::Context>,
+        _camera_settings: CameraSettings,
+    ) -> Box<dyn Presenter> {
+        Box::new(SpringChain::new(gl, self.count, self.displacement))
+    }
+}
+
+impl Default for SpringChainBuilder {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            displacement: 1.0,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use egui::{Button, Slider, Ui};
+use std::{collections::VecDeque, fmt::Write, io, path::Path};
+
+/// Bounded history of fixed-width rows which can be exported as CSV
+pub struct Recording<const N: usize> {
+    header: [&'static str; N],
+    rows: VecDeque<[f64; N]>,
+    capacity: usize,
+}
+
+impl<const N: usize> Recording<N> {
+    pub fn new(header: [&'static str; N], capacity: usize) -> Self {
+        Self {
+            header,
+            rows: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Drops the oldest row when the recording is full
+    pub fn push(&mut self, row: [f64; N]) {
+        if self.rows.len() == self.capacity {
+            self.rows.pop_front();
+        }
+
+        self.rows.push_back(row);
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        if self.rows.len() > capacity {
+            self.rows.drain(0..self.rows.len() - capacity);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    pub fn rows(&self) -> &VecDeque<[f64; N]> {
+        &self.rows
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = self.header.join(",");
+        csv.push('\n');
+
+        for row in &self.rows {
+            for (i, value) in row.iter().enumerate() {
+                if i != 0 {
+                    csv.push(',');
+                }
+
+                write!(csv, "{}", value).unwrap();
+            }
+
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    pub fn save_csv(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_csv())
+    }
+}
+
+/// Presenter state which can be shown again from a snapshot
+pub trait Replayable {
+    type Snapshot: Clone;
+
+    fn snapshot(&self) -> Self::Snapshot;
+    fn restore(&mut self, snapshot: &Self::Snapshot);
+}
+
+/// Bounded timeline of snapshots, one per frame, which can be scrubbed through
+pub struct Replay<S> {
+    frames: VecDeque<S>,
+    capacity: usize,
+    /// Index of the shown frame while scrubbing
+    cursor: Option<usize>,
+}
+
+impl<S: Clone> Replay<S> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            capacity,
+            cursor: None,
+        }
+    }
+
+    /// Drops the oldest frame when the timeline is full
+    pub fn record(&mut self, snapshot: S) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(snapshot);
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.cursor = None;
+    }
+
+    pub fn is_scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Stops scrubbing, returns the newest frame if it has to be restored
+    pub fn resume(&mut self) -> Option<S> {
+        self.cursor.take()?;
+        self.frames.back().cloned()
+    }
+
+    /// Timeline slider, returns the frame to restore when it has been moved
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<S> {
+        if self.frames.is_empty() {
+            return None;
+        }
+
+        let newest = self.frames.len() - 1;
+        let mut frame = self.cursor.unwrap_or(newest).min(newest);
+
+        let changed = ui
+            .horizontal(|ui| {
+                let moved = ui
+                    .add(Slider::new(&mut frame, 0..=newest).text("Frame"))
+                    .changed();
+                let live = ui
+                    .add_enabled(self.is_scrubbing(), Button::new("Live"))
+                    .clicked();
+
+                if live {
+                    frame = newest;
+                }
+
+                moved || live
+            })
+            .inner;
+
+        if !changed {
+            return None;
+        }
+
+        self.cursor = (frame != newest).then_some(frame);
+        self.frames.get(frame).cloned()
+    }
+}
@@ -1,3 +1,4 @@
+use super::texture::Texture;
 use egui_winit::winit::dpi::PhysicalSize;
 use glow::HasContext;
 use std::sync::Arc;
@@ -7,17 +8,38 @@ pub struct Drawbuffer {
     framebuffer: glow::Framebuffer,
     rgb_texture: glow::Texture,
     depth_stencil_texture: glow::Texture,
+    multisampled: Option<Multisampled>,
     size: PhysicalSize<i32>,
 }
 
+/// Framebuffer drawn into when multisampling, resolved into the textures before reading
+struct Multisampled {
+    framebuffer: glow::Framebuffer,
+    color: glow::Renderbuffer,
+    depth_stencil: glow::Renderbuffer,
+}
+
 impl Drawbuffer {
+    /// Sample count of the window surface and of drawbuffers,
+    /// multisampling is switched on and off with `GL_MULTISAMPLE`
+    pub const SAMPLES: i32 = 4;
+
     pub fn new(gl: Arc<glow::Context>, width: i32, height: i32) -> Self {
+        let samples = Self::SAMPLES.min(unsafe { gl.get_parameter_i32(glow::MAX_SAMPLES) });
+        Self::with_samples(gl, width, height, samples)
+    }
+
+    /// No multisampling for `samples` of at most 1
+    pub fn with_samples(gl: Arc<glow::Context>, width: i32, height: i32, samples: i32) -> Self {
         let framebuffer = unsafe { gl.create_framebuffer() }.unwrap();
 
         unsafe { gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer)) };
         let rgb_texture = unsafe { Self::attach_rgb(gl.as_ref(), width, height) };
         let depth_stencil_texture =
             unsafe { Self::attach_depth_stencil(gl.as_ref(), width, height) };
+
+        let multisampled = (samples > 1)
+            .then(|| unsafe { Self::multisampled(gl.as_ref(), width, height, samples) });
         unsafe { gl.bind_framebuffer(glow::FRAMEBUFFER, None) };
 
         Self {
@@ -25,10 +47,78 @@ impl Drawbuffer {
             gl,
             rgb_texture,
             depth_stencil_texture,
+            multisampled,
             size: PhysicalSize { width, height },
         }
     }
 
+    unsafe fn multisampled(
+        gl: &glow::Context,
+        width: i32,
+        height: i32,
+        samples: i32,
+    ) -> Multisampled {
+        let framebuffer = gl.create_framebuffer().unwrap();
+        gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+
+        let renderbuffer = |format, attachment| {
+            let renderbuffer = gl.create_renderbuffer().unwrap();
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+            gl.renderbuffer_storage_multisample(glow::RENDERBUFFER, samples, format, width, height);
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                attachment,
+                glow::RENDERBUFFER,
+                Some(renderbuffer),
+            );
+            renderbuffer
+        };
+
+        let color = renderbuffer(glow::RGB8, glow::COLOR_ATTACHMENT0);
+        let depth_stencil = renderbuffer(glow::DEPTH24_STENCIL8, glow::DEPTH_STENCIL_ATTACHMENT);
+        gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+
+        Multisampled {
+            framebuffer,
+            color,
+            depth_stencil,
+        }
+    }
+
+    /// Framebuffer which is drawn into
+    fn target(&self) -> glow::Framebuffer {
+        self.multisampled
+            .as_ref()
+            .map_or(self.framebuffer, |multisampled| multisampled.framebuffer)
+    }
+
+    /// Averages the samples into the textures
+    fn resolve(&self) {
+        let Some(multisampled) = &self.multisampled else {
+            return;
+        };
+
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::READ_FRAMEBUFFER, Some(multisampled.framebuffer));
+            self.gl
+                .bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(self.framebuffer));
+            self.gl.blit_framebuffer(
+                0,
+                0,
+                self.size.width,
+                self.size.height,
+                0,
+                0,
+                self.size.width,
+                self.size.height,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+    }
+
     unsafe fn attach_rgb(gl: &glow::Context, width: i32, height: i32) -> glow::Texture {
         let texture = gl.create_texture().unwrap();
         gl.bind_texture(glow::TEXTURE_2D, Some(texture));
@@ -92,7 +182,7 @@ impl Drawbuffer {
     pub fn clear(&self) {
         unsafe {
             self.gl
-                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.target()));
             self.gl
                 .clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
             self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
@@ -102,7 +192,7 @@ impl Drawbuffer {
     pub fn draw_with<F: FnOnce()>(&self, f: F) {
         let old_viewport = unsafe {
             self.gl
-                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.target()));
             self.gl.viewport(0, 0, self.size.width, self.size.height);
             let mut old_viewport: [i32; 4] = [0, 0, 0, 0];
             self.gl
@@ -124,6 +214,8 @@ impl Drawbuffer {
     }
 
     pub fn blit(&self, x: i32, y: i32) {
+        self.resolve();
+
         unsafe {
             self.gl
                 .bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.framebuffer));
@@ -149,6 +241,44 @@ impl Drawbuffer {
     pub fn size(&self) -> PhysicalSize<i32> {
         self.size
     }
+
+    pub fn capture(&self) -> Option<Texture> {
+        self.resolve();
+        read_framebuffer(
+            &self.gl,
+            Some(self.framebuffer),
+            PhysicalSize::new(self.size.width as u32, self.size.height as u32),
+        )
+    }
+}
+
+/// Reads color attachment of `framebuffer`, `None` reads the default framebuffer
+pub fn read_framebuffer(
+    gl: &glow::Context,
+    framebuffer: Option<glow::Framebuffer>,
+    size: PhysicalSize<u32>,
+) -> Option<Texture> {
+    let row = 4 * size.width as usize;
+    let mut data = vec![0; row * size.height as usize];
+
+    unsafe {
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, framebuffer);
+        gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+        gl.read_pixels(
+            0,
+            0,
+            size.width as i32,
+            size.height as i32,
+            glow::RGBA,
+            glow::UNSIGNED_BYTE,
+            glow::PixelPackData::Slice(&mut data),
+        );
+        gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+    }
+
+    // OpenGL rows go bottom to top
+    let flipped = data.chunks_exact(row).rev().flatten().copied().collect();
+    Texture::from_rgba(size.width, size.height, flipped).ok()
 }
 
 impl Drop for Drawbuffer {
@@ -157,6 +287,12 @@ impl Drop for Drawbuffer {
             self.gl.delete_framebuffer(self.framebuffer);
             self.gl.delete_texture(self.rgb_texture);
             self.gl.delete_texture(self.depth_stencil_texture);
+
+            if let Some(multisampled) = &self.multisampled {
+                self.gl.delete_framebuffer(multisampled.framebuffer);
+                self.gl.delete_renderbuffer(multisampled.color);
+                self.gl.delete_renderbuffer(multisampled.depth_stencil);
+            }
         }
     }
 }
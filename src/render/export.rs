@@ -0,0 +1,363 @@
+use super::mesh::{ClassicVertex, Mesh};
+use nalgebra as na;
+use std::{io, path::Path};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+/// Writes a glTF 2.0 scene with one node per mesh to `path` and its binary buffer
+/// next to it, with the `.bin` extension. Transforms are baked into the vertices.
+/// Empty meshes are skipped, and if all of them are empty nothing is written.
+pub fn export_gltf(
+    path: &Path,
+    meshes: &[(&Mesh<ClassicVertex>, na::Matrix4<f32>)],
+) -> io::Result<()> {
+    let bin_path = path.with_extension("bin");
+    let bin_uri = bin_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(percent_encode)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid glTF path"))?;
+
+    let mut buffer = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut gltf_meshes = Vec::new();
+
+    for (mesh, transform) in meshes
+        .iter()
+        .filter(|(mesh, _)| !mesh.vertices.is_empty() && !mesh.triangles.is_empty())
+    {
+        let mesh = mesh.transformed(transform);
+        let positions: Vec<_> = mesh.vertices.iter().map(|v| v.position).collect();
+        let normals: Vec<_> = mesh.vertices.iter().map(|v| v.normal).collect();
+
+        let min = positions
+            .iter()
+            .fold(na::Point3::from([f32::MAX; 3]), |a, p| a.inf(p));
+        let max = positions
+            .iter()
+            .fold(na::Point3::from([f32::MIN; 3]), |a, p| a.sup(p));
+
+        let first_accessor = accessors.len();
+
+        buffer_views.push(buffer_view(&mut buffer, ARRAY_BUFFER, |b| {
+            positions
+                .iter()
+                .flat_map(|p| p.coords.iter())
+                .for_each(|c| b.extend(c.to_le_bytes()))
+        }));
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{FLOAT},"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+            buffer_views.len() - 1,
+            positions.len(),
+            min.x,
+            min.y,
+            min.z,
+            max.x,
+            max.y,
+            max.z,
+        ));
+
+        buffer_views.push(buffer_view(&mut buffer, ARRAY_BUFFER, |b| {
+            normals
+                .iter()
+                .flat_map(|n| n.iter())
+                .for_each(|c| b.extend(c.to_le_bytes()))
+        }));
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{FLOAT},"count":{},"type":"VEC3"}}"#,
+            buffer_views.len() - 1,
+            normals.len(),
+        ));
+
+        buffer_views.push(buffer_view(&mut buffer, ELEMENT_ARRAY_BUFFER, |b| {
+            mesh.triangles
+                .iter()
+                .flat_map(|t| t.0)
+                .for_each(|i| b.extend(i.to_le_bytes()))
+        }));
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+            buffer_views.len() - 1,
+            mesh.triangles.len() * 3,
+        ));
+
+        gltf_meshes.push(format!(
+            r#"{{"primitives":[{{"attributes":{{"POSITION":{},"NORMAL":{}}},"indices":{}}}]}}"#,
+            first_accessor,
+            first_accessor + 1,
+            first_accessor + 2,
+        ));
+    }
+
+    if gltf_meshes.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No non-empty meshes to export",
+        ));
+    }
+
+    let nodes: Vec<_> = (0..gltf_meshes.len())
+        .map(|i| format!(r#"{{"mesh":{i}}}"#))
+        .collect();
+    let node_indices: Vec<_> = (0..gltf_meshes.len()).map(|i| i.to_string()).collect();
+
+    let json = format!(
+        r#"{{"asset":{{"version":"2.0","generator":"phyesthon"}},"scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"buffers":[{{"byteLength":{},"uri":"{}"}}],"bufferViews":[{}],"accessors":[{}]}}"#,
+        node_indices.join(","),
+        nodes.join(","),
+        gltf_meshes.join(","),
+        buffer.len(),
+        bin_uri,
+        buffer_views.join(","),
+        accessors.join(","),
+    );
+
+    std::fs::write(&bin_path, &buffer)?;
+    std::fs::write(path, json)
+}
+
+/// Escapes everything except unreserved characters, so that file names are valid
+/// relative URIs and can be embedded in JSON strings
+fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn buffer_view(buffer: &mut Vec<u8>, target: u32, write: impl FnOnce(&mut Vec<u8>)) -> String {
+    let offset = buffer.len();
+    write(buffer);
+    format!(
+        r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+        offset,
+        buffer.len() - offset,
+        target
+    )
+}
+
+/// Writes a binary STL file, facet normals are computed from the triangles
+pub fn export_stl(path: &Path, mesh: &Mesh<ClassicVertex>) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(84 + 50 * mesh.triangles.len());
+
+    let mut header = [0u8; 80];
+    header[..10].copy_from_slice(b"phyesthon ");
+    buffer.extend(header);
+    buffer.extend((mesh.triangles.len() as u32).to_le_bytes());
+
+    for triangle in &mesh.triangles {
+        let [a, b, c] = triangle.0.map(|i| mesh.vertices[i as usize].position);
+        let normal = (b - a)
+            .cross(&(c - a))
+            .try_normalize(0.0)
+            .unwrap_or_default();
+
+        for coords in [normal, a.coords, b.coords, c.coords] {
+            coords.iter().for_each(|c| buffer.extend(c.to_le_bytes()));
+        }
+
+        buffer.extend(0u16.to_le_bytes());
+    }
+
+    std::fs::write(path, buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::models;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Gltf {
+        nodes: Vec<Node>,
+        meshes: Vec<serde::de::IgnoredAny>,
+        buffers: Vec<Buffer>,
+        buffer_views: Vec<BufferView>,
+        accessors: Vec<Accessor>,
+    }
+
+    #[derive(Deserialize)]
+    struct Node {
+        mesh: usize,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Buffer {
+        byte_length: usize,
+        uri: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct BufferView {
+        byte_offset: usize,
+        byte_length: usize,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Accessor {
+        buffer_view: usize,
+        count: usize,
+        #[serde(default)]
+        min: Vec<f32>,
+        #[serde(default)]
+        max: Vec<f32>,
+    }
+
+    fn read_f32s(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn gltf_buffers_match_the_meshes() {
+        let path = std::env::temp_dir().join("phyesthon_export_test.gltf");
+        let cube = models::cube();
+        let sphere = models::sphere(4, 6);
+        let shift = na::Translation3::new(0.0, 5.0, 0.0).to_homogeneous();
+        let empty = Mesh::empty();
+
+        export_gltf(
+            &path,
+            &[
+                (&cube, na::Matrix4::identity()),
+                (&empty, na::Matrix4::identity()),
+                (&sphere, shift),
+            ],
+        )
+        .unwrap();
+
+        let gltf: Gltf = crate::json::load(&path).unwrap();
+        let bin = std::fs::read(path.with_extension("bin")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("bin")).unwrap();
+
+        assert_eq!(gltf.buffers.len(), 1);
+        assert_eq!(gltf.buffers[0].byte_length, bin.len());
+        assert_eq!(gltf.buffers[0].uri, "phyesthon_export_test.bin");
+        assert_eq!(gltf.meshes.len(), 2);
+        assert_eq!(
+            gltf.nodes.iter().map(|n| n.mesh).collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(gltf.accessors.len(), 6);
+
+        for (accessors, mesh) in gltf.accessors.chunks(3).zip([&cube, &sphere]) {
+            let views: Vec<_> = accessors
+                .iter()
+                .map(|a| &gltf.buffer_views[a.buffer_view])
+                .collect();
+
+            assert_eq!(accessors[0].count, mesh.vertices.len());
+            assert_eq!(accessors[1].count, mesh.vertices.len());
+            assert_eq!(accessors[2].count, 3 * mesh.triangles.len());
+            assert_eq!(views[0].byte_length, 12 * mesh.vertices.len());
+            assert_eq!(views[1].byte_length, 12 * mesh.vertices.len());
+            assert_eq!(views[2].byte_length, 12 * mesh.triangles.len());
+            assert!(views
+                .iter()
+                .all(|v| v.byte_offset + v.byte_length <= bin.len()));
+
+            let indices: Vec<_> = bin[views[2].byte_offset..][..views[2].byte_length]
+                .chunks_exact(4)
+                .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+                .collect();
+            let expected: Vec<_> = mesh.triangles.iter().flat_map(|t| t.0).collect();
+            assert_eq!(indices, expected);
+        }
+
+        // Transforms are baked into the positions
+        let sphere_positions = &gltf.buffer_views[gltf.accessors[3].buffer_view];
+        let positions =
+            read_f32s(&bin[sphere_positions.byte_offset..][..sphere_positions.byte_length]);
+        assert_eq!(positions[..3], [0.0, 6.0, 0.0]);
+        assert_eq!(gltf.accessors[3].min[1], 4.0);
+        assert_eq!(gltf.accessors[3].max[1], 6.0);
+        assert_eq!(gltf.accessors[0].min, [-1.0; 3]);
+        assert_eq!(gltf.accessors[0].max, [1.0; 3]);
+    }
+
+    #[test]
+    fn buffer_uri_is_percent_encoded() {
+        assert_eq!(percent_encode("plain_name-1.bin"), "plain_name-1.bin");
+        assert_eq!(
+            percent_encode(r#"a "b"\c 100%.bin"#),
+            "a%20%22b%22%5Cc%20100%25.bin"
+        );
+        assert_eq!(percent_encode("żelek.bin"), "%C5%BCelek.bin");
+
+        let path = std::env::temp_dir().join("phyesthon export 100%.gltf");
+        export_gltf(&path, &[(&models::cube(), na::Matrix4::identity())]).unwrap();
+
+        let gltf: Gltf = crate::json::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("bin")).unwrap();
+
+        assert_eq!(gltf.buffers[0].uri, "phyesthon%20export%20100%25.bin");
+    }
+
+    #[test]
+    fn gltf_without_geometry_is_rejected() {
+        let path = std::env::temp_dir().join("phyesthon_empty_export_test.gltf");
+        let empty = Mesh::empty();
+
+        for meshes in [vec![], vec![(&empty, na::Matrix4::identity())]] {
+            let error = export_gltf(&path, &meshes).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            assert!(!path.exists());
+            assert!(!path.with_extension("bin").exists());
+        }
+    }
+
+    #[test]
+    fn stl_size_matches_the_triangle_count() {
+        let path = std::env::temp_dir().join("phyesthon_export_test.stl");
+
+        for mesh in [models::cube(), models::sphere(5, 8), Mesh::empty()] {
+            export_stl(&path, &mesh).unwrap();
+            let stl = std::fs::read(&path).unwrap();
+
+            assert_eq!(stl.len(), 84 + 50 * mesh.triangles.len());
+            assert_eq!(
+                u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize,
+                mesh.triangles.len()
+            );
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stl_facets_hold_normals_and_vertices() {
+        let path = std::env::temp_dir().join("phyesthon_facets_export_test.stl");
+        let cube = models::cube();
+        export_stl(&path, &cube).unwrap();
+        let stl = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        for (facet, triangle) in stl[84..].chunks_exact(50).zip(&cube.triangles) {
+            let values = read_f32s(&facet[..48]);
+            let vertices = triangle.0.map(|i| cube.vertices[i as usize]);
+
+            assert_eq!(values[..3], *vertices[0].normal.as_slice());
+            for (corner, vertex) in values[3..].chunks_exact(3).zip(vertices) {
+                assert_eq!(corner, vertex.position.coords.as_slice());
+            }
+            assert_eq!(facet[48..], [0, 0]);
+        }
+    }
+}
@@ -1,13 +1,13 @@
 use super::{
     gl_drawable::GlDrawable,
-    mesh::{Mesh, Vertex},
+    mesh::{Mesh, Triangle, Vertex},
     opengl,
 };
 use crate::utils;
 use glow::HasContext;
 use nalgebra as na;
 use num_traits::ToPrimitive;
-use std::sync::Arc;
+use std::{ops::Range, sync::Arc};
 
 const POINT_SIZE: i32 = std::mem::size_of::<na::Point3<f32>>() as i32;
 
@@ -15,11 +15,13 @@ pub struct GlMesh {
     vertex_buffer: glow::Buffer,
     element_buffer: glow::Buffer,
     element_count: u32,
+    vertex_count: usize,
     vertex_array: glow::VertexArray,
     gl: Arc<glow::Context>,
 }
 
-pub struct GlTriangleMesh(GlMesh);
+/// The triangles are kept to detect topology changes in `update_vertices`
+pub struct GlTriangleMesh(GlMesh, Vec<Triangle>);
 
 impl GlTriangleMesh {
     pub fn new<V: Vertex>(gl: Arc<glow::Context>, mesh: &Mesh<V>) -> Self {
@@ -38,13 +40,64 @@ impl GlTriangleMesh {
             V::set_vertex_attrib_pointers(&gl);
         });
 
-        Self(GlMesh {
-            vertex_buffer,
-            element_buffer,
-            element_count: 3 * mesh.triangles.len() as u32,
-            vertex_array,
-            gl,
-        })
+        Self(
+            GlMesh {
+                vertex_buffer,
+                element_buffer,
+                element_count: 3 * mesh.triangles.len() as u32,
+                vertex_count: mesh.vertices.len(),
+                vertex_array,
+                gl,
+            },
+            mesh.triangles.clone(),
+        )
+    }
+}
+
+impl GlTriangleMesh {
+    /// Reuses the vertex buffer if the vertex count is unchanged, the elements are uploaded
+    /// only when the triangles change
+    pub fn update_vertices<V: Vertex>(&mut self, mesh: &Mesh<V>) {
+        let mesh_gl = &mut self.0;
+        let raw_points = utils::slice_as_raw(&mesh.vertices);
+        let topology_changed = mesh.triangles != self.1;
+
+        opengl::with_vao(&mesh_gl.gl, mesh_gl.vertex_array, || unsafe {
+            mesh_gl
+                .gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(mesh_gl.vertex_buffer));
+
+            if mesh.vertices.len() == mesh_gl.vertex_count {
+                mesh_gl
+                    .gl
+                    .buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, 0, raw_points);
+            } else {
+                mesh_gl
+                    .gl
+                    .buffer_data_u8_slice(glow::ARRAY_BUFFER, raw_points, glow::DYNAMIC_DRAW);
+            }
+
+            if topology_changed {
+                mesh_gl.gl.buffer_data_u8_slice(
+                    glow::ELEMENT_ARRAY_BUFFER,
+                    utils::slice_as_raw(&mesh.triangles),
+                    glow::DYNAMIC_DRAW,
+                );
+            }
+        });
+
+        mesh_gl.vertex_count = mesh.vertices.len();
+        mesh_gl.element_count = 3 * mesh.triangles.len() as u32;
+
+        if topology_changed {
+            self.1.clone_from(&mesh.triangles);
+        }
+    }
+
+    pub fn draw_wireframe(&self) {
+        unsafe { self.0.gl.polygon_mode(glow::FRONT_AND_BACK, glow::LINE) };
+        self.draw();
+        unsafe { self.0.gl.polygon_mode(glow::FRONT_AND_BACK, glow::FILL) };
     }
 }
 
@@ -71,11 +124,165 @@ impl Drop for GlTriangleMesh {
     }
 }
 
-pub struct GlLineStrip {
-    vertex_buffer: glow::Buffer,
+/// Triangle mesh drawn once for every transform in its instance buffer,
+/// the transforms are bound to attribute locations 2 to 5
+pub struct GlInstancedTriangleMesh {
+    mesh: GlTriangleMesh,
+    instance_buffer: glow::Buffer,
+    instance_count: i32,
+}
+
+impl GlInstancedTriangleMesh {
+    const INSTANCE_LOCATION: u32 = 2;
+
+    pub fn new<V: Vertex>(
+        gl: Arc<glow::Context>,
+        mesh: &Mesh<V>,
+        instances: &[na::Matrix4<f32>],
+    ) -> Self {
+        let mesh = GlTriangleMesh::new(Arc::clone(&gl), mesh);
+        let instance_buffer = unsafe { gl.create_buffer() }.unwrap();
+
+        opengl::with_vao(&gl, mesh.0.vertex_array, || unsafe {
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(instance_buffer));
+
+            let stride = std::mem::size_of::<na::Matrix4<f32>>() as i32;
+            let column_size = std::mem::size_of::<na::Vector4<f32>>() as i32;
+            for column in 0..4 {
+                let location = Self::INSTANCE_LOCATION + column;
+                gl.vertex_attrib_pointer_f32(
+                    location,
+                    4,
+                    glow::FLOAT,
+                    false,
+                    stride,
+                    column as i32 * column_size,
+                );
+                gl.vertex_attrib_divisor(location, 1);
+                gl.enable_vertex_attrib_array(location);
+            }
+        });
+
+        let mut instanced = Self {
+            mesh,
+            instance_buffer,
+            instance_count: 0,
+        };
+
+        instanced.update_instances(instances);
+        instanced
+    }
+
+    pub fn update_instances(&mut self, instances: &[na::Matrix4<f32>]) {
+        let gl = &self.mesh.0.gl;
+
+        unsafe {
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.instance_buffer));
+            gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                utils::slice_as_raw(instances),
+                glow::DYNAMIC_DRAW,
+            );
+        }
+
+        self.instance_count = instances.len() as i32;
+    }
+}
+
+impl GlDrawable for GlInstancedTriangleMesh {
+    fn draw(&self) {
+        let mesh = &self.mesh.0;
+        opengl::with_vao(&mesh.gl, mesh.vertex_array, || unsafe {
+            mesh.gl.draw_elements_instanced(
+                glow::TRIANGLES,
+                mesh.element_count as i32,
+                glow::UNSIGNED_INT,
+                0,
+                self.instance_count,
+            );
+        });
+    }
+}
+
+impl Drop for GlInstancedTriangleMesh {
+    fn drop(&mut self) {
+        unsafe { self.mesh.0.gl.delete_buffer(self.instance_buffer) };
+    }
+}
+
+/// Slot bookkeeping of a `GlLineStrip` ring buffer, which has an extra slot after the last one
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct StripRing {
     vertex_count: i32,
     capacity: i32,
     first: i32,
+}
+
+impl StripRing {
+    fn new(capacity: i32) -> Self {
+        Self {
+            vertex_count: 0,
+            capacity,
+            first: 0,
+        }
+    }
+
+    /// Moves the ring buffer by one vertex and returns its slot, a vertex written to slot 0
+    /// of a full strip is also copied to the extra last slot to keep the strip continuous
+    fn advance(&mut self) -> (i32, bool) {
+        let slot = (self.first + self.vertex_count) % self.capacity;
+        let copied = self.vertex_count == self.capacity && slot == 0;
+
+        if self.vertex_count == self.capacity {
+            self.first = if slot == 0 { 1 } else { self.first + 1 };
+        } else {
+            self.vertex_count += 1;
+        }
+
+        (slot, copied)
+    }
+
+    /// Moves the ring buffer by `count` vertices and returns at most two writes uploading them,
+    /// each one is the first slot and the range of the pushed vertices written from it
+    fn push(&mut self, count: usize) -> impl Iterator<Item = (i32, Range<usize>)> {
+        // Vertices overwritten within the same batch are never uploaded
+        let skipped = count.saturating_sub(self.capacity as usize);
+        for _ in 0..skipped {
+            self.advance();
+        }
+
+        if count == skipped {
+            return [None, None].into_iter().flatten();
+        }
+
+        let (first_slot, mut copied) = self.advance();
+
+        for _ in skipped + 1..count {
+            copied |= self.advance().1;
+        }
+
+        let before_wrap = skipped + (count - skipped).min((self.capacity - first_slot) as usize);
+        let writes = if before_wrap == count {
+            [
+                Some((first_slot, skipped..count)),
+                copied.then_some((self.capacity, skipped..skipped + 1)),
+            ]
+        } else {
+            // The extra slot directly follows the last one, so the copy of the vertex
+            // wrapping to slot 0 is uploaded together with the part before the wrap
+            [
+                Some((first_slot, skipped..before_wrap + copied as usize)),
+                Some((0, before_wrap..count)),
+            ]
+        };
+
+        writes.into_iter().flatten()
+    }
+}
+
+pub struct GlLineStrip {
+    vertex_buffer: glow::Buffer,
+    ring: StripRing,
     vertex_array: glow::VertexArray,
     gl: Arc<glow::Context>,
 }
@@ -100,10 +307,8 @@ impl GlLineStrip {
 
         Self {
             vertex_buffer,
-            vertex_count: 0,
-            capacity,
+            ring: StripRing::new(capacity),
             vertex_array,
-            first: 0,
             gl,
         }
     }
@@ -124,10 +329,12 @@ impl GlLineStrip {
 
         Self {
             vertex_buffer,
-            vertex_count: length,
-            capacity: length,
+            ring: StripRing {
+                vertex_count: length,
+                capacity: length,
+                first: 0,
+            },
             vertex_array,
-            first: 0,
             gl,
         }
     }
@@ -135,7 +342,7 @@ impl GlLineStrip {
     pub fn recapacitate(&mut self, capacity: usize) {
         let capacity = capacity as i32;
 
-        if capacity == self.capacity {
+        if capacity == self.ring.capacity {
             return;
         }
 
@@ -161,58 +368,63 @@ impl GlLineStrip {
             new_buffer
         };
 
-        self.first = 0;
-        self.capacity = capacity;
+        self.ring = StripRing::new(capacity);
         self.vertex_buffer = new_buffer;
-        self.vertex_count = 0;
     }
 
-    pub fn push_vertex(&mut self, vertex: &na::Point3<f32>) {
-        let slot = (self.first + self.vertex_count) % self.capacity;
-        let offset = POINT_SIZE * slot;
+    pub fn clear(&mut self) {
+        self.ring = StripRing::new(self.ring.capacity);
+    }
 
+    fn write_vertices(&self, slot: i32, vertices: &[na::Point3<f32>]) {
         unsafe {
-            self.gl
-                .bind_buffer(glow::ARRAY_BUFFER, Some(self.vertex_buffer));
             self.gl.buffer_sub_data_u8_slice(
                 glow::ARRAY_BUFFER,
-                offset,
-                utils::slice_as_raw(vertex.coords.as_slice()),
+                POINT_SIZE * slot,
+                utils::slice_as_raw(vertices),
             )
         };
+    }
 
-        if self.vertex_count == self.capacity {
-            if slot == 0 {
-                unsafe {
-                    self.gl.buffer_sub_data_u8_slice(
-                        glow::ARRAY_BUFFER,
-                        POINT_SIZE * self.capacity,
-                        utils::slice_as_raw(vertex.coords.as_slice()),
-                    )
-                };
-
-                self.first = 1;
-            } else {
-                self.first += 1;
-            }
-        } else {
-            self.vertex_count += 1;
+    pub fn push_vertex(&mut self, vertex: &na::Point3<f32>) {
+        self.push_vertices(std::slice::from_ref(vertex));
+    }
+
+    /// Uploads the vertices in at most two calls, one on each side of the ring buffer wrap
+    pub fn push_vertices(&mut self, vertices: &[na::Point3<f32>]) {
+        let mut writes = self.ring.push(vertices.len()).peekable();
+        if writes.peek().is_none() {
+            return;
+        }
+
+        unsafe {
+            self.gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(self.vertex_buffer))
+        };
+
+        for (slot, range) in writes {
+            self.write_vertices(slot, &vertices[range]);
         }
     }
 }
 
 impl GlDrawable for GlLineStrip {
     fn draw(&self) {
-        let first_draw_count = if self.vertex_count == self.capacity && self.first != 0 {
-            self.vertex_count - self.first + 1
+        let StripRing {
+            vertex_count,
+            capacity,
+            first,
+        } = self.ring;
+        let first_draw_count = if vertex_count == capacity && first != 0 {
+            vertex_count - first + 1
         } else {
-            self.vertex_count - self.first
+            vertex_count - first
         };
 
         opengl::with_vao(&self.gl, self.vertex_array, || unsafe {
             self.gl
-                .draw_arrays(glow::LINE_STRIP, self.first, first_draw_count);
-            self.gl.draw_arrays(glow::LINE_STRIP, 0, self.first);
+                .draw_arrays(glow::LINE_STRIP, first, first_draw_count);
+            self.gl.draw_arrays(glow::LINE_STRIP, 0, first);
         });
     }
 }
@@ -269,6 +481,7 @@ impl GlPointCloud {
 
     pub fn update_points(&mut self, points: &[na::Point3<f32>]) {
         let raw_points = utils::slice_as_raw(points);
+        self.point_count = points.len();
 
         unsafe {
             self.gl
@@ -348,6 +561,19 @@ impl GlTesselationBicubicPatch {
         let raw_input = utils::slice_as_raw(input);
         opengl::create_vao_vbo_points(gl, raw_input)
     }
+
+    /// Uploads new control points into the existing buffer
+    pub fn update_points(&mut self, surface_points: &[[na::Point3<f32>; 4]; 4]) {
+        unsafe {
+            self.gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(self.vertex_buffer));
+            self.gl.buffer_sub_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                0,
+                utils::slice_as_raw(surface_points),
+            );
+        }
+    }
 }
 
 impl GlDrawable for GlTesselationBicubicPatch {
@@ -368,3 +594,103 @@ impl Drop for GlTesselationBicubicPatch {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Ring buffer contents written one vertex at a time, the way `push_vertex` used to
+    struct ReferenceStrip {
+        ring: StripRing,
+        slots: Vec<i32>,
+    }
+
+    impl ReferenceStrip {
+        fn new(capacity: i32) -> Self {
+            Self {
+                ring: StripRing::new(capacity),
+                slots: vec![-1; capacity as usize + 1],
+            }
+        }
+
+        fn push(&mut self, vertex: i32) {
+            let (slot, copied) = self.ring.advance();
+            self.slots[slot as usize] = vertex;
+
+            if copied {
+                self.slots[self.ring.capacity as usize] = vertex;
+            }
+        }
+    }
+
+    /// Applies the writes planned by `StripRing::push` and returns their number
+    fn push_batch(ring: &mut StripRing, slots: &mut [i32], vertices: &[i32]) -> usize {
+        ring.push(vertices.len())
+            .map(|(slot, range)| {
+                let slot = slot as usize;
+                slots[slot..slot + range.len()].copy_from_slice(&vertices[range]);
+            })
+            .count()
+    }
+
+    #[test]
+    fn batch_crossing_the_wrap_is_split_in_two() {
+        let mut ring = StripRing::new(4);
+        let mut slots = vec![-1; 5];
+
+        assert_eq!(push_batch(&mut ring, &mut slots, &[1, 2, 3]), 1);
+        assert_eq!(slots, [1, 2, 3, -1, -1]);
+
+        // 4 goes to the last slot, 5 wraps to slot 0 and is also copied to the extra slot
+        let mut planned = ring;
+        assert_eq!(planned.push(3).collect::<Vec<_>>(), [(3, 0..2), (0, 1..3)]);
+
+        assert_eq!(push_batch(&mut ring, &mut slots, &[4, 5, 6]), 2);
+        assert_eq!(slots, [5, 6, 3, 4, 5]);
+        assert_eq!(
+            ring,
+            StripRing {
+                vertex_count: 4,
+                capacity: 4,
+                first: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn batches_match_single_pushes() {
+        let mut rng = StdRng::seed_from_u64(379);
+        let mut next_vertex = 0;
+
+        for capacity in 1..8 {
+            for _ in 0..200 {
+                let mut reference = ReferenceStrip::new(capacity);
+                let mut ring = StripRing::new(capacity);
+                let mut slots = reference.slots.clone();
+
+                for _ in 0..6 {
+                    let batch: Vec<i32> = (0..rng.gen_range(0..3 * capacity + 2))
+                        .map(|_| {
+                            next_vertex += 1;
+                            next_vertex
+                        })
+                        .collect();
+
+                    batch.iter().for_each(|&vertex| reference.push(vertex));
+                    assert!(push_batch(&mut ring, &mut slots, &batch) <= 2);
+
+                    assert_eq!(ring, reference.ring);
+                    assert_eq!(slots, reference.slots);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_batches_write_nothing() {
+        let mut ring = StripRing::new(3);
+        assert_eq!(ring.push(0).count(), 0);
+        assert_eq!(ring, StripRing::new(3));
+    }
+}
@@ -0,0 +1,69 @@
+use super::gl_program::GlProgram;
+use crate::ui::widgets::vector_drag;
+use egui::{Response, Ui};
+use nalgebra as na;
+
+/// Point light of the Phong shaders
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub position: na::Vector3<f32>,
+    pub color: na::Vector3<f32>,
+    pub ambient: na::Vector3<f32>,
+}
+
+impl Light {
+    const MAX_DISTANCE: f32 = 100.0;
+    const MAX_INTENSITY: f32 = 10.0;
+
+    pub fn new(
+        position: na::Vector3<f32>,
+        color: na::Vector3<f32>,
+        ambient: na::Vector3<f32>,
+    ) -> Self {
+        Self {
+            position,
+            color,
+            ambient,
+        }
+    }
+
+    /// Expects the program to be enabled
+    pub fn set_uniforms(&self, program: &GlProgram) {
+        program.uniform_3_f32_slice("light_position", self.position.as_slice());
+        program.uniform_3_f32_slice("light_color", self.color.as_slice());
+        program.uniform_3_f32_slice("ambient", self.ambient.as_slice());
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) -> Response {
+        let rgb = &["R", "G", "B"];
+
+        ui.label("Position");
+        let position = vector_drag(
+            ui,
+            &mut self.position,
+            -Self::MAX_DISTANCE,
+            Self::MAX_DISTANCE,
+            "",
+            0.05,
+            &["x", "y", "z"],
+        );
+
+        ui.label("Color");
+        let color = vector_drag(ui, &mut self.color, 0.0, Self::MAX_INTENSITY, "", 0.01, rgb);
+
+        ui.label("Ambient");
+        let ambient = vector_drag(ui, &mut self.ambient, 0.0, 1.0, "", 0.01, rgb);
+
+        position | color | ambient
+    }
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self::new(
+            na::vector![2.0, 4.0, 2.0],
+            na::vector![2.0, 2.0, 2.0],
+            na::vector![0.4, 0.4, 0.4],
+        )
+    }
+}
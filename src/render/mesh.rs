@@ -1,17 +1,29 @@
 use glow::HasContext;
 use nalgebra as na;
+use std::collections::HashMap;
+use std::hash::Hash;
 
 const POINT_SIZE: i32 = std::mem::size_of::<na::Point3<f32>>() as i32;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(C)]
 pub struct Triangle(pub [u32; 3]);
 
 pub trait Vertex {
     fn set_vertex_attrib_pointers(gl: &glow::Context);
+    fn position(&self) -> na::Point3<f32>;
+    fn transform(&mut self, transform: &na::Matrix4<f32>, normal_transform: &na::Matrix3<f32>);
 }
 
 impl Vertex for na::Point3<f32> {
+    fn position(&self) -> na::Point3<f32> {
+        *self
+    }
+
+    fn transform(&mut self, transform: &na::Matrix4<f32>, _normal_transform: &na::Matrix3<f32>) {
+        *self = transform.transform_point(self);
+    }
+
     fn set_vertex_attrib_pointers(gl: &glow::Context) {
         unsafe {
             gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, POINT_SIZE, 0);
@@ -34,6 +46,17 @@ impl ClassicVertex {
 }
 
 impl Vertex for ClassicVertex {
+    fn position(&self) -> na::Point3<f32> {
+        self.position
+    }
+
+    fn transform(&mut self, transform: &na::Matrix4<f32>, normal_transform: &na::Matrix3<f32>) {
+        self.position = transform.transform_point(&self.position);
+        self.normal = (normal_transform * self.normal)
+            .try_normalize(0.0)
+            .unwrap_or_default();
+    }
+
     fn set_vertex_attrib_pointers(gl: &glow::Context) {
         unsafe {
             gl.vertex_attrib_pointer_f32(
@@ -59,6 +82,7 @@ impl Vertex for ClassicVertex {
     }
 }
 
+#[derive(Clone)]
 pub struct Mesh<V: Vertex> {
     pub vertices: Vec<V>,
     pub triangles: Vec<Triangle>,
@@ -78,6 +102,85 @@ impl<V: Vertex> Mesh<V> {
             triangles: Vec::new(),
         }
     }
+
+    /// Transforms positions by `transform` and normals by its inverse transpose
+    pub fn apply_transform(&mut self, transform: &na::Matrix4<f32>) {
+        let normal_transform = transform
+            .fixed_view::<3, 3>(0, 0)
+            .try_inverse()
+            .map_or(na::Matrix3::identity(), |inverse| inverse.transpose());
+
+        for vertex in &mut self.vertices {
+            vertex.transform(transform, &normal_transform);
+        }
+    }
+
+    pub fn transformed(&self, transform: &na::Matrix4<f32>) -> Self
+    where
+        V: Clone,
+    {
+        let mut mesh = self.clone();
+        mesh.apply_transform(transform);
+        mesh
+    }
+
+    /// Appends transformed vertices and triangles of `other`
+    pub fn merge(&mut self, other: &Mesh<V>, transform: &na::Matrix4<f32>)
+    where
+        V: Clone,
+    {
+        let offset = self.vertices.len() as u32;
+        let other = other.transformed(transform);
+
+        self.vertices.extend(other.vertices);
+        self.triangles.extend(
+            other
+                .triangles
+                .into_iter()
+                .map(|triangle| Triangle(triangle.0.map(|i| i + offset))),
+        );
+    }
+
+    /// Returns the minimal and maximal corners, both are the origin for an empty mesh
+    pub fn bounding_box(&self) -> (na::Point3<f32>, na::Point3<f32>) {
+        let mut positions = self.vertices.iter().map(Vertex::position);
+        let Some(first) = positions.next() else {
+            return (na::Point3::origin(), na::Point3::origin());
+        };
+
+        positions.fold((first, first), |(min, max), position| {
+            (min.inf(&position), max.sup(&position))
+        })
+    }
+
+    /// Merges vertices with equal keys, keeping the first of them, and rewrites the triangles.
+    /// Returns the number of removed vertices.
+    pub fn dedup_vertices_by_key<K: Hash + Eq>(&mut self, key: impl Fn(&V) -> K) -> usize {
+        let old_vertices = std::mem::take(&mut self.vertices);
+        let old_count = old_vertices.len();
+        let mut indices = HashMap::new();
+
+        let remap: Vec<u32> = old_vertices
+            .into_iter()
+            .map(|vertex| {
+                *indices.entry(key(&vertex)).or_insert_with(|| {
+                    self.vertices.push(vertex);
+                    self.vertices.len() as u32 - 1
+                })
+            })
+            .collect();
+
+        for triangle in &mut self.triangles {
+            triangle.0 = triangle.0.map(|i| remap[i as usize]);
+        }
+
+        old_count - self.vertices.len()
+    }
+}
+
+/// Snaps coordinates to a grid of size `epsilon` so that they can be compared and hashed
+pub fn quantized(vector: &na::Vector3<f32>, epsilon: f32) -> [i64; 3] {
+    [0, 1, 2].map(|i| (vector[i] / epsilon).round() as i64)
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -99,6 +202,17 @@ impl DuckVertex {
 }
 
 impl Vertex for DuckVertex {
+    fn position(&self) -> na::Point3<f32> {
+        self.position
+    }
+
+    fn transform(&mut self, transform: &na::Matrix4<f32>, normal_transform: &na::Matrix3<f32>) {
+        self.position = transform.transform_point(&self.position);
+        self.normal = (normal_transform * self.normal)
+            .try_normalize(0.0)
+            .unwrap_or_default();
+    }
+
     fn set_vertex_attrib_pointers(gl: &glow::Context) {
         unsafe {
             // Positions
@@ -139,29 +253,49 @@ impl Vertex for DuckVertex {
 }
 
 #[derive(Debug)]
-pub struct ParseError;
+pub enum ParseError {
+    Io(std::io::Error),
+    Format,
+}
+
+impl From<std::io::Error> for ParseError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Format => write!(f, "invalid mesh format"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 impl Mesh<DuckVertex> {
-    pub fn from_file(path: &std::path::Path) -> Self {
-        let path_string = path.to_str().expect("Cannot convert path to string");
-        let error_msg = format!("Could not load mesh at {}", path_string);
-        let string = std::fs::read_to_string(path).expect(&error_msg);
-        Self::parse_model(&string).expect("Error parsing model file")
+    pub fn from_file(path: &std::path::Path) -> Result<Self, ParseError> {
+        let string = std::fs::read_to_string(path)?;
+        Self::parse_model(&string)
     }
 
     pub fn parse_model(string: &str) -> Result<Self, ParseError> {
         let mut lines = string.lines();
 
-        let vertex_count = Self::parse_u32(lines.next().ok_or(ParseError {})?)?;
+        let vertex_count = Self::parse_u32(lines.next().ok_or(ParseError::Format)?)?;
         let mut vertices = Vec::new();
         for _ in 0..vertex_count {
-            vertices.push(Self::parse_vertex(lines.next().ok_or(ParseError {})?)?);
+            vertices.push(Self::parse_vertex(lines.next().ok_or(ParseError::Format)?)?);
         }
 
-        let triangle_count = Self::parse_u32(lines.next().ok_or(ParseError {})?)?;
+        let triangle_count = Self::parse_u32(lines.next().ok_or(ParseError::Format)?)?;
         let mut triangles = Vec::new();
         for _ in 0..triangle_count {
-            triangles.push(Self::parse_triangle(lines.next().ok_or(ParseError {})?)?);
+            triangles.push(Self::parse_triangle(
+                lines.next().ok_or(ParseError::Format)?,
+            )?);
         }
 
         Ok(Self {
@@ -171,13 +305,13 @@ impl Mesh<DuckVertex> {
     }
 
     pub fn parse_u32(string: &str) -> Result<u32, ParseError> {
-        string.parse().map_err(|_| ParseError {})
+        string.parse().map_err(|_| ParseError::Format)
     }
 
     pub fn parse_vertex(string: &str) -> Result<DuckVertex, ParseError> {
         let nums: Vec<_> = string.split(' ').flat_map(|s| s.parse()).collect();
         if nums.len() != 8 {
-            return Err(ParseError {});
+            return Err(ParseError::Format);
         }
 
         Ok(DuckVertex {
@@ -190,9 +324,555 @@ impl Mesh<DuckVertex> {
     pub fn parse_triangle(string: &str) -> Result<Triangle, ParseError> {
         let nums: Vec<_> = string.split(' ').flat_map(Self::parse_u32).collect();
         if nums.len() != 3 {
-            return Err(ParseError {});
+            return Err(ParseError::Format);
         }
 
         Ok(Triangle([nums[0], nums[1], nums[2]]))
     }
 }
+
+impl Mesh<ClassicVertex> {
+    pub fn from_obj(path: &std::path::Path) -> Result<Self, ParseError> {
+        let string = std::fs::read_to_string(path)?;
+        Self::parse_obj(&string)
+    }
+
+    /// Parses `v`, `vn` and `f` records, other records are ignored.
+    /// Polygons are triangulated as fans and missing normals are synthesized from the faces.
+    pub fn parse_obj(string: &str) -> Result<Self, ParseError> {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut indices = HashMap::new();
+        let mut mesh = Self::empty();
+        let mut missing_normals = Vec::new();
+
+        for line in string.lines() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => positions.push(na::Point3::from(Self::parse_obj_vector(tokens)?)),
+                Some("vn") => normals.push(Self::parse_obj_vector(tokens)?),
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for token in tokens {
+                        let key =
+                            Self::parse_obj_face_vertex(token, positions.len(), normals.len())?;
+                        let index = *indices.entry(key).or_insert_with(|| {
+                            let (position, normal) = key;
+                            mesh.vertices.push(ClassicVertex::new(
+                                positions[position],
+                                normal.map_or(na::Vector3::zeros(), |n| normals[n]),
+                            ));
+                            missing_normals.push(normal.is_none());
+                            mesh.vertices.len() as u32 - 1
+                        });
+
+                        face.push(index);
+                    }
+
+                    if face.len() < 3 {
+                        return Err(ParseError::Format);
+                    }
+
+                    for i in 1..face.len() - 1 {
+                        mesh.triangles
+                            .push(Triangle([face[0], face[i], face[i + 1]]));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if missing_normals.contains(&true) {
+            mesh.accumulate_face_normals(&missing_normals);
+        }
+
+        Ok(mesh)
+    }
+
+    fn parse_obj_vector<'a>(
+        tokens: impl Iterator<Item = &'a str>,
+    ) -> Result<na::Vector3<f32>, ParseError> {
+        let nums = tokens
+            .take(3)
+            .map(|t| t.parse().map_err(|_| ParseError::Format))
+            .collect::<Result<Vec<f32>, _>>()?;
+
+        if nums.len() != 3 {
+            return Err(ParseError::Format);
+        }
+
+        Ok(na::Vector3::new(nums[0], nums[1], nums[2]))
+    }
+
+    /// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero-based position and normal indices
+    fn parse_obj_face_vertex(
+        string: &str,
+        position_count: usize,
+        normal_count: usize,
+    ) -> Result<(usize, Option<usize>), ParseError> {
+        let mut parts = string.split('/');
+        let position =
+            Self::parse_obj_index(parts.next(), position_count)?.ok_or(ParseError::Format)?;
+        let _tex = parts.next();
+        let normal = Self::parse_obj_index(parts.next(), normal_count)?;
+
+        Ok((position, normal))
+    }
+
+    fn parse_obj_index(string: Option<&str>, count: usize) -> Result<Option<usize>, ParseError> {
+        let Some(string) = string.filter(|s| !s.is_empty()) else {
+            return Ok(None);
+        };
+
+        let index: i64 = string.parse().map_err(|_| ParseError::Format)?;
+        let index = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+
+        if index < 0 || index >= count as i64 {
+            return Err(ParseError::Format);
+        }
+
+        Ok(Some(index as usize))
+    }
+
+    /// Merges vertices whose positions and normals are equal up to `epsilon`
+    pub fn dedup_vertices(&mut self, epsilon: f32) -> usize {
+        self.dedup_vertices_by_key(|v| {
+            (
+                quantized(&v.position.coords, epsilon),
+                quantized(&v.normal, epsilon),
+            )
+        })
+    }
+
+    pub fn recompute_normals(&mut self) {
+        for vertex in &mut self.vertices {
+            vertex.normal = na::Vector3::zeros();
+        }
+
+        self.accumulate_face_normals(&vec![true; self.vertices.len()]);
+    }
+
+    /// Like `recompute_normals`, but only faces meeting at an angle of at most `max_angle`
+    /// are smoothed together. Vertices on sharper edges are split.
+    pub fn recompute_normals_with_threshold(&mut self, max_angle: f32) {
+        let face_normals: Vec<_> = self.triangles.iter().map(|t| self.face_normal(t)).collect();
+
+        let mut incident_faces = vec![Vec::new(); self.vertices.len()];
+        for (face, triangle) in self.triangles.iter().enumerate() {
+            for idx in triangle.0 {
+                incident_faces[idx as usize].push(face);
+            }
+        }
+
+        let cos_threshold = max_angle.cos();
+        let unit = |n: &na::Vector3<f32>| n.try_normalize(0.0).unwrap_or_default();
+
+        let mut vertices = Vec::new();
+        let mut indices = HashMap::new();
+        let mut triangles = Vec::with_capacity(self.triangles.len());
+
+        for (face, triangle) in self.triangles.iter().enumerate() {
+            let face_unit = unit(&face_normals[face]);
+            triangles.push(Triangle(triangle.0.map(|idx| {
+                let normal = unit(
+                    &incident_faces[idx as usize]
+                        .iter()
+                        .filter(|&&other| {
+                            face_unit.dot(&unit(&face_normals[other])) >= cos_threshold
+                        })
+                        .map(|&other| face_normals[other])
+                        .sum(),
+                );
+
+                *indices
+                    .entry((idx, normal.map(f32::to_bits)))
+                    .or_insert_with(|| {
+                        vertices.push(ClassicVertex::new(
+                            self.vertices[idx as usize].position,
+                            normal,
+                        ));
+                        vertices.len() as u32 - 1
+                    })
+            })));
+        }
+
+        self.vertices = vertices;
+        self.triangles = triangles;
+    }
+
+    /// Area-weighted, not normalized
+    fn face_normal(&self, triangle: &Triangle) -> na::Vector3<f32> {
+        let [a, b, c] = triangle.0.map(|i| self.vertices[i as usize].position);
+        (b - a).cross(&(c - a))
+    }
+
+    fn accumulate_face_normals(&mut self, affected: &[bool]) {
+        for triangle in &self.triangles {
+            let face_normal = self.face_normal(triangle);
+
+            for idx in triangle.0 {
+                if affected[idx as usize] {
+                    self.vertices[idx as usize].normal += face_normal;
+                }
+            }
+        }
+
+        for (vertex, _) in self.vertices.iter_mut().zip(affected).filter(|(_, a)| **a) {
+            vertex.normal = vertex.normal.try_normalize(0.0).unwrap_or_default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD_OBJ: &str = "\
+# Unit square in the xy plane
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vn 0 0 1
+f 1//1 2//1 3//1 4//1
+";
+
+    fn triangles<V: Vertex>(mesh: &Mesh<V>) -> Vec<[u32; 3]> {
+        mesh.triangles.iter().map(|t| t.0).collect()
+    }
+
+    fn assert_near(a: &na::Vector3<f32>, b: &na::Vector3<f32>) {
+        assert!((a - b).norm() < 1e-5, "{a} != {b}");
+    }
+
+    /// Cube with the vertices shared between faces
+    fn shared_cube() -> Mesh<ClassicVertex> {
+        let mut cube = crate::render::models::cube();
+        cube.dedup_vertices_by_key(|v| quantized(&v.position.coords, 1e-3));
+        cube
+    }
+
+    #[test]
+    fn recomputed_cube_normals_point_outward() {
+        let mut cube = crate::render::models::cube();
+        let expected: Vec<_> = cube.vertices.iter().map(|v| v.normal).collect();
+        cube.vertices
+            .iter_mut()
+            .for_each(|v| v.normal = na::vector![1.0, 2.0, 3.0]);
+
+        cube.recompute_normals();
+
+        for (vertex, expected) in cube.vertices.iter().zip(&expected) {
+            assert_near(&vertex.normal, expected);
+        }
+    }
+
+    #[test]
+    fn shared_vertex_normals_point_outward() {
+        let mut cube = shared_cube();
+        assert_eq!(cube.vertices.len(), 8);
+
+        cube.recompute_normals();
+
+        for vertex in &cube.vertices {
+            assert!((vertex.normal.norm() - 1.0).abs() < 1e-5);
+            // Each corner normal lies strictly inside the octant of the corner
+            assert!(vertex.normal.dot(&vertex.position.coords) > 1.0);
+        }
+    }
+
+    #[test]
+    fn threshold_splits_sharp_edges() {
+        let mut sharp = shared_cube();
+        sharp.recompute_normals_with_threshold(std::f32::consts::FRAC_PI_4);
+
+        assert_eq!(sharp.vertices.len(), 24);
+        assert_eq!(sharp.triangles.len(), 12);
+        for triangle in &sharp.triangles {
+            let normals = triangle.0.map(|i| sharp.vertices[i as usize].normal);
+            assert_near(&normals[0], &sharp.face_normal(triangle).normalize());
+            assert!(normals.iter().all(|n| *n == normals[0]));
+        }
+
+        let mut smooth = shared_cube();
+        smooth.recompute_normals_with_threshold(std::f32::consts::PI);
+        let mut expected = shared_cube();
+        expected.recompute_normals();
+
+        assert_eq!(smooth.vertices.len(), 8);
+        for vertex in &smooth.vertices {
+            let original = expected
+                .vertices
+                .iter()
+                .find(|v| v.position == vertex.position)
+                .unwrap();
+            assert_near(&vertex.normal, &original.normal);
+        }
+    }
+
+    #[test]
+    fn degenerate_triangles_give_zero_normals() {
+        let mut mesh = Mesh::new(
+            vec![ClassicVertex::new(na::point![1.0, 1.0, 1.0], na::vector![0.0, 1.0, 0.0]); 3],
+            vec![Triangle([0, 1, 2])],
+        );
+
+        mesh.recompute_normals();
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| v.normal == na::Vector3::zeros()));
+
+        mesh.apply_transform(&na::Matrix4::new_scaling(2.0));
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| v.normal == na::Vector3::zeros()));
+    }
+
+    #[test]
+    fn obj_polygons_are_triangulated_as_fans() {
+        let mesh = Mesh::parse_obj(QUAD_OBJ).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(triangles(&mesh), [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.vertices[2].position, na::point![1.0, 1.0, 0.0]);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| v.normal == na::vector![0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn obj_negative_indices_are_relative() {
+        let mesh = Mesh::parse_obj(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 -1\nf -3//-1 -2//-1 -1//-1\nv 5 5 5\nf 1 2 -1",
+        )
+        .unwrap();
+
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.vertices[0].normal, na::vector![0.0, 0.0, -1.0]);
+        assert_eq!(mesh.vertices[5].position, na::point![5.0, 5.0, 5.0]);
+        assert_eq!(triangles(&mesh), [[0, 1, 2], [3, 4, 5]]);
+    }
+
+    #[test]
+    fn obj_missing_normals_are_synthesized() {
+        let mesh = Mesh::parse_obj("v 0 0 0\nv 2 0 0\nv 0 2 0\nf 1/7 2/8 3/9").unwrap();
+
+        for vertex in &mesh.vertices {
+            assert_eq!(vertex.normal, na::vector![0.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn obj_vertices_with_the_same_indices_are_shared() {
+        let mesh = Mesh::parse_obj(
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nvn 1 0 0\n\
+             f 1//1 2//1 3//1\nf 1//1 3//1 4//1\nf 1//2 3//2 4//2",
+        )
+        .unwrap();
+
+        assert_eq!(mesh.vertices.len(), 7);
+        assert_eq!(triangles(&mesh), [[0, 1, 2], [0, 2, 3], [4, 5, 6]]);
+    }
+
+    #[test]
+    fn obj_ignores_other_records() {
+        let mesh =
+            Mesh::parse_obj(&format!("o square\nvt 0 0\ns off\nusemtl red\n{QUAD_OBJ}")).unwrap();
+        assert_eq!(mesh.triangles.len(), 2);
+    }
+
+    #[test]
+    fn malformed_obj_is_rejected() {
+        for obj in [
+            "v 0 0\nf 1 1 1",
+            "v 0 zero 0",
+            "v 0 0 0\nv 1 0 0\nf 1 2",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -4 1 2",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1//1 2//1 3//1",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 x",
+        ] {
+            assert!(
+                matches!(Mesh::parse_obj(obj), Err(ParseError::Format)),
+                "{obj:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn cube_positions_dedup_to_corners() {
+        let mut cube = crate::render::models::cube();
+        assert_eq!(cube.vertices.len(), 24);
+        let positions: Vec<_> = cube
+            .triangles
+            .iter()
+            .map(|t| t.0.map(|i| cube.vertices[i as usize].position))
+            .collect();
+
+        assert_eq!(
+            cube.dedup_vertices_by_key(|v| quantized(&v.position.coords, 1e-3)),
+            16
+        );
+
+        assert_eq!(cube.vertices.len(), 8);
+        assert_eq!(cube.triangles.len(), 12);
+        for (triangle, positions) in cube.triangles.iter().zip(positions) {
+            assert_eq!(
+                triangle.0.map(|i| cube.vertices[i as usize].position),
+                positions
+            );
+        }
+    }
+
+    #[test]
+    fn dedup_keeps_distinct_normals() {
+        let mut cube = crate::render::models::cube();
+        assert_eq!(cube.dedup_vertices(1e-3), 0);
+        assert_eq!(cube.vertices.len(), 24);
+
+        let mut doubled = crate::render::models::cube();
+        doubled.merge(&crate::render::models::cube(), &na::Matrix4::identity());
+        assert_eq!(doubled.dedup_vertices(1e-3), 24);
+        assert_eq!(doubled.vertices.len(), 24);
+        assert_eq!(triangles(&doubled)[..12], triangles(&doubled)[12..],);
+    }
+
+    #[test]
+    fn dedup_merges_within_epsilon() {
+        let normal = na::vector![0.0, 0.0, 1.0];
+        let mut mesh = Mesh::new(
+            vec![
+                ClassicVertex::new(na::point![0.0, 0.0, 0.0], normal),
+                ClassicVertex::new(na::point![1.0, 0.0, 0.0], normal),
+                ClassicVertex::new(na::point![0.0, 1.0, 0.0], normal),
+                ClassicVertex::new(na::point![1.0, 0.0, 1e-6], normal),
+            ],
+            vec![Triangle([0, 1, 2]), Triangle([2, 3, 0])],
+        );
+
+        assert_eq!(mesh.dedup_vertices(1e-4), 1);
+        assert_eq!(triangles(&mesh), [[0, 1, 2], [2, 1, 0]]);
+    }
+
+    #[test]
+    fn cube_bounding_box() {
+        assert_eq!(
+            crate::render::models::cube().bounding_box(),
+            (na::point![-1.0, -1.0, -1.0], na::point![1.0, 1.0, 1.0])
+        );
+    }
+
+    #[test]
+    fn bounding_box_follows_the_vertices() {
+        let mesh = Mesh::new(
+            vec![
+                na::point![2.0, -1.0, 0.5],
+                na::point![-3.0, 4.0, 0.5],
+                na::point![0.0, 0.0, -7.0],
+            ],
+            Vec::new(),
+        );
+        assert_eq!(
+            mesh.bounding_box(),
+            (na::point![-3.0, -1.0, -7.0], na::point![2.0, 4.0, 0.5])
+        );
+
+        let single = Mesh::new(vec![na::point![1.0, 2.0, 3.0]], Vec::new());
+        assert_eq!(
+            single.bounding_box(),
+            (na::point![1.0, 2.0, 3.0], na::point![1.0, 2.0, 3.0])
+        );
+
+        assert_eq!(
+            Mesh::<na::Point3<f32>>::empty().bounding_box(),
+            (na::Point3::origin(), na::Point3::origin())
+        );
+    }
+
+    #[test]
+    fn rotation_moves_vertices_and_normals() {
+        let mut cube = crate::render::models::cube();
+        let rotation =
+            na::Rotation3::from_axis_angle(&na::Vector3::z_axis(), std::f32::consts::FRAC_PI_2)
+                .to_homogeneous();
+
+        cube.apply_transform(&rotation);
+
+        // Vertex 16 is the corner (1, 1, 1) of the right face
+        assert_near(
+            &cube.vertices[16].position.coords,
+            &na::vector![-1.0, 1.0, 1.0],
+        );
+        assert_near(&cube.vertices[16].normal, &na::vector![0.0, 1.0, 0.0]);
+        assert_eq!(
+            cube.bounding_box(),
+            crate::render::models::cube().bounding_box()
+        );
+    }
+
+    #[test]
+    fn normals_use_the_inverse_transpose() {
+        let mut mesh = Mesh::new(
+            vec![ClassicVertex::new(
+                na::point![1.0, 1.0, 0.0],
+                na::vector![1.0, 1.0, 0.0].normalize(),
+            )],
+            Vec::new(),
+        );
+        let scaling = na::Matrix4::new_nonuniform_scaling(&na::vector![2.0, 1.0, 1.0])
+            .append_translation(&na::vector![0.0, 0.0, 3.0]);
+
+        mesh.apply_transform(&scaling);
+
+        assert_near(
+            &mesh.vertices[0].position.coords,
+            &na::vector![2.0, 1.0, 3.0],
+        );
+        assert_near(
+            &mesh.vertices[0].normal,
+            &na::vector![1.0, 2.0, 0.0].normalize(),
+        );
+    }
+
+    #[test]
+    fn merged_cubes_keep_their_geometry() {
+        let mut cubes = crate::render::models::cube();
+        let shift = na::Translation3::new(3.0, 0.0, 0.0).to_homogeneous();
+        cubes.merge(&crate::render::models::cube(), &shift);
+
+        assert_eq!(cubes.vertices.len(), 48);
+        assert_eq!(cubes.triangles.len(), 24);
+        assert_eq!(
+            triangles(&cubes)[12..],
+            triangles(&crate::render::models::cube())
+                .iter()
+                .map(|t| t.map(|i| i + 24))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            cubes.bounding_box(),
+            (na::point![-1.0, -1.0, -1.0], na::point![4.0, 1.0, 1.0])
+        );
+
+        cubes.dedup_vertices_by_key(|v| quantized(&v.position.coords, 1e-3));
+        assert_eq!(cubes.vertices.len(), 16);
+        assert_eq!(cubes.triangles.len(), 24);
+    }
+
+    #[test]
+    fn merging_into_an_empty_mesh_copies() {
+        let mut mesh = Mesh::empty();
+        mesh.merge(&crate::render::models::cube(), &na::Matrix4::identity());
+
+        assert_eq!(triangles(&mesh), triangles(&crate::render::models::cube()));
+        assert_eq!(mesh.vertices.len(), 24);
+    }
+}
@@ -1,10 +1,12 @@
 pub mod color;
 pub mod drawbuffer;
+pub mod export;
 pub mod gl_drawable;
 pub mod gl_mesh;
 pub mod gl_program;
 pub mod gl_texture;
 pub mod gridable;
+pub mod light;
 pub mod mesh;
 pub mod models;
 pub mod opengl;
@@ -86,6 +86,109 @@ pub fn inverse_cube() -> Mesh<ClassicVertex> {
     cube
 }
 
+/// Unit UV-sphere with a single vertex at each pole
+pub fn sphere(stacks: u32, slices: u32) -> Mesh<ClassicVertex> {
+    let stacks = stacks.max(2);
+    let slices = slices.max(3);
+
+    let mut vertices = vec![ClassicVertex::new(
+        na::point![0.0, 1.0, 0.0],
+        na::vector![0.0, 1.0, 0.0],
+    )];
+
+    for stack in 1..stacks {
+        let phi = stack as f32 / stacks as f32 * std::f32::consts::PI;
+        for slice in 0..slices {
+            let theta = slice as f32 / slices as f32 * std::f32::consts::TAU;
+            let normal = na::vector![phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin()];
+            vertices.push(ClassicVertex::new(na::Point3::from(normal), normal));
+        }
+    }
+
+    let south_pole = vertices.len() as u32;
+    vertices.push(ClassicVertex::new(
+        na::point![0.0, -1.0, 0.0],
+        na::vector![0.0, -1.0, 0.0],
+    ));
+
+    let ring = |stack: u32, slice: u32| 1 + (stack - 1) * slices + slice % slices;
+
+    let mut triangles = Vec::new();
+    for slice in 0..slices {
+        triangles.push(Triangle([0, ring(1, slice + 1), ring(1, slice)]));
+
+        for stack in 1..stacks - 1 {
+            triangles.push(Triangle([
+                ring(stack, slice),
+                ring(stack, slice + 1),
+                ring(stack + 1, slice),
+            ]));
+            triangles.push(Triangle([
+                ring(stack, slice + 1),
+                ring(stack + 1, slice + 1),
+                ring(stack + 1, slice),
+            ]));
+        }
+
+        triangles.push(Triangle([
+            south_pole,
+            ring(stacks - 1, slice),
+            ring(stacks - 1, slice + 1),
+        ]));
+    }
+
+    Mesh {
+        vertices,
+        triangles,
+    }
+}
+
+/// Cylinder of radius 1 along the y axis, spanning y in [-1, 1], with closed caps
+pub fn cylinder(slices: u32) -> Mesh<ClassicVertex> {
+    let slices = slices.max(3);
+    let up = na::vector![0.0, 1.0, 0.0];
+    let down = na::vector![0.0, -1.0, 0.0];
+
+    let ring = |y: f32| {
+        (0..slices).map(move |slice| {
+            let theta = slice as f32 / slices as f32 * std::f32::consts::TAU;
+            na::point![theta.cos(), y, theta.sin()]
+        })
+    };
+
+    // Side top ring, side bottom ring, top cap ring, bottom cap ring and cap centers
+    let vertices: Vec<_> = ring(1.0)
+        .chain(ring(-1.0))
+        .map(|p| ClassicVertex::new(p, na::vector![p.x, 0.0, p.z]))
+        .chain(ring(1.0).map(|p| ClassicVertex::new(p, up)))
+        .chain(ring(-1.0).map(|p| ClassicVertex::new(p, down)))
+        .chain([
+            ClassicVertex::new(na::point![0.0, 1.0, 0.0], up),
+            ClassicVertex::new(na::point![0.0, -1.0, 0.0], down),
+        ])
+        .collect();
+
+    let top_center = 4 * slices;
+    let bottom_center = top_center + 1;
+
+    let triangles = (0..slices)
+        .flat_map(|slice| {
+            let next = (slice + 1) % slices;
+            [
+                Triangle([slice, next, slices + slice]),
+                Triangle([next, slices + next, slices + slice]),
+                Triangle([top_center, 2 * slices + next, 2 * slices + slice]),
+                Triangle([bottom_center, 3 * slices + slice, 3 * slices + next]),
+            ]
+        })
+        .collect();
+
+    Mesh {
+        vertices,
+        triangles,
+    }
+}
+
 /// For GlLineStrip
 pub fn wire_cube() -> Vec<na::Point3<f32>> {
     vec![
@@ -113,32 +216,50 @@ pub fn wire_cube() -> Vec<na::Point3<f32>> {
 
 /// For GlLines
 pub fn wire_grid() -> Vec<na::Point3<f32>> {
-    wire_grid_from_fn(|u, v, w| na::point![u as f32 / 3.0, v as f32 / 3.0, w as f32 / 3.0])
+    wire_lattice(4)
 }
 
 pub fn wire_grid_from_fn<F: Fn(usize, usize, usize) -> na::Point3<f32>>(
     f: F,
 ) -> Vec<na::Point3<f32>> {
-    (0..3)
-        .cartesian_product(0..4)
-        .cartesian_product(0..4)
+    wire_lattice_from_fn(4, f)
+}
+
+/// Edges of a `size`×`size`×`size` lattice spanning [-1, 1] in every coordinate, for GlLines
+pub fn wire_lattice(size: usize) -> Vec<na::Point3<f32>> {
+    let step = 1.0 / (size - 1) as f32;
+    wire_lattice_from_fn(size, |u, v, w| {
+        na::point![u as f32 * step, v as f32 * step, w as f32 * step]
+    })
+}
+
+/// Edges of a `size`×`size`×`size` lattice with points `2 * f(u, v, w) - 1`
+pub fn wire_lattice_from_fn<F: Fn(usize, usize, usize) -> na::Point3<f32>>(
+    size: usize,
+    f: F,
+) -> Vec<na::Point3<f32>> {
+    let last = size - 1;
+
+    (0..last)
+        .cartesian_product(0..size)
+        .cartesian_product(0..size)
         .flat_map(|((u, v), w)| {
             let un = u + 1;
             [f(u, v, w), f(un, v, w)]
         })
         .chain(
-            (0..4)
-                .cartesian_product(0..3)
-                .cartesian_product(0..4)
+            (0..size)
+                .cartesian_product(0..last)
+                .cartesian_product(0..size)
                 .flat_map(|((u, v), w)| {
                     let vn = v + 1;
                     [f(u, v, w), f(u, vn, w)]
                 }),
         )
         .chain(
-            (0..4)
-                .cartesian_product(0..4)
-                .cartesian_product(0..3)
+            (0..size)
+                .cartesian_product(0..size)
+                .cartesian_product(0..last)
                 .flat_map(|((u, v), w)| {
                     let wn = w + 1;
                     [f(u, v, w), f(u, v, wn)]
@@ -192,3 +313,109 @@ pub fn rect() -> Mesh<na::Point3<f32>> {
         vec![Triangle([2, 1, 0]), Triangle([3, 2, 0])],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that every triangle is wound counter-clockwise when looked at from the side its
+    /// vertex normals point to, so that it survives back face culling
+    fn assert_ccw_winding(mesh: &Mesh<ClassicVertex>) {
+        for triangle in &mesh.triangles {
+            let [a, b, c] = triangle.0.map(|i| mesh.vertices[i as usize]);
+            let face_normal = (b.position - a.position).cross(&(c.position - a.position));
+            for vertex in [a, b, c] {
+                assert!(
+                    face_normal.dot(&vertex.normal) > 0.0,
+                    "{triangle:?} is wound against its normals"
+                );
+            }
+        }
+    }
+
+    fn assert_unit_normals(mesh: &Mesh<ClassicVertex>) {
+        for vertex in &mesh.vertices {
+            assert!((vertex.normal.norm() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn sphere_vertices_lie_on_the_unit_sphere() {
+        for (stacks, slices) in [(2, 3), (8, 16), (17, 5)] {
+            let sphere = sphere(stacks, slices);
+
+            assert_eq!(sphere.vertices.len() as u32, 2 + (stacks - 1) * slices);
+            assert_eq!(sphere.triangles.len() as u32, 2 * slices * (stacks - 1));
+            for vertex in &sphere.vertices {
+                assert!((vertex.position.coords.norm() - 1.0).abs() < 1e-5);
+                assert!((vertex.normal - vertex.position.coords).norm() < 1e-5);
+            }
+            assert_ccw_winding(&sphere);
+        }
+    }
+
+    #[test]
+    fn sphere_poles_are_shared() {
+        let sphere = sphere(6, 10);
+        for pole in [1.0, -1.0] {
+            let poles: Vec<_> = sphere
+                .vertices
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| v.position == na::point![0.0, pole, 0.0])
+                .map(|(i, _)| i as u32)
+                .collect();
+            assert_eq!(poles.len(), 1);
+
+            let fan = sphere
+                .triangles
+                .iter()
+                .filter(|t| t.0.contains(&poles[0]))
+                .count();
+            assert_eq!(fan, 10);
+        }
+    }
+
+    #[test]
+    fn closed_models_face_outward() {
+        for mesh in [cube(), sphere(5, 7), cylinder(9)] {
+            assert_ccw_winding(&mesh);
+            assert_unit_normals(&mesh);
+            assert!(mesh
+                .vertices
+                .iter()
+                .all(|v| v.normal.dot(&v.position.coords) > 0.0));
+        }
+    }
+
+    #[test]
+    fn inverse_cube_faces_inward() {
+        let cube = inverse_cube();
+        assert_ccw_winding(&cube);
+        assert_unit_normals(&cube);
+        assert!(cube
+            .vertices
+            .iter()
+            .all(|v| v.normal.dot(&v.position.coords) < 0.0));
+    }
+
+    #[test]
+    fn double_plane_faces_both_ways() {
+        let plane = double_plane();
+        assert_ccw_winding(&plane);
+        assert_unit_normals(&plane);
+        assert_eq!(plane.vertices.iter().map(|v| v.normal.y).sum::<f32>(), 0.0);
+    }
+
+    #[test]
+    fn wire_lattice_edges() {
+        for size in 2..=6 {
+            assert_eq!(wire_lattice(size).len(), 2 * 3 * size * size * (size - 1));
+        }
+
+        assert_eq!(wire_grid(), wire_lattice(4));
+        assert!(wire_lattice(3)
+            .iter()
+            .all(|p| p.iter().all(|&c| [-1.0, 0.0, 1.0].contains(&c))));
+    }
+}
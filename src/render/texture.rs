@@ -1,6 +1,9 @@
 use std::ops::RangeInclusive;
 
-use image::{DynamicImage, GenericImage, GenericImageView, RgbImage, Rgba, RgbaImage};
+use image::{
+    error::{ImageError, ParameterError, ParameterErrorKind},
+    DynamicImage, GenericImage, GenericImageView, RgbImage, Rgba, RgbaImage,
+};
 use itertools::Itertools;
 use nalgebra::{vector, Vector2};
 
@@ -19,6 +22,31 @@ impl Texture {
         Self { image }
     }
 
+    /// `data` holds rows of RGBA pixels, top to bottom
+    pub fn from_rgba(width: u32, height: u32, data: Vec<u8>) -> image::ImageResult<Self> {
+        let dimension_mismatch = || {
+            ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            ))
+        };
+
+        if data.len() as u64 != 4 * width as u64 * height as u64 {
+            return Err(dimension_mismatch());
+        }
+
+        let image_buffer =
+            RgbaImage::from_raw(width, height, data).ok_or_else(dimension_mismatch)?;
+
+        Ok(Self {
+            image: DynamicImage::ImageRgba8(image_buffer),
+        })
+    }
+
+    /// Format is deduced from the extension
+    pub fn save(&self, path: &std::path::Path) -> image::ImageResult<()> {
+        self.image.save(path)
+    }
+
     pub fn new_rgba(width: u32, height: u32) -> Self {
         let image_buffer = RgbaImage::new(width, height);
         let image = DynamicImage::ImageRgba8(image_buffer);
@@ -47,32 +75,49 @@ impl Texture {
         }
     }
 
-    pub fn flood_fill(&mut self, x: i32, y: i32, color: Rgba<u8>, wrap_x: bool, wrap_y: bool) {
+    /// Pixels are colored when pushed onto the stack, so each one is visited at most once.
+    /// Returns the number of colored pixels.
+    pub fn flood_fill(
+        &mut self,
+        x: i32,
+        y: i32,
+        color: Rgba<u8>,
+        wrap_x: bool,
+        wrap_y: bool,
+    ) -> usize {
         let start_color = self.image.get_pixel(x as u32, y as u32);
+        if start_color == color {
+            return 0;
+        }
+
+        self.image.put_pixel(x as u32, y as u32, color);
         let mut to_color = vec![(x, y)];
-        to_color.reserve(self.image.height() as usize * self.image.width() as usize);
+        let mut colored = 1;
 
         while let Some((x, y)) = to_color.pop() {
-            if self.image.get_pixel(x as u32, y as u32) == start_color {
-                self.image.put_pixel(x as u32, y as u32, color);
-
-                self.add_for_fill(&mut to_color, x + 1, y, start_color, wrap_x, wrap_y);
-                self.add_for_fill(&mut to_color, x, y + 1, start_color, wrap_x, wrap_y);
-                self.add_for_fill(&mut to_color, x - 1, y, start_color, wrap_x, wrap_y);
-                self.add_for_fill(&mut to_color, x, y - 1, start_color, wrap_x, wrap_y);
+            for (x, y) in [(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)] {
+                let Some((x, y)) = self.wrapped_pixel(x, y, wrap_x, wrap_y) else {
+                    continue;
+                };
+
+                if self.image.get_pixel(x, y) == start_color {
+                    self.image.put_pixel(x, y, color);
+                    to_color.push((x as i32, y as i32));
+                    colored += 1;
+                }
             }
         }
+
+        colored
     }
 
-    fn add_for_fill(
+    fn wrapped_pixel(
         &self,
-        to_color: &mut Vec<(i32, i32)>,
         mut x: i32,
         mut y: i32,
-        start_color: Rgba<u8>,
         wrap_x: bool,
         wrap_y: bool,
-    ) {
+    ) -> Option<(u32, u32)> {
         if wrap_x {
             x = x.rem_euclid(self.image.width() as i32);
         }
@@ -81,10 +126,52 @@ impl Texture {
             y = y.rem_euclid(self.image.height() as i32);
         }
 
-        if self.image.in_bounds(x as u32, y as u32)
-            && self.image.get_pixel(x as u32, y as u32) == start_color
-        {
-            to_color.push((x, y));
+        (x >= 0 && y >= 0 && self.image.in_bounds(x as u32, y as u32))
+            .then_some((x as u32, y as u32))
+    }
+
+    /// Separable gaussian blur with wrap-around edges
+    pub fn blur(&mut self, sigma: f64) {
+        if sigma <= 0.0 {
+            return;
+        }
+
+        let radius = (3.0 * sigma).ceil() as i64;
+        let kernel: Vec<_> = (-radius..=radius)
+            .map(|i| (-(i * i) as f64 / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let kernel_sum: f64 = kernel.iter().sum();
+        let kernel: Vec<_> = kernel.iter().map(|k| k / kernel_sum).collect();
+
+        let (width, height) = (self.image.width() as i64, self.image.height() as i64);
+        let pixels: Vec<[f64; 4]> = Itertools::cartesian_product(0..height, 0..width)
+            .map(|(y, x)| self.image.get_pixel(x as u32, y as u32).0.map(|c| c as f64))
+            .collect();
+
+        let convolve = |pixels: &[[f64; 4]], step: (i64, i64)| -> Vec<[f64; 4]> {
+            Itertools::cartesian_product(0..height, 0..width)
+                .map(|(y, x)| {
+                    let mut sum = [0.0; 4];
+                    for (k, weight) in (-radius..=radius).zip(&kernel) {
+                        let sx = (x + k * step.0).rem_euclid(width);
+                        let sy = (y + k * step.1).rem_euclid(height);
+                        let pixel = pixels[(sy * width + sx) as usize];
+                        (0..4).for_each(|c| sum[c] += weight * pixel[c]);
+                    }
+                    sum
+                })
+                .collect()
+        };
+
+        let blurred = convolve(&convolve(&pixels, (1, 0)), (0, 1));
+
+        for (i, pixel) in blurred.into_iter().enumerate() {
+            let (x, y) = (i as i64 % width, i as i64 / width);
+            self.image.put_pixel(
+                x as u32,
+                y as u32,
+                Rgba(pixel.map(|c| c.round().clamp(0.0, 255.0) as u8)),
+            );
         }
     }
 
@@ -152,6 +239,56 @@ impl Texture {
         }
     }
 
+    /// Coordinates are in range [0, 1], pixel centers are at half-integer offsets.
+    /// Returned channels are in range [0, 1].
+    pub fn sample_bilinear(&self, u: f64, v: f64, wrap_x: bool, wrap_y: bool) -> [f64; 4] {
+        let x = u * self.image.width() as f64 - 0.5;
+        let y = v * self.image.height() as f64 - 0.5;
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        let [x_0, x_1] = Self::sample_indices(x, self.image.width(), wrap_x);
+        let [y_0, y_1] = Self::sample_indices(y, self.image.height(), wrap_y);
+
+        let pixel = |x, y| self.image.get_pixel(x, y).0.map(|c| c as f64 / 255.0);
+        let lerp =
+            |a: [f64; 4], b: [f64; 4], t: f64| std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t);
+
+        lerp(
+            lerp(pixel(x_0, y_0), pixel(x_1, y_0), fx),
+            lerp(pixel(x_0, y_1), pixel(x_1, y_1), fx),
+            fy,
+        )
+    }
+
+    /// Bilinear resampling, edges are clamped
+    pub fn resized(&self, width: u32, height: u32) -> Texture {
+        let mut resized = Self::new_rgba(width, height);
+
+        for (x, y) in Itertools::cartesian_product(0..width, 0..height) {
+            let sample = self.sample_bilinear(
+                (x as f64 + 0.5) / width as f64,
+                (y as f64 + 0.5) / height as f64,
+                false,
+                false,
+            );
+
+            resized.put(x, y, Rgba(sample.map(|c| (c * 255.0).round() as u8)));
+        }
+
+        resized
+    }
+
+    fn sample_indices(coord: f64, size: u32, wrap: bool) -> [u32; 2] {
+        let first = coord.floor() as i64;
+        [first, first + 1].map(|i| {
+            if wrap {
+                i.rem_euclid(size as i64) as u32
+            } else {
+                i.clamp(0, size as i64 - 1) as u32
+            }
+        })
+    }
+
     pub fn width(&self) -> f32 {
         self.image.width() as f32
     }
@@ -165,4 +302,243 @@ impl Texture {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Texture whose every pixel has a different color
+    fn gradient(width: u32, height: u32) -> Texture {
+        let data = Itertools::cartesian_product(0..height, 0..width)
+            .flat_map(|(y, x)| [(40 * x) as u8, (40 * y) as u8, 200, 255 - x as u8])
+            .collect();
+        Texture::from_rgba(width, height, data).unwrap()
+    }
+
+    #[test]
+    fn saved_png_loads_back() {
+        let path = std::env::temp_dir().join("phyesthon_texture_test.png");
+        let texture = gradient(3, 2);
+
+        texture.save(&path).unwrap();
+        let loaded = Texture::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.image.to_rgba8(), texture.image.to_rgba8());
+    }
+
+    fn checkerboard() -> Texture {
+        let (black, white) = ([0, 0, 0, 255], [255, 255, 255, 255]);
+        Texture::from_rgba(2, 2, [white, black, black, white].concat()).unwrap()
+    }
 
+    fn assert_channels(sample: [f64; 4], expected: [f64; 4]) {
+        assert!(
+            sample
+                .iter()
+                .zip(expected)
+                .all(|(s, e)| (s - e).abs() < 1e-9),
+            "{sample:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn checkerboard_center_is_the_average() {
+        assert_channels(
+            checkerboard().sample_bilinear(0.5, 0.5, false, false),
+            [0.5, 0.5, 0.5, 1.0],
+        );
+    }
+
+    #[test]
+    fn pixel_centers_sample_exactly() {
+        let texture = gradient(3, 2);
+        for (x, y) in Itertools::cartesian_product(0..3, 0..2) {
+            let expected = texture.image.get_pixel(x, y).0.map(|c| c as f64 / 255.0);
+            let (u, v) = ((x as f64 + 0.5) / 3.0, (y as f64 + 0.5) / 2.0);
+            assert_channels(texture.sample_bilinear(u, v, true, true), expected);
+        }
+    }
+
+    #[test]
+    fn edges_wrap_or_clamp() {
+        let texture = checkerboard();
+
+        // Halfway between the first column and the wrapped-around last one
+        assert_channels(
+            texture.sample_bilinear(0.0, 0.25, true, false),
+            [0.5, 0.5, 0.5, 1.0],
+        );
+        assert_channels(texture.sample_bilinear(0.0, 0.25, false, false), [1.0; 4]);
+        assert_channels(
+            texture.sample_bilinear(0.25, 1.0, false, true),
+            [0.5, 0.5, 0.5, 1.0],
+        );
+        assert_channels(
+            texture.sample_bilinear(0.25, 1.0, false, false),
+            [0.0, 0.0, 0.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn single_red_pixel_from_rgba() {
+        let texture = Texture::from_rgba(1, 1, vec![255, 0, 0, 255]).unwrap();
+
+        assert_eq!((texture.width(), texture.height()), (1.0, 1.0));
+        assert_eq!(texture.image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn rgba_rows_go_top_to_bottom() {
+        let texture = gradient(3, 2);
+        assert_eq!(texture.image.get_pixel(2, 0), Rgba([80, 0, 200, 253]));
+        assert_eq!(texture.image.get_pixel(0, 1), Rgba([0, 40, 200, 255]));
+    }
+
+    #[test]
+    fn wrong_rgba_length_is_rejected() {
+        for (width, height, length) in [(1, 1, 3), (2, 2, 12), (2, 1, 12), (0, 5, 4)] {
+            assert!(matches!(
+                Texture::from_rgba(width, height, vec![0; length]),
+                Err(ImageError::Parameter(_))
+            ));
+        }
+    }
+
+    /// Opaque black texture with a single fully red pixel
+    fn bright_pixel(size: u32, x: u32, y: u32) -> Texture {
+        let mut texture =
+            Texture::from_rgba(size, size, [0, 0, 0, 255].repeat((size * size) as usize)).unwrap();
+        texture.put(x, y, Rgba([255, 0, 0, 255]));
+        texture
+    }
+
+    fn red(texture: &Texture, x: u32, y: u32) -> u8 {
+        texture.image.get_pixel(x, y)[0]
+    }
+
+    #[test]
+    fn blur_spreads_and_preserves_intensity() {
+        let mut texture = bright_pixel(9, 4, 4);
+        texture.blur(1.0);
+
+        let total: u32 = texture.image.pixels().map(|(_, _, p)| p[0] as u32).sum();
+        // Each of the 7x7 pixels within the kernel radius is rounded by at most 0.5
+        assert!(total.abs_diff(255) <= 25, "{total}");
+
+        assert!(red(&texture, 4, 4) < 255);
+        assert!(red(&texture, 4, 4) > red(&texture, 5, 4));
+        assert!(red(&texture, 5, 4) > red(&texture, 5, 5));
+        assert!(red(&texture, 5, 5) > 0);
+        assert_eq!(red(&texture, 3, 4), red(&texture, 5, 4));
+        assert_eq!(red(&texture, 4, 3), red(&texture, 4, 5));
+        assert_eq!(red(&texture, 0, 0), 0);
+        assert!(texture
+            .image
+            .pixels()
+            .all(|(_, _, p)| p[3] == 255 && p[1] == 0));
+    }
+
+    #[test]
+    fn blur_wraps_around() {
+        let mut texture = bright_pixel(9, 0, 0);
+        texture.blur(1.0);
+
+        assert_eq!(red(&texture, 8, 8), red(&texture, 1, 1));
+        assert_eq!(red(&texture, 8, 0), red(&texture, 1, 0));
+        assert!(red(&texture, 8, 8) > 0);
+    }
+
+    #[test]
+    fn blur_without_spread_is_identity() {
+        let mut texture = bright_pixel(3, 1, 1);
+        texture.blur(0.0);
+        assert_eq!(
+            texture.image.to_rgba8(),
+            bright_pixel(3, 1, 1).image.to_rgba8()
+        );
+    }
+
+    #[test]
+    fn upscaling_keeps_corner_colors() {
+        let texture = gradient(2, 2);
+        let resized = texture.resized(4, 4);
+
+        assert_eq!((resized.width(), resized.height()), (4.0, 4.0));
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            assert_eq!(
+                resized.image.get_pixel(3 * x, 3 * y),
+                texture.image.get_pixel(x, y)
+            );
+        }
+
+        // Inner pixels are a quarter of the way between the original ones
+        let mid = resized.image.get_pixel(1, 0);
+        assert_eq!(mid, Rgba([10, 0, 200, 255]));
+    }
+
+    #[test]
+    fn resizing_to_the_same_size_is_identity() {
+        let texture = gradient(5, 3);
+        assert_eq!(
+            texture.resized(5, 3).image.to_rgba8(),
+            texture.image.to_rgba8()
+        );
+    }
+
+    #[test]
+    fn downscaling_samples_the_center() {
+        let texture = checkerboard().resized(4, 4).resized(1, 1);
+        assert_eq!(texture.image.get_pixel(0, 0), Rgba([128, 128, 128, 255]));
+    }
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    fn colored(texture: &Texture, color: Rgba<u8>) -> usize {
+        texture
+            .image
+            .pixels()
+            .filter(|(_, _, p)| *p == color)
+            .count()
+    }
+
+    #[test]
+    fn open_space_is_colored_once_per_pixel() {
+        let mut texture = Texture::new_rgba(360, 360);
+        texture.fill(BLUE);
+
+        assert_eq!(texture.flood_fill(100, 200, RED, true, true), 360 * 360);
+        assert_eq!(colored(&texture, RED), 360 * 360);
+        assert_eq!(texture.flood_fill(0, 0, RED, true, true), 0);
+    }
+
+    #[test]
+    fn fill_stops_at_walls_unless_wrapping() {
+        // A vertical wall at x = 2 splits the texture in two
+        let mut texture = Texture::new_rgba(6, 4);
+        texture.fill(BLUE);
+        (0..4).for_each(|y| texture.put(2, y, RED));
+
+        let mut bounded = Texture::from_rgba(6, 4, texture.image.to_rgba8().into_raw()).unwrap();
+        assert_eq!(bounded.flood_fill(0, 0, RED, false, true), 8);
+        assert_eq!(colored(&bounded, BLUE), 12);
+        assert!((0..4).all(|y| bounded.image.get_pixel(5, y) == BLUE));
+
+        // Wrapping in x reaches the right side around the edge
+        assert_eq!(texture.flood_fill(0, 0, RED, true, false), 20);
+        assert_eq!(colored(&texture, BLUE), 0);
+    }
+
+    #[test]
+    fn inverse_fill_swaps_colors() {
+        let mut texture = Texture::new_rgba(3, 3);
+        texture.fill(BLUE);
+        texture.put(1, 1, RED);
+
+        texture.flood_fill_inv(0, 0, false, false);
+        assert_eq!(colored(&texture, RED), 9);
+
+        texture.flood_fill_inv(2, 2, false, false);
+        assert_eq!(colored(&texture, BLUE), 9);
+    }
+}
@@ -1,19 +1,67 @@
 use crate::numerics::{
     bezier,
     ode::{PlainODE, State},
+    FloatFn,
 };
 use itertools::Itertools;
 use nalgebra as na;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::rc::Rc;
 
-pub const POINT_COUNT: usize = 64;
-pub const SPACE_DIM: usize = POINT_COUNT * 3;
-pub const ODE_DIM: usize = SPACE_DIM * 2;
+pub const LATTICE_SIZE: usize = 4;
+pub const POINT_COUNT: usize = point_count(LATTICE_SIZE);
+pub const SPACE_DIM: usize = space_dim(LATTICE_SIZE);
+pub const ODE_DIM: usize = ode_dim(LATTICE_SIZE);
 pub const ROOM_HALF_SIZE: f64 = 5.0;
 
+pub const fn point_count(lattice_size: usize) -> usize {
+    lattice_size * lattice_size * lattice_size
+}
+
+pub const fn space_dim(lattice_size: usize) -> usize {
+    point_count(lattice_size) * 3
+}
+
+pub const fn ode_dim(lattice_size: usize) -> usize {
+    space_dim(lattice_size) * 2
+}
+
 pub type JellyState = State<ODE_DIM>;
 
+/// Positions of all points, the first half of a jelly state `y`
+pub fn positions(y: &[f64]) -> &[f64] {
+    &y[..y.len() / 2]
+}
+
+/// Velocities of all points, the second half of a jelly state `y`
+pub fn velocities(y: &[f64]) -> &[f64] {
+    &y[y.len() / 2..]
+}
+
+pub fn velocities_mut(y: &mut [f64]) -> &mut [f64] {
+    let half = y.len() / 2;
+    &mut y[half..]
+}
+
+pub fn position(y: &[f64], point: usize) -> na::Point3<f64> {
+    na::Point3::from_slice(&positions(y)[3 * point..3 * point + 3])
+}
+
+pub fn velocity(y: &[f64], point: usize) -> na::Vector3<f64> {
+    na::Vector3::from_column_slice(&velocities(y)[3 * point..3 * point + 3])
+}
+
+pub fn set_position(y: &mut [f64], point: usize, position: &na::Point3<f64>) {
+    y[3 * point..3 * point + 3].copy_from_slice(position.coords.as_slice());
+}
+
+pub fn set_velocity(y: &mut [f64], point: usize, velocity: &na::Vector3<f64>) {
+    velocities_mut(y)[3 * point..3 * point + 3].copy_from_slice(velocity.as_slice());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ControlFrameTransform {
     pub translation: na::Vector3<f64>,
     pub rotation: na::Quaternion<f64>,
@@ -32,28 +80,62 @@ impl ControlFrameTransform {
             * na::Rotation3::from(na::UnitQuaternion::new_normalize(self.rotation)).to_homogeneous()
     }
 }
-pub struct JellyODE {
+/// Spring between two lattice points, indices are of the points, not of their coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    pub first: usize,
+    pub second: usize,
+    pub length: f64,
+}
+
+/// Mass-spring model of an `N`×`N`×`N` control lattice, `PlainODE` is implemented for the
+/// lattice sizes from 2 to 6
+pub struct JellyODE<const N: usize = LATTICE_SIZE> {
     point_mass_inverse: f64,
     point_mass: f64,
     pub corner_spring_constant: f64,
     pub inner_spring_constant: f64,
     pub damping_factor: f64,
-    pub elasticity_coefficient: f64,
+    /// Restitution of collisions with the walls perpendicular to each axis,
+    /// only the velocity along that axis is scaled by it
+    pub elasticity_coefficient: na::Vector3<f64>,
+    /// Uniform force acting on every point, scaled by `wind_modulation(t)`
+    pub wind: na::Vector3<f64>,
+    pub wind_modulation: FloatFn<f64>,
+    pub enable_wind: bool,
     control_frame: Rc<RefCell<ControlFrameTransform>>,
+    springs: Vec<Spring>,
+    /// Indices of the springs attached to each point, in the order of `springs`,
+    /// with the sign of the spring force acting on that point
+    point_springs: Vec<Vec<(usize, f64)>>,
 }
 
 impl JellyODE {
+    pub fn default_state() -> JellyState {
+        Self::initial_state()
+    }
+}
+
+impl<const N: usize> JellyODE<N> {
     const MAX_COLLISIONS: usize = 100;
+    const SPACE_DIM: usize = space_dim(N);
 
     pub fn new(control_frame: Rc<RefCell<ControlFrameTransform>>) -> Self {
+        let springs = Self::springs();
+
         Self {
             point_mass: 1.0,
             point_mass_inverse: 1.0,
             corner_spring_constant: 10.0,
             inner_spring_constant: 3.0,
-            elasticity_coefficient: 0.1,
+            elasticity_coefficient: na::vector![0.1, 0.1, 0.1],
             damping_factor: 1.0,
+            wind: na::vector![1.0, 0.0, 0.0],
+            wind_modulation: Box::new(|_| 1.0),
+            enable_wind: false,
             control_frame,
+            point_springs: Self::point_springs(&springs),
+            springs,
         }
     }
 
@@ -66,19 +148,72 @@ impl JellyODE {
         self.point_mass
     }
 
-    pub fn default_state() -> JellyState {
-        JellyState {
+    /// Resting lattice with zero velocities, `D` has to be equal to `ode_dim(N)`
+    pub fn initial_state<const D: usize>() -> State<D> {
+        let cube = bezier::Cube::<f64, N>::new();
+
+        State {
             t: 0.0,
             y: na::SVector::from_iterator(
-                bezier::Cube::new()
-                    .as_flat()
-                    .iter()
-                    .chain([0.0].iter().cycle().take(SPACE_DIM))
-                    .copied(),
+                (0..point_count(N))
+                    .flat_map(|i| cube.flat(i).coords.data.0[0])
+                    .chain(std::iter::repeat_n(0.0, Self::SPACE_DIM)),
             ),
         }
     }
 
+    fn point_index(u: usize, v: usize, w: usize) -> usize {
+        w + v * N + u * N * N
+    }
+
+    /// Structural springs along the lattice edges and shear springs along face diagonals
+    /// of each lattice cell, every spring is listed once
+    pub fn springs() -> Vec<Spring> {
+        let edge_length = 2.0 / (N - 1) as f64;
+        let mut springs = Vec::new();
+
+        for ((u, v), w) in (0..N).cartesian_product(0..N).cartesian_product(0..N) {
+            for ((du, dv), dw) in (-1i64..=1)
+                .cartesian_product(-1i64..=1)
+                .cartesian_product(-1i64..=1)
+            {
+                let nonzero = [du, dv, dw].iter().filter(|&&d| d != 0).count();
+                let [ou, ov, ow] = [u as i64 + du, v as i64 + dv, w as i64 + dw];
+
+                if nonzero == 0
+                    || nonzero == 3
+                    || [ou, ov, ow].iter().any(|&c| c < 0 || c >= N as i64)
+                {
+                    continue;
+                }
+
+                let first = Self::point_index(u, v, w);
+                let second = Self::point_index(ou as usize, ov as usize, ow as usize);
+
+                if first < second {
+                    springs.push(Spring {
+                        first,
+                        second,
+                        length: edge_length * (nonzero as f64).sqrt(),
+                    });
+                }
+            }
+        }
+
+        springs
+    }
+
+    fn point_springs(springs: &[Spring]) -> Vec<Vec<(usize, f64)>> {
+        let mut point_springs = vec![Vec::new(); point_count(N)];
+
+        for (index, spring) in springs.iter().enumerate() {
+            point_springs[spring.first].push((index, -1.0));
+            point_springs[spring.second].push((index, 1.0));
+        }
+
+        point_springs
+    }
+
     /// Force acting on point `p_1`
     fn spring_force(
         p_0: &na::Point3<f64>,
@@ -118,113 +253,154 @@ impl JellyODE {
     }
 
     fn corner_force(
-        &self,
+        corner_spring_constant: f64,
         frame_transform: &na::Matrix4<f64>,
-        state: &JellyState,
+        y: &[f64],
         u: usize,
         v: usize,
         w: usize,
     ) -> na::Vector3<f64> {
-        if (u != 3 && u != 0) || (v != 3 && v != 0) || (w != 3 && w != 0) {
+        if [u, v, w].iter().any(|&c| c != N - 1 && c != 0) {
             na::Vector3::zeros()
         } else {
             let corner_point = Self::corner_point(frame_transform, u, v, w);
-            let idx = (w + v * 4 + u * 16) * 3;
             Self::spring_force(
                 &corner_point,
-                &na::point![state.y[idx], state.y[idx + 1], state.y[idx + 2]],
+                &position(y, Self::point_index(u, v, w)),
                 0.0,
-                self.corner_spring_constant,
+                corner_spring_constant,
             )
         }
     }
 
-    fn coord_neigh_range(i: i64) -> &'static [i64] {
-        if i == 0 {
-            &[0, 1]
-        } else if i == 3 {
-            &[-1, 0]
-        } else {
-            &[-1, 0, 1]
+    /// Sum of the forces of `point_springs` attached to a single point, each spring is
+    /// evaluated once for each of its ends so that points can be handled independently
+    fn inner_force(
+        springs: &[Spring],
+        inner_spring_constant: f64,
+        y: &[f64],
+        point_springs: &[(usize, f64)],
+    ) -> na::Vector3<f64> {
+        let mut force = na::Vector3::zeros();
+
+        for &(index, sign) in point_springs {
+            let spring = springs[index];
+            force += sign
+                * Self::spring_force(
+                    &position(y, spring.first),
+                    &position(y, spring.second),
+                    spring.length,
+                    inner_spring_constant,
+                );
         }
+
+        force
     }
 
-    fn inner_force(&self, state: &JellyState, u: usize, v: usize, w: usize) -> na::Vector3<f64> {
-        let u = u as i64;
-        let v = v as i64;
-        let w = w as i64;
-        let mut force_accumulator = na::Vector3::zeros();
-
-        for &du in Self::coord_neigh_range(u) {
-            for &dv in Self::coord_neigh_range(v) {
-                for &dw in Self::coord_neigh_range(w) {
-                    if (du == 0 && dv == 0 && dw == 0) || (du != 0 && dv != 0 && dw != 0) {
-                        continue;
-                    }
-
-                    let idx = (w + v * 4 + u * 16) as usize * 3;
-                    let idx_other = ((w + dw) + (v + dv) * 4 + (u + du) * 16) as usize * 3;
-                    let position = na::point![state.y[idx], state.y[idx + 1], state.y[idx + 2]];
-                    let other_position = na::point![
-                        state.y[idx_other],
-                        state.y[idx_other + 1],
-                        state.y[idx_other + 2]
-                    ];
-
-                    let diagonal_spring = ((du + dv + dw) % 2).abs() == 0;
-
-                    force_accumulator += Self::spring_force(
-                        &other_position,
-                        &position,
-                        2.0 / 3.0
-                            * if diagonal_spring {
-                                std::f64::consts::SQRT_2
-                            } else {
-                                1.0
-                            },
-                        self.inner_spring_constant,
-                    );
-                }
-            }
+    fn damping_force(damping_factor: f64, y: &[f64], point: usize) -> na::Vector3<f64> {
+        -velocity(y, point) * damping_factor
+    }
+
+    pub fn wind_force(&self, t: f64) -> na::Vector3<f64> {
+        if self.enable_wind {
+            self.wind * (self.wind_modulation)(t)
+        } else {
+            na::Vector3::zeros()
         }
+    }
 
-        force_accumulator
+    fn accelerations(&self, frame_transform: &na::Matrix4<f64>, t: f64, y: &[f64]) -> Vec<f64> {
+        let wind_force = self.wind_force(t);
+        // `self` is not `Sync`, so only plain values are shared with the worker threads
+        let springs = self.springs.as_slice();
+        let point_springs = self.point_springs.as_slice();
+        let inner_spring_constant = self.inner_spring_constant;
+        let corner_spring_constant = self.corner_spring_constant;
+        let damping_factor = self.damping_factor;
+        let point_mass_inverse = self.point_mass_inverse;
+
+        let mut accelerations = vec![0.0; Self::SPACE_DIM];
+        accelerations
+            .par_chunks_mut(3)
+            .enumerate()
+            .for_each(|(point, acceleration)| {
+                let (u, v, w) = (point / (N * N), point / N % N, point % N);
+                let force = Self::corner_force(corner_spring_constant, frame_transform, y, u, v, w)
+                    + Self::inner_force(springs, inner_spring_constant, y, &point_springs[point])
+                    + Self::damping_force(damping_factor, y, point)
+                    + wind_force;
+                acceleration.copy_from_slice((force * point_mass_inverse).as_slice());
+            });
+
+        accelerations
     }
 
-    fn damping_force(&self, state: &JellyState, u: usize, v: usize, w: usize) -> na::Vector3<f64> {
-        let idx = (w + v * 4 + u * 16) * 3 + SPACE_DIM;
-        let velocity = na::vector![state.y[idx], state.y[idx + 1], state.y[idx + 2]];
-        -velocity * self.damping_factor
+    pub fn frame_transform(&self) -> na::Matrix4<f64> {
+        self.control_frame.borrow().compose()
     }
 
-    fn force(
+    /// End points and extensions `length − rest length` of the inner springs
+    /// and of the springs attaching the lattice corners to the control frame
+    pub fn spring_extensions<const D: usize>(
         &self,
+        state: &State<D>,
         frame_transform: &na::Matrix4<f64>,
-        state: &JellyState,
-        u: usize,
-        v: usize,
-        w: usize,
-    ) -> na::Vector3<f64> {
-        self.corner_force(frame_transform, state, u, v, w)
-            + self.inner_force(state, u, v, w)
-            + self.damping_force(state, u, v, w)
+    ) -> Vec<(na::Point3<f64>, na::Point3<f64>, f64)> {
+        let y = state.y.as_slice();
+
+        let inner = self.springs.iter().map(|spring| {
+            let first = position(y, spring.first);
+            let second = position(y, spring.second);
+            (first, second, (second - first).norm() - spring.length)
+        });
+
+        let corners = [0, N - 1]
+            .into_iter()
+            .cartesian_product([0, N - 1])
+            .cartesian_product([0, N - 1])
+            .map(|((u, v), w)| {
+                let corner = Self::corner_point(frame_transform, u, v, w);
+                let point = position(y, Self::point_index(u, v, w));
+                (corner, point, (point - corner).norm())
+            });
+
+        inner.chain(corners).collect()
     }
 
-    fn accelerations(
+    /// Elastic energy `½·k·(length − rest length)²` stored in the inner springs
+    /// and in the springs attaching the lattice corners to the control frame
+    pub fn potential_energy<const D: usize>(
         &self,
+        state: &State<D>,
         frame_transform: &na::Matrix4<f64>,
-        state: &JellyState,
-    ) -> na::SVector<f64, SPACE_DIM> {
-        na::SVector::from_iterator(
-            (0..4)
-                .cartesian_product(0..4)
-                .cartesian_product(0..4)
-                .flat_map(|((u, v), w)| {
-                    let force =
-                        self.force(frame_transform, state, u, v, w) * self.point_mass_inverse;
-                    [force.x, force.y, force.z]
-                }),
-        )
+    ) -> f64 {
+        let y = state.y.as_slice();
+        let energy = |length: f64, rest_length: f64, spring_constant: f64| {
+            0.5 * spring_constant * (length - rest_length).powi(2)
+        };
+
+        let inner: f64 = self
+            .springs
+            .iter()
+            .map(|spring| {
+                let length = (position(y, spring.first) - position(y, spring.second)).norm();
+                energy(length, spring.length, self.inner_spring_constant)
+            })
+            .sum();
+
+        let corners: f64 = [0, N - 1]
+            .into_iter()
+            .cartesian_product([0, N - 1])
+            .cartesian_product([0, N - 1])
+            .map(|((u, v), w)| {
+                let length = (Self::corner_point(frame_transform, u, v, w)
+                    - position(y, Self::point_index(u, v, w)))
+                .norm();
+                energy(length, 0.0, self.corner_spring_constant)
+            })
+            .sum();
+
+        inner + corners
     }
 
     fn collide_position_coordinate(&self, c: &mut f64, vc: &mut f64) -> bool {
@@ -243,41 +419,32 @@ impl JellyODE {
 
     // True on collision
     fn collide(&self, position: &mut na::Point3<f64>, velocity: &mut na::Vector3<f64>) -> bool {
-        let collision = self.collide_position_coordinate(&mut position.x, &mut velocity.x)
-            || self.collide_position_coordinate(&mut position.y, &mut velocity.y)
-            || self.collide_position_coordinate(&mut position.z, &mut velocity.z);
-
-        if collision {
-            velocity.x = velocity.x * self.elasticity_coefficient;
-            velocity.y = velocity.y * self.elasticity_coefficient;
-            velocity.z = velocity.z * self.elasticity_coefficient;
+        let mut collided = false;
+
+        for axis in 0..3 {
+            if self.collide_position_coordinate(&mut position[axis], &mut velocity[axis]) {
+                velocity[axis] *= self.elasticity_coefficient[axis];
+                collided = true;
+            }
         }
 
-        collision
+        collided
     }
 
-    pub fn apply_collisions(&self, mut state: JellyState) -> JellyState {
-        for i in (0..SPACE_DIM).step_by(3) {
+    pub fn apply_collisions<const D: usize>(&self, mut state: State<D>) -> State<D> {
+        let y = state.y.as_mut_slice();
+
+        for point in 0..point_count(N) {
             for _ in 0..Self::MAX_COLLISIONS {
-                let mut position = na::point![state.y[i + 0], state.y[i + 1], state.y[i + 2]];
-                let mut velocity = na::vector![
-                    state.y[i + SPACE_DIM + 0],
-                    state.y[i + SPACE_DIM + 1],
-                    state.y[i + SPACE_DIM + 2]
-                ];
-
-                let collided = self.collide(&mut position, &mut velocity);
-                if collided {
-                    state.y[i + 0] = position.x;
-                    state.y[i + 1] = position.y;
-                    state.y[i + 2] = position.z;
-
-                    state.y[i + SPACE_DIM + 0] = velocity.x;
-                    state.y[i + SPACE_DIM + 1] = velocity.y;
-                    state.y[i + SPACE_DIM + 2] = velocity.z;
-                } else {
+                let mut new_position = position(y, point);
+                let mut new_velocity = velocity(y, point);
+
+                if !self.collide(&mut new_position, &mut new_velocity) {
                     break;
                 }
+
+                set_position(y, point, &new_position);
+                set_velocity(y, point, &new_velocity);
             }
         }
 
@@ -285,17 +452,171 @@ impl JellyODE {
     }
 }
 
-impl PlainODE<ODE_DIM> for JellyODE {
-    fn derivative(&self, state: &JellyState) -> na::SVector<f64, ODE_DIM> {
-        let frame_transform = self.control_frame.borrow().compose();
-        na::SVector::from_iterator(
-            state
-                .y
-                .iter()
-                .skip(SPACE_DIM)
-                .take(SPACE_DIM)
-                .copied()
-                .chain(self.accelerations(&frame_transform, state).iter().copied()),
-        )
+macro_rules! impl_jelly_ode {
+    ($($n:literal),*) => {
+        $(
+            impl PlainODE<{ ode_dim($n) }> for JellyODE<$n> {
+                fn derivative(&self, state: &State<{ ode_dim($n) }>) -> na::SVector<f64, { ode_dim($n) }> {
+                    let frame_transform = self.control_frame.borrow().compose();
+                    na::SVector::from_iterator(
+                        velocities(state.y.as_slice())
+                            .iter()
+                            .copied()
+                            .chain(self.accelerations(&frame_transform, state.t, state.y.as_slice())),
+                    )
+                }
+            }
+        )*
+    };
+}
+
+impl_jelly_ode!(2, 3, 4, 5, 6);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn perturbed_ode<const N: usize>() -> JellyODE<N> {
+        let control_frame = Rc::new(RefCell::new(ControlFrameTransform {
+            translation: na::vector![0.3, -0.2, 0.1],
+            rotation: na::Quaternion::new(0.9, 0.1, 0.3, -0.2),
+        }));
+
+        let mut ode = JellyODE::new(control_frame);
+        ode.enable_wind = true;
+        ode.damping_factor = 0.7;
+        ode.set_point_mass(0.3);
+        ode
+    }
+
+    fn random_state(dim: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..dim).map(|_| rng.gen_range(-2.0..2.0)).collect()
+    }
+
+    /// Serial evaluation of `accelerations`, the force of every spring is computed once
+    /// and added to both of its ends
+    fn serial_accelerations<const N: usize>(
+        ode: &JellyODE<N>,
+        frame_transform: &na::Matrix4<f64>,
+        t: f64,
+        y: &[f64],
+    ) -> Vec<f64> {
+        let mut forces = vec![na::Vector3::zeros(); point_count(N)];
+
+        for spring in &ode.springs {
+            let force = JellyODE::<N>::spring_force(
+                &position(y, spring.first),
+                &position(y, spring.second),
+                spring.length,
+                ode.inner_spring_constant,
+            );
+
+            forces[spring.second] += force;
+            forces[spring.first] -= force;
+        }
+
+        (0..N)
+            .cartesian_product(0..N)
+            .cartesian_product(0..N)
+            .flat_map(|((u, v), w)| {
+                let point = JellyODE::<N>::point_index(u, v, w);
+                let force = JellyODE::<N>::corner_force(
+                    ode.corner_spring_constant,
+                    frame_transform,
+                    y,
+                    u,
+                    v,
+                    w,
+                ) + forces[point]
+                    + JellyODE::<N>::damping_force(ode.damping_factor, y, point)
+                    + ode.wind_force(t);
+                (force * ode.point_mass_inverse).data.0[0]
+            })
+            .collect()
+    }
+
+    fn assert_parallel_matches_serial<const N: usize>() {
+        let ode = perturbed_ode::<N>();
+        let frame_transform = ode.frame_transform();
+        let y = random_state(ode_dim(N));
+
+        assert_eq!(
+            ode.accelerations(&frame_transform, 0.4, &y),
+            serial_accelerations(&ode, &frame_transform, 0.4, &y)
+        );
+    }
+
+    /// Edges of the lattice cells and two diagonals of every cell face
+    fn expected_spring_count(n: usize) -> usize {
+        3 * n * n * (n - 1) + 6 * n * (n - 1) * (n - 1)
+    }
+
+    #[test]
+    fn lattice_2x2x2_springs() {
+        let springs = JellyODE::<2>::springs();
+        assert_eq!(springs.len(), 24);
+
+        let edges = springs.iter().filter(|spring| spring.length == 2.0).count();
+        assert_eq!(edges, 12);
+        assert!(springs
+            .iter()
+            .all(|spring| spring.length == 2.0 || spring.length == 2.0 * 2.0f64.sqrt()));
+    }
+
+    #[test]
+    fn spring_counts() {
+        assert_eq!(JellyODE::<3>::springs().len(), expected_spring_count(3));
+        assert_eq!(JellyODE::<4>::springs().len(), expected_spring_count(4));
+        assert_eq!(JellyODE::<5>::springs().len(), expected_spring_count(5));
+        assert_eq!(JellyODE::<6>::springs().len(), expected_spring_count(6));
+    }
+
+    #[test]
+    fn initial_state_is_at_rest() {
+        let ode = perturbed_ode::<2>();
+        let state = JellyODE::<2>::initial_state::<{ ode_dim(2) }>();
+
+        assert!(velocities(state.y.as_slice()).iter().all(|&v| v == 0.0));
+        assert_eq!(position(state.y.as_slice(), 7), na::point![1.0, 1.0, 1.0]);
+        assert!(ode
+            .spring_extensions(&state, &na::Matrix4::identity())
+            .iter()
+            .all(|(_, _, extension)| extension.abs() < 1e-12));
+    }
+
+    #[test]
+    fn collisions_scale_only_the_bouncing_component() {
+        let mut ode = perturbed_ode::<2>();
+        ode.elasticity_coefficient = na::vector![0.5, 0.25, 0.1];
+
+        let mut position = na::point![ROOM_HALF_SIZE + 1.0, 0.0, 0.0];
+        let mut velocity = na::vector![2.0, 3.0, 4.0];
+        assert!(ode.collide(&mut position, &mut velocity));
+        assert_eq!(position, na::point![ROOM_HALF_SIZE - 1.0, 0.0, 0.0]);
+        assert_eq!(velocity, na::vector![-1.0, 3.0, 4.0]);
+
+        let mut position = na::point![0.0, -ROOM_HALF_SIZE - 0.5, ROOM_HALF_SIZE + 2.0];
+        let mut velocity = na::vector![2.0, -4.0, 10.0];
+        assert!(ode.collide(&mut position, &mut velocity));
+        assert_eq!(
+            position,
+            na::point![0.0, -ROOM_HALF_SIZE + 0.5, ROOM_HALF_SIZE - 2.0]
+        );
+        assert_eq!(velocity, na::vector![2.0, 1.0, -1.0]);
+
+        let mut position = na::point![1.0, 2.0, 3.0];
+        let mut velocity = na::vector![2.0, 3.0, 4.0];
+        assert!(!ode.collide(&mut position, &mut velocity));
+        assert_eq!(velocity, na::vector![2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn parallel_accelerations_match_serial() {
+        assert_parallel_matches_serial::<2>();
+        assert_parallel_matches_serial::<3>();
+        assert_parallel_matches_serial::<4>();
+        assert_parallel_matches_serial::<6>();
     }
 }
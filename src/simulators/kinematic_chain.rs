@@ -1,26 +1,98 @@
-use crate::numerics::{kinematics::flat_chain, Rect, Segment};
+use crate::numerics::{kinematics::flat_chain, Circle, Polygon, Rect, Segment};
 use crate::render::texture::Texture;
 use image::Rgba;
-use std::collections::VecDeque;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64::consts::{PI, TAU};
 
 use nalgebra as na;
 
 pub const CONFIG_SIZE: usize = 360;
 pub const CONFIG_RANGE: std::ops::Range<i64> = 0..(CONFIG_SIZE as i64);
 
-#[derive(Clone, Copy)]
-struct BFSTrove {
-    previous: Option<(usize, usize)>,
-    distance: usize,
+/// 8-connected neighborhood with the cost of each move
+const MOVES: [(i64, i64, f64); 8] = [
+    (0, 1, 1.0),
+    (1, 0, 1.0),
+    (-1, 0, 1.0),
+    (0, -1, 1.0),
+    (1, 1, std::f64::consts::SQRT_2),
+    (1, -1, std::f64::consts::SQRT_2),
+    (-1, 1, std::f64::consts::SQRT_2),
+    (-1, -1, std::f64::consts::SQRT_2),
+];
+
+fn wrapped_neighbor(cell: (usize, usize), d_1: i64, d_2: i64) -> (usize, usize) {
+    (
+        (cell.0 as i64 + d_1).rem_euclid(CONFIG_SIZE as i64) as usize,
+        (cell.1 as i64 + d_2).rem_euclid(CONFIG_SIZE as i64) as usize,
+    )
+}
+
+/// Distances in cells from every configuration to the closest obstructed one
+pub struct ClearanceMap(Vec<[f64; CONFIG_SIZE]>);
+
+impl ClearanceMap {
+    pub fn from_obstructions(config: &ConfigObstuction) -> Self {
+        let mut distances = vec![[f64::INFINITY; CONFIG_SIZE]; CONFIG_SIZE];
+        let mut queue = BinaryHeap::new();
+
+        for (alpha_1, subarray) in config.obstructed.iter().enumerate() {
+            for (alpha_2, &obstructed) in subarray.iter().enumerate() {
+                if obstructed {
+                    distances[alpha_1][alpha_2] = 0.0;
+                    queue.push(QueueNode {
+                        cost: 0.0,
+                        cell: (alpha_1, alpha_2),
+                    });
+                }
+            }
+        }
+
+        while let Some(QueueNode { cost, cell }) = queue.pop() {
+            if cost > distances[cell.0][cell.1] {
+                continue;
+            }
+
+            for (d_1, d_2, step) in MOVES {
+                let next = wrapped_neighbor(cell, d_1, d_2);
+                let distance = cost + step;
+
+                if distance < distances[next.0][next.1] {
+                    distances[next.0][next.1] = distance;
+                    queue.push(QueueNode {
+                        cost: distance,
+                        cell: next,
+                    });
+                }
+            }
+        }
+
+        Self(distances)
+    }
+
+    pub fn clearance(&self, config: &na::Point2<f64>) -> f64 {
+        let cell = config_cell(config);
+        self.0[cell.0][cell.1]
+    }
+
+    /// Cost of a move into `cell`, moves close to obstacles get more expensive with `weight`.
+    /// It is never lower than `step`, so the heuristics stay admissible.
+    fn step_cost(&self, cell: (usize, usize), step: f64, weight: f64) -> f64 {
+        step * (1.0 + weight / self.0[cell.0][cell.1])
+    }
 }
 
 #[derive(Clone, Copy)]
-struct IndexedBFSTrove {
-    trove: BFSTrove,
-    alpha_1: usize,
-    alpha_2: usize,
+struct BFSTrove {
+    previous: Option<(usize, usize)>,
+    distance: f64,
 }
 
+/// Distances from the start over the whole reachable configuration space.
+/// Despite the name, it is computed with Dijkstra's algorithm since diagonal moves are longer.
 pub struct BFSMap(Vec<[Option<BFSTrove>; CONFIG_SIZE]>);
 
 impl BFSMap {
@@ -28,7 +100,12 @@ impl BFSMap {
         Self(vec![[None; CONFIG_SIZE]; CONFIG_SIZE])
     }
 
-    pub fn from_obstructions(start: &Option<na::Point2<f64>>, config: &ConfigObstuction) -> Self {
+    pub fn from_obstructions(
+        start: &Option<na::Point2<f64>>,
+        config: &ConfigObstuction,
+        clearance: &ClearanceMap,
+        clearance_weight: f64,
+    ) -> Self {
         let mut troves: Vec<[Option<BFSTrove>; CONFIG_SIZE]> =
             vec![[None; CONFIG_SIZE]; CONFIG_SIZE];
 
@@ -36,42 +113,40 @@ impl BFSMap {
             return Self(troves);
         };
 
-        let mut queue = VecDeque::from([IndexedBFSTrove {
-            alpha_1: start.x.to_degrees().rem_euclid(360.0).floor() as usize,
-            alpha_2: start.y.to_degrees().rem_euclid(360.0).floor() as usize,
-            trove: BFSTrove {
-                previous: None,
-                distance: 0,
-            },
+        let start_cell = config_cell(start);
+        troves[start_cell.0][start_cell.1] = Some(BFSTrove {
+            previous: None,
+            distance: 0.0,
+        });
+
+        let mut closed = vec![[false; CONFIG_SIZE]; CONFIG_SIZE];
+        let mut queue = BinaryHeap::from([QueueNode {
+            cost: 0.0,
+            cell: start_cell,
         }]);
 
-        while let Some(node) = queue.pop_front() {
-            if let Some(existing_trove) = troves[node.alpha_1][node.alpha_2] {
-                if existing_trove.distance <= node.trove.distance {
-                    continue;
-                }
+        while let Some(QueueNode { cost, cell }) = queue.pop() {
+            if closed[cell.0][cell.1] {
+                continue;
             }
 
-            troves[node.alpha_1][node.alpha_2] = Some(node.trove);
+            closed[cell.0][cell.1] = true;
 
-            for (d_1, d_2) in [(0, 1), (1, 0), (-1, 0), (0, -1)] {
-                let new_alpha_1 =
-                    (node.alpha_1 as i64 + d_1).rem_euclid(CONFIG_SIZE as i64) as usize;
-                let new_alpha_2 =
-                    (node.alpha_2 as i64 + d_2).rem_euclid(CONFIG_SIZE as i64) as usize;
+            for (d_1, d_2, step) in MOVES {
+                let next = wrapped_neighbor(cell, d_1, d_2);
+                let distance = cost + clearance.step_cost(next, step, clearance_weight);
 
-                if !config.obstructed[new_alpha_1][new_alpha_2]
-                    && troves[new_alpha_1][new_alpha_2]
-                        .map_or(true, |t| t.distance > node.trove.distance + 1)
+                if !config.obstructed[next.0][next.1]
+                    && troves[next.0][next.1].map_or(true, |t| t.distance > distance)
                 {
-                    queue.push_back(IndexedBFSTrove {
-                        trove: BFSTrove {
-                            previous: Some((node.alpha_1, node.alpha_2)),
-                            distance: node.trove.distance + 1,
-                        },
-                        alpha_1: new_alpha_1,
-                        alpha_2: new_alpha_2,
-                    })
+                    troves[next.0][next.1] = Some(BFSTrove {
+                        previous: Some(cell),
+                        distance,
+                    });
+                    queue.push(QueueNode {
+                        cost: distance,
+                        cell: next,
+                    });
                 }
             }
         }
@@ -97,22 +172,254 @@ impl BFSMap {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    /// A* degenerates to Dijkstra's algorithm
+    Zero,
+    Euclidean,
+    /// Exact distance without obstacles in the 8-connected grid
+    Octile,
+}
+
+impl Heuristic {
+    /// Distance in cells on the torus of configurations
+    fn estimate(&self, from: (usize, usize), to: (usize, usize)) -> f64 {
+        let wrapped = |a: usize, b: usize| {
+            let diff = a.abs_diff(b);
+            diff.min(CONFIG_SIZE - diff) as f64
+        };
+
+        let d_1 = wrapped(from.0, to.0);
+        let d_2 = wrapped(from.1, to.1);
+
+        match self {
+            Heuristic::Zero => 0.0,
+            Heuristic::Euclidean => d_1.hypot(d_2),
+            Heuristic::Octile => d_1.max(d_2) + (std::f64::consts::SQRT_2 - 1.0) * d_1.min(d_2),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+struct QueueNode {
+    cost: f64,
+    cell: (usize, usize),
+}
+
+impl Eq for QueueNode {}
+
+impl PartialOrd for QueueNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueNode {
+    /// Reversed so that `BinaryHeap` pops the cheapest node
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+fn config_cell(config: &na::Point2<f64>) -> (usize, usize) {
+    (
+        config.x.to_degrees().rem_euclid(CONFIG_SIZE as f64).floor() as usize,
+        config.y.to_degrees().rem_euclid(CONFIG_SIZE as f64).floor() as usize,
+    )
+}
+
+/// Shortest path over the same grid as `BFSMap`, the path has the same format as `BFSMap::path_to`
+pub fn astar_path(
+    start: &na::Point2<f64>,
+    goal: &na::Point2<f64>,
+    config: &ConfigObstuction,
+    heuristic: Heuristic,
+    clearance: &ClearanceMap,
+    clearance_weight: f64,
+) -> Option<Vec<na::Point2<f64>>> {
+    astar_search(start, goal, config, heuristic, clearance, clearance_weight).0
+}
+
+/// `astar_path` together with the number of cells closed by the search
+fn astar_search(
+    start: &na::Point2<f64>,
+    goal: &na::Point2<f64>,
+    config: &ConfigObstuction,
+    heuristic: Heuristic,
+    clearance: &ClearanceMap,
+    clearance_weight: f64,
+) -> (Option<Vec<na::Point2<f64>>>, usize) {
+    let start_cell = config_cell(start);
+    let goal_cell = config_cell(goal);
+
+    let mut distances = vec![[f64::INFINITY; CONFIG_SIZE]; CONFIG_SIZE];
+    let mut previous = vec![[None; CONFIG_SIZE]; CONFIG_SIZE];
+    let mut closed = vec![[false; CONFIG_SIZE]; CONFIG_SIZE];
+    let mut closed_count = 0;
+
+    distances[start_cell.0][start_cell.1] = 0.0;
+    let mut queue = BinaryHeap::from([QueueNode {
+        cost: heuristic.estimate(start_cell, goal_cell),
+        cell: start_cell,
+    }]);
+
+    while let Some(QueueNode { cell, .. }) = queue.pop() {
+        if closed[cell.0][cell.1] {
+            continue;
+        }
+
+        closed[cell.0][cell.1] = true;
+        closed_count += 1;
+
+        if cell == goal_cell {
+            break;
+        }
+
+        for (d_1, d_2, step) in MOVES {
+            let next = wrapped_neighbor(cell, d_1, d_2);
+            let distance =
+                distances[cell.0][cell.1] + clearance.step_cost(next, step, clearance_weight);
+            if config.obstructed[next.0][next.1] || distance >= distances[next.0][next.1] {
+                continue;
+            }
+
+            distances[next.0][next.1] = distance;
+            previous[next.0][next.1] = Some(cell);
+            queue.push(QueueNode {
+                cost: distance + heuristic.estimate(next, goal_cell),
+                cell: next,
+            });
+        }
+    }
+
+    if !closed[goal_cell.0][goal_cell.1] {
+        return (None, closed_count);
+    }
+
+    let mut path = vec![*goal];
+    let mut current = goal_cell;
+    while let Some(prev) = previous[current.0][current.1] {
+        path.push(na::point![
+            (prev.0 as f64 + 0.5).to_radians(),
+            (prev.1 as f64 + 0.5).to_radians()
+        ]);
+        current = prev;
+    }
+
+    path.reverse();
+    (Some(path), closed_count)
+}
+
+/// Shortest difference between two configurations on the torus of angles
+fn config_difference(from: &na::Point2<f64>, to: &na::Point2<f64>) -> na::Vector2<f64> {
+    (to - from).map(|d| (d + PI).rem_euclid(TAU) - PI)
+}
+
+/// Greedily removes waypoints as long as the straight segment between the kept ones is free
+pub fn smooth_path(path: &[na::Point2<f64>], config: &ConfigObstuction) -> Vec<na::Point2<f64>> {
+    let Some(first) = path.first() else {
+        return Vec::new();
+    };
+
+    let mut smoothed = vec![*first];
+    let mut anchor = 0;
+
+    while anchor + 1 < path.len() {
+        anchor = (anchor + 2..path.len())
+            .rev()
+            .find(|&i| config.is_segment_free(&path[anchor], &path[i]))
+            .unwrap_or(anchor + 1);
+        smoothed.push(path[anchor]);
+    }
+
+    smoothed
+}
+
+/// Inserts configurations along the segments of the path so that consecutive ones are at most
+/// `step` apart, the segments follow the shortest way on the torus
+pub fn resample_path(path: &[na::Point2<f64>], step: f64) -> Vec<na::Point2<f64>> {
+    let mut resampled: Vec<_> = path.first().into_iter().copied().collect();
+
+    for segment in path.windows(2) {
+        let difference = config_difference(&segment[0], &segment[1]);
+        let steps = (difference.norm() / step).ceil().max(1.0) as usize;
+        resampled.extend((1..=steps).map(|i| segment[0] + difference * (i as f64 / steps as f64)));
+    }
+
+    resampled
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Obstacle {
+    Rect(Rect),
+    Circle(Circle),
+    Polygon(Polygon),
+}
+
+impl Obstacle {
+    pub fn collides_with_segment(&self, segment: &Segment) -> bool {
+        match self {
+            Obstacle::Rect(rect) => segment.collides_with_rect(rect),
+            Obstacle::Circle(circle) => segment.collides_with_circle(circle),
+            Obstacle::Polygon(polygon) => segment.collides_with_polygon(polygon),
+        }
+    }
+
+    pub fn contains_point(&self, point: &na::Point2<f64>) -> bool {
+        match self {
+            Obstacle::Rect(rect) => rect.contains_point(point),
+            Obstacle::Circle(circle) => circle.contains_point(point),
+            Obstacle::Polygon(polygon) => polygon.contains_point(point),
+        }
+    }
+}
+
 pub struct ConfigObstuction {
     obstructed: [[bool; CONFIG_SIZE]; CONFIG_SIZE],
+    /// Elbow and effector positions of every cell, row by row
+    joints: Vec<[na::Point2<f64>; 2]>,
     system: flat_chain::System,
     origin: na::Point2<f64>,
 }
 
 impl ConfigObstuction {
+    /// Sampling density of segment checks, half of a grid cell
+    const SEGMENT_SAMPLE_STEP: f64 = 0.5 * TAU / CONFIG_SIZE as f64;
+
     pub fn new(system: flat_chain::System, origin: na::Point2<f64>) -> Self {
         let obstructed = [[false; CONFIG_SIZE]; CONFIG_SIZE];
         Self {
+            joints: Self::joints(&system, &origin),
             system,
             origin,
             obstructed,
         }
     }
 
+    /// Removes all obstacles, joint positions are only recomputed if the system has changed
+    pub fn reset(&mut self, system: flat_chain::System) {
+        if system != self.system {
+            self.joints = Self::joints(&system, &self.origin);
+            self.system = system;
+        }
+
+        self.obstructed = [[false; CONFIG_SIZE]; CONFIG_SIZE];
+    }
+
+    fn joints(system: &flat_chain::System, origin: &na::Point2<f64>) -> Vec<[na::Point2<f64>; 2]> {
+        (0..CONFIG_SIZE * CONFIG_SIZE)
+            .into_par_iter()
+            .map(|cell| {
+                let state = system.forward_kinematics(&na::point![
+                    ((cell / CONFIG_SIZE) as f64).to_radians(),
+                    ((cell % CONFIG_SIZE) as f64).to_radians()
+                ]);
+
+                [state.p_1 + origin.coords, state.p_2 + origin.coords]
+            })
+            .collect()
+    }
+
     pub fn correct_solution(
         &self,
         solution: &flat_chain::ReverseSolutions,
@@ -156,25 +463,45 @@ impl ConfigObstuction {
         }
     }
 
+    pub fn is_obstructed(&self, config: &na::Point2<f64>) -> bool {
+        let cell = config_cell(config);
+        self.obstructed[cell.0][cell.1]
+    }
+
+    /// Checks configurations sampled along the shortest segment on the torus
+    pub fn is_segment_free(&self, from: &na::Point2<f64>, to: &na::Point2<f64>) -> bool {
+        resample_path(&[*from, *to], Self::SEGMENT_SAMPLE_STEP)
+            .iter()
+            .all(|config| !self.is_obstructed(config))
+    }
+
     pub fn add_rect(&mut self, rect: &Rect) {
-        for (alpha_1, subarray) in self.obstructed.iter_mut().enumerate() {
-            for (alpha_2, obstruction) in subarray.iter_mut().enumerate() {
-                let state = self.system.forward_kinematics(&na::point![
-                    (alpha_1 as f64).to_radians(),
-                    (alpha_2 as f64).to_radians()
-                ]);
+        self.add_obstacle(&Obstacle::Rect(*rect));
+    }
 
-                let segment_1_collision = Segment::new(self.origin, state.p_1 + self.origin.coords)
-                    .collides_with_rect(rect);
-                let segment_2_collision = Segment::new(
-                    state.p_1 + self.origin.coords,
-                    state.p_2 + self.origin.coords,
-                )
-                .collides_with_rect(rect);
+    pub fn add_circle(&mut self, center: na::Point2<f64>, radius: f64) {
+        self.add_obstacle(&Obstacle::Circle(Circle::new(center, radius)));
+    }
 
-                *obstruction |= segment_1_collision || segment_2_collision;
-            }
-        }
+    pub fn add_polygon(&mut self, vertices: &[na::Point2<f64>]) {
+        self.add_obstacle(&Obstacle::Polygon(Polygon::new(vertices.to_vec())));
+    }
+
+    pub fn add_obstacle(&mut self, obstacle: &Obstacle) {
+        // Every cell is independent
+        self.obstructed
+            .par_iter_mut()
+            .zip(self.joints.par_chunks(CONFIG_SIZE))
+            .for_each(|(subarray, joints)| {
+                for (obstruction, &[elbow, effector]) in subarray.iter_mut().zip(joints) {
+                    let segment_1_collision =
+                        obstacle.collides_with_segment(&Segment::new(self.origin, elbow));
+                    let segment_2_collision =
+                        obstacle.collides_with_segment(&Segment::new(elbow, effector));
+
+                    *obstruction |= segment_1_collision || segment_2_collision;
+                }
+            });
     }
 
     pub fn texture(&self, access_map: &BFSMap, path: Option<&[na::Point2<f64>]>) -> Texture {
@@ -189,7 +516,7 @@ impl ConfigObstuction {
                         0,
                         if obstructed { 255 } else { 0 },
                         255 - access_map.0[alpha_1 as usize][alpha_2 as usize]
-                            .map_or(255, |t| t.distance.min(255) as u8),
+                            .map_or(255, |t| t.distance.min(255.0) as u8),
                         255,
                     ]),
                 );
@@ -207,3 +534,398 @@ impl ConfigObstuction {
         texture
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_space() -> ConfigObstuction {
+        ConfigObstuction::new(
+            flat_chain::System::new(150.0, 100.0),
+            na::point![400.0, 400.0],
+        )
+    }
+
+    fn obstruct(
+        config: &mut ConfigObstuction,
+        alpha_1: std::ops::RangeInclusive<usize>,
+        alpha_2: std::ops::RangeInclusive<usize>,
+    ) {
+        for a_1 in alpha_1 {
+            for a_2 in alpha_2.clone() {
+                config.obstructed[a_1][a_2] = true;
+            }
+        }
+    }
+
+    fn cell_center(alpha_1: usize, alpha_2: usize) -> na::Point2<f64> {
+        na::point![
+            (alpha_1 as f64 + 0.5).to_radians(),
+            (alpha_2 as f64 + 0.5).to_radians()
+        ]
+    }
+
+    /// Length of a grid path in cells, every step has to be one of the 8-connected moves
+    fn path_cost(path: &[na::Point2<f64>]) -> f64 {
+        path.windows(2)
+            .map(|pair| {
+                let (from, to) = (config_cell(&pair[0]), config_cell(&pair[1]));
+                let (_, _, cost) = MOVES
+                    .into_iter()
+                    .find(|&(d_1, d_2, _)| wrapped_neighbor(from, d_1, d_2) == to)
+                    .expect("Path cells are not neighbours");
+                cost
+            })
+            .sum()
+    }
+
+    fn dijkstra_distance(
+        start: &na::Point2<f64>,
+        goal: &na::Point2<f64>,
+        config: &ConfigObstuction,
+    ) -> Option<f64> {
+        let clearance = ClearanceMap::from_obstructions(config);
+        let map = BFSMap::from_obstructions(&Some(*start), config, &clearance, 0.0);
+        let cell = config_cell(goal);
+        map.0[cell.0][cell.1].map(|trove| trove.distance)
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn astar_in_open_space_is_octile() {
+        let config = open_space();
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let (start, goal) = (cell_center(10, 20), cell_center(50, 35));
+
+        for heuristic in [Heuristic::Zero, Heuristic::Euclidean, Heuristic::Octile] {
+            let path = astar_path(&start, &goal, &config, heuristic, &clearance, 0.0).unwrap();
+
+            assert_eq!(path.first(), Some(&start));
+            assert_eq!(path.last(), Some(&goal));
+            assert_close(path_cost(&path), 25.0 + 15.0 * std::f64::consts::SQRT_2);
+        }
+    }
+
+    #[test]
+    fn astar_path_is_as_short_as_dijkstra() {
+        let mut config = open_space();
+        // A wall around the start with a gap on the far side
+        obstruct(&mut config, 30..=32, 0..=359);
+        (30..=32).for_each(|alpha_1| config.obstructed[alpha_1][300] = false);
+        obstruct(&mut config, 80..=120, 80..=120);
+        let clearance = ClearanceMap::from_obstructions(&config);
+
+        for (start, goal) in [
+            (cell_center(50, 100), cell_center(150, 110)),
+            (cell_center(10, 10), cell_center(50, 100)),
+            (cell_center(100, 60), cell_center(100, 140)),
+        ] {
+            let expected = dijkstra_distance(&start, &goal, &config).unwrap();
+            for heuristic in [Heuristic::Zero, Heuristic::Euclidean, Heuristic::Octile] {
+                let path = astar_path(&start, &goal, &config, heuristic, &clearance, 0.0).unwrap();
+                assert_close(path_cost(&path), expected);
+                assert!(path
+                    .iter()
+                    .all(|config_point| !config.is_obstructed(config_point)));
+            }
+        }
+    }
+
+    #[test]
+    fn octile_astar_closes_fewer_cells_than_dijkstra() {
+        let config = open_space();
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let (start, goal) = (cell_center(10, 20), cell_center(50, 35));
+
+        let closed = |heuristic| {
+            let (path, closed) = astar_search(&start, &goal, &config, heuristic, &clearance, 0.0);
+            assert!(path.is_some());
+            closed
+        };
+        let (octile, zero) = (closed(Heuristic::Octile), closed(Heuristic::Zero));
+
+        // Without obstacles the map closes every cell it reaches
+        let map = BFSMap::from_obstructions(&Some(start), &config, &clearance, 0.0);
+        let map_closed = map
+            .0
+            .iter()
+            .flatten()
+            .filter(|trove| trove.is_some())
+            .count();
+
+        assert!(octile < zero, "{octile} >= {zero}");
+        assert!(octile < map_closed, "{octile} >= {map_closed}");
+    }
+
+    #[test]
+    fn astar_reports_unreachable_goals() {
+        let mut config = open_space();
+        obstruct(&mut config, 100..=102, 0..=359);
+        obstruct(&mut config, 200..=202, 0..=359);
+        let clearance = ClearanceMap::from_obstructions(&config);
+
+        let path = astar_path(
+            &cell_center(50, 50),
+            &cell_center(150, 50),
+            &config,
+            Heuristic::Octile,
+            &clearance,
+            0.0,
+        );
+        assert!(path.is_none());
+    }
+
+    #[test]
+    fn diagonal_paths_are_shorter_than_staircases() {
+        let config = open_space();
+        let (start, goal) = (cell_center(40, 40), cell_center(60, 60));
+
+        let distance = dijkstra_distance(&start, &goal, &config).unwrap();
+        assert_close(distance, 20.0 * std::f64::consts::SQRT_2);
+        assert!(distance < 40.0);
+
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let map = BFSMap::from_obstructions(&Some(start), &config, &clearance, 0.0);
+        let path = map.path_to(&goal).unwrap();
+        assert_eq!(path.len(), 21);
+        assert_close(path_cost(&path), distance);
+    }
+
+    #[test]
+    fn dijkstra_wraps_around_the_torus() {
+        let config = open_space();
+        let start = cell_center(1, 2);
+        let goal = cell_center(358, 357);
+
+        assert_close(
+            dijkstra_distance(&start, &goal, &config).unwrap(),
+            2.0 + 3.0 * std::f64::consts::SQRT_2,
+        );
+
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let map = BFSMap::from_obstructions(&Some(start), &config, &clearance, 0.0);
+        let path = map.path_to(&goal).unwrap();
+        let cells: Vec<_> = path.iter().map(config_cell).collect();
+        assert!(cells.iter().any(|cell| cell.0 == 0) && cells.iter().any(|cell| cell.0 == 359));
+        assert!(cells
+            .iter()
+            .all(|&(a_1, a_2)| !(2..=357).contains(&a_1) && !(3..=356).contains(&a_2)));
+    }
+
+    #[test]
+    fn dijkstra_goes_around_obstacles() {
+        let mut config = open_space();
+        // Only the wrapped-around side of the wall at alpha_1 = 180 is open
+        obstruct(&mut config, 178..=182, 10..=359);
+        let (start, goal) = (cell_center(170, 100), cell_center(190, 100));
+
+        let distance = dijkstra_distance(&start, &goal, &config).unwrap();
+        assert!(distance > 100.0, "{distance}");
+
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let map = BFSMap::from_obstructions(&Some(start), &config, &clearance, 0.0);
+        let path = map.path_to(&goal).unwrap();
+        assert!(path.iter().all(|point| !config.is_obstructed(point)));
+        assert!(path.iter().any(|point| config_cell(point).1 < 10));
+        assert!(map.path_to(&cell_center(180, 200)).is_none());
+    }
+
+    #[test]
+    fn no_start_reaches_nothing() {
+        let config = open_space();
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let map = BFSMap::from_obstructions(&None, &config, &clearance, 0.0);
+
+        assert!(map.path_to(&cell_center(0, 0)).is_none());
+        assert_eq!(
+            BFSMap::from_obstructions(&Some(cell_center(5, 5)), &config, &clearance, 0.0)
+                .path_to(&cell_center(5, 5)),
+            Some(vec![cell_center(5, 5)])
+        );
+    }
+
+    #[test]
+    fn open_space_path_collapses_to_endpoints() {
+        let config = open_space();
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let (start, goal) = (cell_center(10, 20), cell_center(80, 35));
+        let path = astar_path(&start, &goal, &config, Heuristic::Octile, &clearance, 0.0).unwrap();
+        assert!(path.len() > 2);
+
+        assert_eq!(smooth_path(&path, &config), [start, goal]);
+    }
+
+    #[test]
+    fn smoothed_paths_stay_free() {
+        let mut config = open_space();
+        obstruct(&mut config, 40..=60, 0..=200);
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let (start, goal) = (cell_center(20, 100), cell_center(80, 100));
+        let path = astar_path(&start, &goal, &config, Heuristic::Octile, &clearance, 0.0).unwrap();
+
+        let smoothed = smooth_path(&path, &config);
+
+        assert!(smoothed.len() > 2 && smoothed.len() < path.len());
+        assert_eq!(smoothed.first(), Some(&start));
+        assert_eq!(smoothed.last(), Some(&goal));
+        assert!(smoothed.iter().all(|point| path.contains(point)));
+        assert!(smoothed
+            .windows(2)
+            .all(|pair| config.is_segment_free(&pair[0], &pair[1])));
+    }
+
+    #[test]
+    fn short_paths_are_not_smoothed() {
+        let config = open_space();
+        let point = cell_center(3, 4);
+        assert!(smooth_path(&[], &config).is_empty());
+        assert_eq!(smooth_path(&[point], &config), [point]);
+    }
+
+    #[test]
+    fn resampled_steps_are_bounded() {
+        let path = [
+            na::point![0.5, 0.5],
+            na::point![1.5, 0.5],
+            na::point![1.5, 2.0],
+            na::point![1.5, 2.0],
+        ];
+        let step = 0.1;
+
+        let resampled = resample_path(&path, step);
+
+        assert_eq!(resampled.first(), path.first());
+        assert_close(na::distance(resampled.last().unwrap(), &path[3]), 0.0);
+        assert_eq!(resampled.len(), 1 + 10 + 15 + 1);
+        assert!(resampled
+            .windows(2)
+            .all(|pair| na::distance(&pair[0], &pair[1]) <= step + 1e-12));
+    }
+
+    #[test]
+    fn resampling_takes_the_short_way_around() {
+        let path = [
+            na::point![350.0_f64.to_radians(), 1.0],
+            na::point![10.0_f64.to_radians(), 1.0],
+        ];
+
+        let resampled = resample_path(&path, 1.0_f64.to_radians());
+
+        // Configurations are not wrapped, so the path goes on past 360 degrees
+        assert_eq!(resampled.len(), 21);
+        for (i, point) in resampled.iter().enumerate() {
+            assert_close(point.x.to_degrees(), 350.0 + i as f64);
+            assert_eq!(point.y, 1.0);
+        }
+        assert!(resample_path(&[], 0.1).is_empty());
+    }
+
+    #[test]
+    fn clearance_grows_away_from_obstacles() {
+        let mut config = open_space();
+        obstruct(&mut config, 0..=0, 0..=0);
+        obstruct(&mut config, 100..=110, 200..=210);
+        let clearance = ClearanceMap::from_obstructions(&config);
+
+        assert_eq!(clearance.clearance(&cell_center(105, 205)), 0.0);
+        assert_eq!(clearance.clearance(&cell_center(111, 205)), 1.0);
+        assert_eq!(clearance.clearance(&cell_center(105, 215)), 5.0);
+        assert_close(
+            clearance.clearance(&cell_center(112, 212)),
+            2.0 * std::f64::consts::SQRT_2,
+        );
+        // Distances wrap around the torus
+        assert_close(
+            clearance.clearance(&cell_center(358, 359)),
+            1.0 + std::f64::consts::SQRT_2,
+        );
+        assert!(ClearanceMap::from_obstructions(&open_space())
+            .clearance(&cell_center(10, 10))
+            .is_infinite());
+    }
+
+    fn min_clearance(path: &[na::Point2<f64>], clearance: &ClearanceMap) -> f64 {
+        path.iter()
+            .map(|point| clearance.clearance(point))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn clearance_weight_keeps_paths_away_from_obstacles() {
+        let mut config = open_space();
+        obstruct(&mut config, 55..=65, 170..=190);
+        let clearance = ClearanceMap::from_obstructions(&config);
+        let (start, goal) = (cell_center(10, 180), cell_center(110, 180));
+
+        let shortest =
+            astar_path(&start, &goal, &config, Heuristic::Octile, &clearance, 0.0).unwrap();
+        let weighted =
+            astar_path(&start, &goal, &config, Heuristic::Octile, &clearance, 20.0).unwrap();
+
+        assert!(min_clearance(&shortest, &clearance) <= std::f64::consts::SQRT_2);
+        assert!(min_clearance(&weighted, &clearance) > 2.0 * min_clearance(&shortest, &clearance));
+        assert!(path_cost(&weighted) > path_cost(&shortest));
+
+        // The full map weighs the moves the same way
+        let map = BFSMap::from_obstructions(&Some(start), &config, &clearance, 20.0);
+        assert_eq!(
+            min_clearance(&map.path_to(&goal).unwrap(), &clearance),
+            min_clearance(&weighted, &clearance)
+        );
+    }
+
+    /// Obstruction computed cell by cell on one thread, without the cached joint positions
+    fn serial_obstruction(config: &ConfigObstuction, obstacles: &[Obstacle]) -> Vec<Vec<bool>> {
+        (0..CONFIG_SIZE)
+            .map(|alpha_1| {
+                (0..CONFIG_SIZE)
+                    .map(|alpha_2| {
+                        let state = config.system.forward_kinematics(&na::point![
+                            (alpha_1 as f64).to_radians(),
+                            (alpha_2 as f64).to_radians()
+                        ]);
+                        let elbow = state.p_1 + config.origin.coords;
+                        let effector = state.p_2 + config.origin.coords;
+
+                        obstacles.iter().any(|obstacle| {
+                            obstacle.collides_with_segment(&Segment::new(config.origin, elbow))
+                                || obstacle.collides_with_segment(&Segment::new(elbow, effector))
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_obstruction_matches_serial() {
+        let mut config = open_space();
+        let origin = config.origin;
+        let obstacles = [
+            Obstacle::Rect(Rect {
+                p_1: origin + na::vector![-200.0, 120.0],
+                p_2: origin + na::vector![-140.0, 200.0],
+            }),
+            Obstacle::Circle(Circle::new(origin + na::vector![150.0, -60.0], 40.0)),
+            Obstacle::Polygon(Polygon::new(vec![
+                origin + na::vector![0.0, -220.0],
+                origin + na::vector![80.0, -160.0],
+                origin + na::vector![-30.0, -130.0],
+            ])),
+        ];
+
+        for obstacle in &obstacles {
+            config.add_obstacle(obstacle);
+        }
+
+        let expected = serial_obstruction(&config, &obstacles);
+        let obstructed = config.obstructed.iter().flatten().filter(|&&o| o).count();
+        assert!(obstructed > 0 && obstructed < CONFIG_SIZE * CONFIG_SIZE);
+        for (row, expected_row) in config.obstructed.iter().zip(&expected) {
+            assert_eq!(row.as_slice(), expected_row.as_slice());
+        }
+    }
+}
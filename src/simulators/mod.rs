@@ -3,3 +3,4 @@ pub mod kinematic_chain;
 pub mod puma;
 pub mod spinning_top;
 pub mod spring;
+pub mod spring_chain;
@@ -1,5 +1,6 @@
 use crate::numerics::{angle::Angle, rotations::*};
 use nalgebra as na;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct ConfigState {
@@ -49,7 +50,16 @@ impl ConfigState {
         let f04 = f03 * f33 * f34;
         let f05 = f04 * f44 * f45;
 
+        let origin = na::Point3::origin();
+
         CylindersTransforms {
+            joint_positions: [
+                origin,
+                (f01 * f11).transform_point(&origin),
+                (f02 * f22).transform_point(&origin),
+                (f03 * f33).transform_point(&origin),
+                f05.transform_point(&origin),
+            ],
             bone_transforms: [
                 f01 * f11_half * thin * scale1,
                 f02 * f22_half * rot2 * thin * scale2,
@@ -68,7 +78,42 @@ impl ConfigState {
     }
 
     pub fn next_config(&self, next_position: &SceneState, params: &Params) -> Self {
-        next_position.inverse_kinematics(&self, params)
+        next_position.inverse_kinematics(self, params).config
+    }
+
+    /// Angles in degrees in `[-180, 180)` followed by `q2`
+    pub fn joint_values(&self) -> [f64; JOINT_COUNT] {
+        let signed = |angle: Angle| (angle.deg() + 180.0).rem_euclid(360.0) - 180.0;
+
+        [
+            signed(self.a1),
+            signed(self.a2),
+            signed(self.a3),
+            signed(self.a4),
+            signed(self.a5),
+            self.q2,
+        ]
+    }
+
+    /// Clamps every joint to its limit, returns which joints were clamped
+    pub fn clamp_to_limits(&mut self, params: &Params) -> [bool; JOINT_COUNT] {
+        let values = self.joint_values();
+        let mut limited = [false; JOINT_COUNT];
+        let mut clamped = values;
+
+        for (index, limit) in params.limits.iter().enumerate() {
+            clamped[index] = values[index].clamp(limit.min, limit.max);
+            limited[index] = clamped[index] != values[index];
+        }
+
+        self.a1 = Angle::from_deg(clamped[0]);
+        self.a2 = Angle::from_deg(clamped[1]);
+        self.a3 = Angle::from_deg(clamped[2]);
+        self.a4 = Angle::from_deg(clamped[3]);
+        self.a5 = Angle::from_deg(clamped[4]);
+        self.q2 = clamped[5];
+
+        limited
     }
 
     pub fn lerp(&self, other: &Self, t: f64) -> Self {
@@ -87,6 +132,58 @@ impl ConfigState {
 pub struct CylindersTransforms {
     pub bone_transforms: [na::Matrix4<f64>; 5],
     pub joint_transforms: [na::Matrix4<f64>; 4],
+    /// Ends of the bones, from the base to the effector
+    pub joint_positions: [na::Point3<f64>; 5],
+}
+
+impl CylindersTransforms {
+    /// Which of the bones reach below the plane `z = height`
+    pub fn bones_below(&self, height: f64) -> [bool; 4] {
+        std::array::from_fn(|index| {
+            self.joint_positions[index]
+                .z
+                .min(self.joint_positions[index + 1].z)
+                < height
+        })
+    }
+}
+
+/// Degenerate pose in which an angle is not determined by the effector frame,
+/// inverse kinematics keeps the angle of the guide configuration then
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Singularity {
+    /// Wrist center on the base axis, `a1` is arbitrary
+    Shoulder,
+    /// Effector axis perpendicular to the arm plane, `a2 + a3` is arbitrary
+    Wrist,
+    /// Second joint at the wrist center, `a2` is arbitrary
+    Elbow,
+}
+
+impl Singularity {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Singularity::Shoulder => "Shoulder singularity: wrist center on the base axis",
+            Singularity::Wrist => "Wrist singularity: effector axis perpendicular to the arm",
+            Singularity::Elbow => "Elbow singularity: second joint at the wrist center",
+        }
+    }
+}
+
+/// Explicit choice between the two solutions of the ambiguous angles
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Branch {
+    /// `a1` offset by π
+    pub flip_a1: bool,
+    /// `a2 + a3` offset by π
+    pub flip_a23: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct InverseSolution {
+    pub config: ConfigState,
+    /// Singularities the pose is within `SceneState::SINGULARITY_TOLERANCE` of
+    pub singularities: Vec<Singularity>,
 }
 
 pub struct SceneState {
@@ -106,7 +203,37 @@ impl SceneState {
         )
     }
 
-    pub fn inverse_kinematics(&self, guide: &ConfigState, params: &Params) -> ConfigState {
+    pub const SINGULARITY_TOLERANCE: f64 = 1e-6;
+
+    /// Ambiguous angles are chosen to be the closest to `guide`
+    pub fn inverse_kinematics(&self, guide: &ConfigState, params: &Params) -> InverseSolution {
+        self.solve(guide, None, params)
+    }
+
+    /// Ambiguous angles are chosen by `branch`, `guide` is only used in singularities
+    pub fn inverse_kinematics_branch(
+        &self,
+        branch: &Branch,
+        guide: &ConfigState,
+        params: &Params,
+    ) -> InverseSolution {
+        self.solve(guide, Some(branch), params)
+    }
+
+    fn solve(
+        &self,
+        guide: &ConfigState,
+        branch: Option<&Branch>,
+        params: &Params,
+    ) -> InverseSolution {
+        let choose = |guide: Angle, flip: Option<bool>, angle: Angle| match flip {
+            Some(false) => angle,
+            Some(true) => angle + Angle::pi_rad(),
+            None => guide.closest(angle, angle + Angle::pi_rad()),
+        };
+
+        let mut singularities = Vec::new();
+
         // Effector is at p4, its axes are i5, j5 and k5
         let p4 = self.position;
         let p4 = na::vector![p4.x, p4.y, p4.z, 1.0];
@@ -117,6 +244,10 @@ impl SceneState {
 
         let p3 = p4 - params.l4 * d4x;
 
+        if p3.xy().norm() < Self::SINGULARITY_TOLERANCE {
+            singularities.push(Singularity::Shoulder);
+        }
+
         let a1 = if p3.x != 0.0 || p3.y != 0.0 {
             let a1_abs = Angle::from_rad(f64::atan2(p3.y, p3.x).abs());
             let c1 = a1_abs.cos();
@@ -135,7 +266,7 @@ impl SceneState {
                 }
             };
 
-            guide.a1.closest(a1_mod_pi, a1_mod_pi + Angle::pi_rad())
+            choose(guide.a1, branch.map(|b| b.flip_a1), a1_mod_pi)
         } else {
             guide.a1
         };
@@ -145,12 +276,16 @@ impl SceneState {
 
         let icjs = i * c1 + j * s1;
 
+        if k.hypot(icjs) < Self::SINGULARITY_TOLERANCE {
+            singularities.push(Singularity::Wrist);
+        }
+
         // a2 + a3
         let a23 = if k == 0.0 && icjs == 0.0 {
             guide.a2 + guide.a3
         } else {
             let a23_mod_pi = Angle::from_rad(f64::atan2(k, -icjs));
-            (guide.a2 + guide.a3).closest(a23_mod_pi, a23_mod_pi + Angle::pi_rad())
+            choose(guide.a2 + guide.a3, branch.map(|b| b.flip_a23), a23_mod_pi)
         };
 
         let s23 = a23.sin();
@@ -163,6 +298,10 @@ impl SceneState {
             p3.x / c1
         } + params.l3 * s23;
 
+        if x_a2.hypot(y_a2) < Self::SINGULARITY_TOLERANCE {
+            singularities.push(Singularity::Elbow);
+        }
+
         let a2 = if x_a2 == 0.0 && y_a2 == 0.0 {
             guide.a2
         } else {
@@ -218,30 +357,65 @@ impl SceneState {
             -a5_abs
         };
 
-        ConfigState {
-            a1,
-            a2,
-            a3,
-            a4,
-            a5,
-            q2,
+        InverseSolution {
+            config: ConfigState {
+                a1,
+                a2,
+                a3,
+                a4,
+                a5,
+                q2,
+            },
+            singularities,
         }
     }
 }
 
-#[derive(Clone, Copy)]
+pub const JOINT_COUNT: usize = 6;
+pub const JOINT_NAMES: [&str; JOINT_COUNT] = ["a1", "a2", "a3", "a4", "a5", "q2"];
+
+/// Range of a joint, in degrees for angles
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JointLimit {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Params {
     pub l1: f64,
     pub l3: f64,
     pub l4: f64,
+    /// Limits of the joints in the order of `JOINT_NAMES`
+    pub limits: [JointLimit; JOINT_COUNT],
+}
+
+impl Params {
+    pub const MAX_Q2: f64 = 20.0;
 }
 
 impl Default for Params {
     fn default() -> Self {
+        let full_turn = JointLimit {
+            min: -180.0,
+            max: 180.0,
+        };
+
         Self {
             l1: 3.0,
             l3: 3.0,
             l4: 3.0,
+            limits: [
+                full_turn,
+                full_turn,
+                full_turn,
+                full_turn,
+                full_turn,
+                JointLimit {
+                    min: 0.0,
+                    max: Self::MAX_Q2,
+                },
+            ],
         }
     }
 }
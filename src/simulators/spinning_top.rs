@@ -3,23 +3,136 @@ use crate::{
     physics::inertia::Inertia,
 };
 use nalgebra as na;
+use std::f64::consts::PI;
+
+/// Shape of the top in its body frame, the origin lies on the boundary of every body.
+/// Boxes lie in the positive octant, rotationally symmetric bodies are placed along `TopBody::AXIS`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TopBody {
+    Cube { side_length: f64 },
+    Cuboid { sizes: na::Vector3<f64> },
+    Cylinder { radius: f64, height: f64 },
+    Sphere { radius: f64 },
+}
+
+impl TopBody {
+    /// Symmetry axis of cylinders and spheres, the same as the diagonal of the cube
+    pub const AXIS: na::Vector3<f64> =
+        na::vector![0.5773502691896258, 0.5773502691896258, 0.5773502691896258];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TopBody::Cube { .. } => "Cube",
+            TopBody::Cuboid { .. } => "Cuboid",
+            TopBody::Cylinder { .. } => "Cylinder",
+            TopBody::Sphere { .. } => "Sphere",
+        }
+    }
+
+    pub fn volume(&self) -> f64 {
+        match *self {
+            TopBody::Cube { side_length } => side_length.powi(3),
+            TopBody::Cuboid { sizes } => sizes.product(),
+            TopBody::Cylinder { radius, height } => PI * radius.powi(2) * height,
+            TopBody::Sphere { radius } => 4.0 / 3.0 * PI * radius.powi(3),
+        }
+    }
+
+    /// Center of mass in the body frame
+    pub fn center(&self) -> na::Vector3<f64> {
+        match *self {
+            TopBody::Cube { side_length } => 0.5 * side_length * na::vector![1.0, 1.0, 1.0],
+            TopBody::Cuboid { sizes } => 0.5 * sizes,
+            TopBody::Cylinder { height, .. } => 0.5 * height * Self::AXIS,
+            TopBody::Sphere { radius } => radius * Self::AXIS,
+        }
+    }
+
+    /// Distance from the origin to the furthest point of the body
+    pub fn reach(&self) -> f64 {
+        match *self {
+            TopBody::Cylinder { radius, height } => radius.hypot(height),
+            _ => 2.0 * self.center().norm(),
+        }
+    }
+
+    pub fn contains(&self, point: &na::Vector3<f64>) -> bool {
+        let epsilon = 1e-9 * self.reach();
+        let within = |value: f64, max: f64| -epsilon <= value && value <= max + epsilon;
+
+        match *self {
+            TopBody::Cube { side_length } => point.iter().all(|&c| within(c, side_length)),
+            TopBody::Cuboid { sizes } => point.iter().zip(&sizes).all(|(&c, &s)| within(c, s)),
+            TopBody::Cylinder { radius, height } => {
+                let axial = point.dot(&Self::AXIS);
+                within(axial, height) && within((point - axial * Self::AXIS).norm(), radius)
+            }
+            TopBody::Sphere { radius } => within((point - self.center()).norm(), radius),
+        }
+    }
+
+    /// Inertia tensor about the center of mass
+    fn central_inertia(&self, mass: f64) -> na::Matrix3<f64> {
+        match *self {
+            TopBody::Cube { side_length } => {
+                mass * side_length.powi(2) / 6.0 * na::Matrix3::identity()
+            }
+            TopBody::Cuboid { sizes } => {
+                let squares = sizes.component_mul(&sizes);
+                mass / 12.0
+                    * na::Matrix3::from_diagonal(&na::vector![
+                        squares.y + squares.z,
+                        squares.x + squares.z,
+                        squares.x + squares.y
+                    ])
+            }
+            TopBody::Cylinder { radius, height } => {
+                let axial = Self::AXIS * Self::AXIS.transpose();
+                mass * (3.0 * radius.powi(2) + height.powi(2)) / 12.0
+                    * (na::Matrix3::identity() - axial)
+                    + mass * radius.powi(2) / 2.0 * axial
+            }
+            TopBody::Sphere { radius } => {
+                2.0 / 5.0 * mass * radius.powi(2) * na::Matrix3::identity()
+            }
+        }
+    }
+
+    /// Inertia tensor about `point`
+    pub fn inertia(&self, density: f64, point: &na::Vector3<f64>) -> na::Matrix3<f64> {
+        let mass = density * self.volume();
+        let center = self.center() - point;
+
+        // Parallel axis theorem
+        self.central_inertia(mass)
+            + mass * (center.norm_squared() * na::Matrix3::identity() - center * center.transpose())
+    }
+}
 
 pub struct SpinningTopODE {
     inertia: Inertia,
-    side_length: f64,
+    body: TopBody,
+    /// Point of the body frame resting on the plane
+    contact_point: na::Vector3<f64>,
     density: f64,
     pub gravity: na::Vector3<f64>,
     pub enable_gravity: bool,
+    /// Coefficient of the viscous friction torque `-c·ω` at the contact point
+    pub friction: f64,
+    pub enable_friction: bool,
 }
 
 impl SpinningTopODE {
-    pub fn new(density: f64, side_length: f64) -> Self {
+    pub fn new(density: f64, body: TopBody) -> Self {
         let mut me = Self {
             inertia: Inertia::unit(),
             gravity: na::Vector3::new(0.0, -10.0, 0.0),
             enable_gravity: true,
+            friction: 0.1,
+            enable_friction: false,
             density,
-            side_length,
+            body,
+            contact_point: na::Vector3::zeros(),
         };
 
         me.calc_inertia();
@@ -28,40 +141,95 @@ impl SpinningTopODE {
     }
 
     fn calc_inertia(&mut self) {
-        self.inertia = Inertia::new(
-            self.density
-                * self.side_length.powi(5)
-                * na::matrix![
-                    2.0/3.0, -0.25, -0.25;
-                    -0.25, 2.0 /3.0, -0.25;
-                    -0.25, -0.25, 2.0/3.0;
-                ],
-        );
+        self.inertia = Inertia::new(self.body.inertia(self.density, &self.contact_point));
+    }
+
+    /// Center of mass relative to the contact point
+    pub fn lever(&self) -> na::Vector3<f64> {
+        self.body.center() - self.contact_point
     }
 
     pub fn torque(&self, rotation: &na::UnitQuaternion<f64>) -> na::Vector3<f64> {
-        let natural_center = 0.5 * self.side_length() * na::vector![1.0, 1.0, 1.0];
+        self.lever()
+            .cross(&rotation.inverse().transform_vector(&self.weight()))
+    }
+
+    pub fn friction_torque(&self, angular_velocity: &na::Vector3<f64>) -> na::Vector3<f64> {
+        if self.enable_friction {
+            -self.friction * angular_velocity
+        } else {
+            na::Vector3::zeros()
+        }
+    }
+
+    pub fn rotation(state: &State<7>) -> na::UnitQuaternion<f64> {
+        na::UnitQuaternion::new_normalize(na::Quaternion::new(
+            state.y[3], state.y[4], state.y[5], state.y[6],
+        ))
+    }
+
+    /// Angular momentum in the world frame, `R·I·ω`
+    pub fn angular_momentum(&self, state: &State<7>) -> na::Vector3<f64> {
+        Self::rotation(state).transform_vector(&(self.inertia.matrix() * state.y.xyz()))
+    }
+
+    /// Precession (azimuth around the vertical) and nutation (tilt from the vertical)
+    /// of the axis going from the contact point through the center of mass, in radians
+    pub fn axis_angles(&self, state: &State<7>) -> (f64, f64) {
+        let axis = self.lever().try_normalize(0.0).unwrap_or(TopBody::AXIS);
+        let axis = Self::rotation(state).transform_vector(&axis);
+
+        (axis.z.atan2(axis.x), axis.y.clamp(-1.0, 1.0).acos())
+    }
 
-        natural_center.cross(&rotation.inverse().transform_vector(&self.weight()))
+    /// Rotational kinetic energy, `½·ωᵀ·I·ω`
+    pub fn energy(&self, state: &State<7>) -> f64 {
+        let angular_velocity = state.y.xyz();
+        0.5 * angular_velocity.dot(&(self.inertia.matrix() * angular_velocity))
     }
 
     pub fn weight(&self) -> na::Vector3<f64> {
         self.gravity() * self.mass()
     }
 
+    pub fn inertia(&self) -> &Inertia {
+        &self.inertia
+    }
+
     pub fn mass(&self) -> f64 {
-        self.density * self.side_length.powi(3)
+        self.density * self.body.volume()
     }
 
-    pub fn side_length(&self) -> f64 {
-        self.side_length
+    pub fn body(&self) -> &TopBody {
+        &self.body
     }
 
-    pub fn set_side_length(&mut self, side_length: f64) {
-        self.side_length = side_length;
+    /// Resets the contact point to the origin if it does not lie within the new body
+    pub fn set_body(&mut self, body: TopBody) {
+        self.body = body;
+
+        if !self.body.contains(&self.contact_point) {
+            self.contact_point = na::Vector3::zeros();
+        }
+
         self.calc_inertia()
     }
 
+    pub fn contact_point(&self) -> &na::Vector3<f64> {
+        &self.contact_point
+    }
+
+    /// Returns `false` and keeps the old contact point if `point` does not lie within the body
+    pub fn set_contact_point(&mut self, point: na::Vector3<f64>) -> bool {
+        if !self.body.contains(&point) {
+            return false;
+        }
+
+        self.contact_point = point;
+        self.calc_inertia();
+        true
+    }
+
     pub fn density(&self) -> f64 {
         self.density
     }
@@ -83,12 +251,11 @@ impl SpinningTopODE {
 impl PlainODE<7> for SpinningTopODE {
     fn derivative(&self, state: &State<7>) -> na::SVector<f64, 7> {
         let angular_velocity = state.y.xyz();
-        let rotation = na::UnitQuaternion::new_normalize(na::Quaternion::new(
-            state.y[3], state.y[4], state.y[5], state.y[6],
-        ));
+        let rotation = Self::rotation(state);
 
         let angular_velocity_derivative = self.inertia.inverse_matrix()
             * (self.torque(&rotation)
+                + self.friction_torque(&angular_velocity)
                 + (self.inertia.matrix() * angular_velocity).cross(&angular_velocity));
         let angular_velocity_quaternion = na::Quaternion::new(
             0.0,
@@ -110,3 +277,105 @@ impl PlainODE<7> for SpinningTopODE {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numerics::ode::{runge_kutta::RungeKuttaIV, Solver};
+
+    fn spinning_state(rotation: na::UnitQuaternion<f64>) -> State<7> {
+        let angular_velocity = na::vector![1.0, 8.0, -2.0];
+        State {
+            t: 0.0,
+            y: na::vector![
+                angular_velocity.x,
+                angular_velocity.y,
+                angular_velocity.z,
+                rotation.w,
+                rotation.i,
+                rotation.j,
+                rotation.k,
+            ],
+        }
+    }
+
+    fn asymmetric_top() -> SpinningTopODE {
+        SpinningTopODE::new(
+            1.0,
+            TopBody::Cuboid {
+                sizes: na::vector![0.5, 1.0, 2.0],
+            },
+        )
+    }
+
+    /// Kinetic energies of the states visited over `steps` steps of RK4
+    fn energies(ode: SpinningTopODE, state: State<7>, steps: usize) -> Vec<f64> {
+        let solver = RungeKuttaIV::new(0.001, ode);
+        std::iter::successors(Some(state), |state| Some(solver.step(state)))
+            .take(steps)
+            .map(|state| solver.ode().energy(&state))
+            .collect()
+    }
+
+    #[test]
+    fn friction_dissipates_kinetic_energy() {
+        let mut ode = asymmetric_top();
+        ode.enable_gravity = false;
+        ode.enable_friction = true;
+        ode.friction = 2.0;
+
+        let energies = energies(ode, spinning_state(na::UnitQuaternion::identity()), 2000);
+
+        assert!(energies.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(energies.last().unwrap() < &(0.5 * energies[0]));
+    }
+
+    #[test]
+    fn without_friction_kinetic_energy_is_conserved() {
+        let mut ode = asymmetric_top();
+        ode.enable_gravity = false;
+
+        let energies = energies(ode, spinning_state(na::UnitQuaternion::identity()), 2000);
+
+        assert!(energies
+            .iter()
+            .all(|energy| (energy - energies[0]).abs() < 1e-6 * energies[0]));
+    }
+
+    #[test]
+    fn friction_dissipates_total_energy_under_gravity() {
+        let mut ode = asymmetric_top();
+        ode.enable_friction = true;
+        ode.friction = 2.0;
+
+        let solver = RungeKuttaIV::new(0.001, ode);
+        let total_energy = |state: &State<7>| {
+            let ode = solver.ode();
+            let height = SpinningTopODE::rotation(state).transform_vector(&ode.lever());
+            ode.energy(state) - ode.weight().dot(&height)
+        };
+
+        let tilt = na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), 0.3);
+        let energies: Vec<_> =
+            std::iter::successors(Some(spinning_state(tilt)), |state| Some(solver.step(state)))
+                .take(2000)
+                .map(|state| total_energy(&state))
+                .collect();
+
+        assert!(energies.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn friction_torque_opposes_rotation() {
+        let mut ode = asymmetric_top();
+        let angular_velocity = na::vector![1.0, -2.0, 3.0];
+        assert_eq!(ode.friction_torque(&angular_velocity), na::Vector3::zeros());
+
+        ode.enable_friction = true;
+        ode.friction = 0.5;
+        assert_eq!(
+            ode.friction_torque(&angular_velocity),
+            na::vector![-0.5, 1.0, -1.5]
+        );
+    }
+}
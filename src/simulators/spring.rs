@@ -1,4 +1,7 @@
-use crate::numerics::{ode::ODE, FloatFn};
+use crate::numerics::{
+    ode::{PlainODE, State},
+    FloatFn,
+};
 use nalgebra as na;
 use struct_iterable::Iterable;
 
@@ -28,16 +31,33 @@ impl SpringState {
     }
 }
 
-pub struct SpringODE {
-    t: F,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DampingKind {
+    Undamped,
+    Underdamped,
+    CriticallyDamped,
+    Overdamped,
+}
 
+impl DampingKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DampingKind::Undamped => "Undamped",
+            DampingKind::Underdamped => "Underdamped",
+            DampingKind::CriticallyDamped => "Critically damped",
+            DampingKind::Overdamped => "Overdamped",
+        }
+    }
+}
+
+pub struct SpringODE {
     pub mass: F,
     pub equilibrium: FloatFn<F>,
 
-    position: F,
-    velocity: F,
-
     pub spring_constant: F,
+    /// Coefficient of the Duffing term `k₃·(equilibrium − position)³`
+    pub cubic_constant: F,
+    pub enable_cubic: bool,
     pub damping_factor: F,
     pub external_force: FloatFn<F>,
 }
@@ -46,93 +66,187 @@ impl SpringODE {
     pub fn new(
         mass: F,
         equilibrium: FloatFn<F>,
-        position: F,
-        velocity: F,
         spring_constant: F,
         damping_factor: F,
         external_force: FloatFn<F>,
     ) -> Self {
         Self {
-            t: 0.0,
             mass,
             equilibrium,
-            position,
-            velocity,
             spring_constant,
+            cubic_constant: 1.0,
+            enable_cubic: false,
             damping_factor,
             external_force,
         }
     }
 
-    pub fn state(&self) -> SpringState {
+    pub fn initial_state(position: F, velocity: F) -> State<2> {
+        State {
+            t: 0.0,
+            y: na::vector![position, velocity],
+        }
+    }
+
+    pub fn state(&self, state: &State<2>) -> SpringState {
         SpringState {
-            t: self.t,
+            t: state.t,
 
-            position: self.position(),
-            velocity: self.velocity(),
-            acceleration: self.acceleration(),
+            position: Self::position(state),
+            velocity: Self::velocity(state),
+            acceleration: self.acceleration(state),
 
-            spring_force: self.spring_force(),
-            damping_force: self.damping_force(),
-            external_force: self.external_force(),
-            total_force: self.total_force(),
+            spring_force: self.spring_force(state),
+            damping_force: self.damping_force(state),
+            external_force: self.external_force(state.t),
+            total_force: self.total_force(state),
 
-            equilibrium: self.equilibrium(),
+            equilibrium: self.equilibrium(state.t),
         }
     }
 
-    pub fn total_force(&self) -> F {
-        self.spring_force() + self.damping_force() + self.external_force()
+    pub fn total_force(&self, state: &State<2>) -> F {
+        self.spring_force(state) + self.damping_force(state) + self.external_force(state.t)
+    }
+
+    pub fn external_force(&self, t: F) -> F {
+        (self.external_force)(t)
     }
 
-    pub fn external_force(&self) -> F {
-        (self.external_force)(self.t)
+    pub fn damping_force(&self, state: &State<2>) -> F {
+        -self.damping_factor * Self::velocity(state)
     }
 
-    pub fn damping_force(&self) -> F {
-        -self.damping_factor * self.velocity
+    pub fn spring_force(&self, state: &State<2>) -> F {
+        let elongation = self.equilibrium(state.t) - Self::position(state);
+        let linear = self.spring_constant * elongation;
+
+        if self.enable_cubic {
+            linear + self.cubic_constant * elongation.powi(3)
+        } else {
+            linear
+        }
     }
 
-    pub fn spring_force(&self) -> F {
-        self.spring_constant * (self.equilibrium() - self.position)
+    pub fn equilibrium(&self, t: F) -> F {
+        (self.equilibrium)(t)
     }
 
-    pub fn equilibrium(&self) -> F {
-        (self.equilibrium)(self.t)
+    pub fn position(state: &State<2>) -> F {
+        state.y[0]
     }
 
-    pub fn position(&self) -> F {
-        self.position
+    pub fn velocity(state: &State<2>) -> F {
+        state.y[1]
     }
 
-    pub fn velocity(&self) -> F {
-        self.velocity
+    pub fn acceleration(&self, state: &State<2>) -> F {
+        self.total_force(state) / self.mass
     }
 
-    pub fn acceleration(&self) -> F {
-        self.total_force() / self.mass
+    /// Angular frequency of the undamped, unforced oscillation
+    pub fn natural_frequency(&self) -> F {
+        (self.spring_constant / self.mass).sqrt()
     }
-}
 
-impl ODE<F, 2> for SpringODE {
-    fn derivative(&self) -> na::Vector2<F> {
-        na::vector![self.velocity, self.acceleration()]
+    /// `c / (2·√(k·m))`
+    pub fn damping_ratio(&self) -> F {
+        self.damping_factor / (2.0 * (self.spring_constant * self.mass).sqrt())
     }
 
-    fn t(&self) -> F {
-        self.t
+    /// Closed-form position of the linear spring at time `t`, treating the equilibrium and
+    /// the external force as constant at their values in `initial`
+    pub fn analytic_position(&self, initial: &SpringState, t: F) -> F {
+        let omega = self.natural_frequency();
+        let zeta = self.damping_ratio();
+        let rest = initial.equilibrium + initial.external_force / self.spring_constant;
+
+        let t = t - initial.t;
+        let u0 = initial.position - rest;
+        let v0 = initial.velocity;
+
+        let u = match self.damping_kind() {
+            DampingKind::Undamped | DampingKind::Underdamped => {
+                let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+                (-zeta * omega * t).exp()
+                    * (u0 * (omega_d * t).cos()
+                        + (v0 + zeta * omega * u0) / omega_d * (omega_d * t).sin())
+            }
+            DampingKind::CriticallyDamped => (-omega * t).exp() * (u0 + (v0 + omega * u0) * t),
+            DampingKind::Overdamped => {
+                let root = (zeta * zeta - 1.0).sqrt();
+                let r_1 = -omega * (zeta - root);
+                let r_2 = -omega * (zeta + root);
+                let a = (v0 - r_2 * u0) / (r_1 - r_2);
+                a * (r_1 * t).exp() + (u0 - a) * (r_2 * t).exp()
+            }
+        };
+
+        rest + u
     }
 
-    fn y(&self) -> na::SVector<F, 2> {
-        na::vector![self.position, self.velocity]
+    pub fn damping_kind(&self) -> DampingKind {
+        const TOLERANCE: F = 1e-6;
+        let ratio = self.damping_ratio();
+
+        if ratio < TOLERANCE {
+            DampingKind::Undamped
+        } else if (ratio - 1.0).abs() < TOLERANCE {
+            DampingKind::CriticallyDamped
+        } else if ratio < 1.0 {
+            DampingKind::Underdamped
+        } else {
+            DampingKind::Overdamped
+        }
     }
+}
 
-    fn set_t(&mut self, t: F) {
-        self.t = t;
+impl PlainODE<2> for SpringODE {
+    fn derivative(&self, state: &State<2>) -> na::Vector2<F> {
+        na::vector![Self::velocity(state), self.acceleration(state)]
     }
+}
 
-    fn set_y(&mut self, y: na::SVector<F, 2>) {
-        self.position = y[0];
-        self.velocity = y[1];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::numerics::{ode::Solver, RungeKuttaIV};
+
+    fn spring(damping_factor: F) -> SpringODE {
+        SpringODE::new(
+            1.0,
+            Box::new(|_| 0.5),
+            4.0,
+            damping_factor,
+            Box::new(|_| -1.0),
+        )
+    }
+
+    #[test]
+    fn analytic_position_matches_runge_kutta() {
+        for (damping_factor, kind) in [
+            (1.0, DampingKind::Underdamped),
+            (4.0, DampingKind::CriticallyDamped),
+            (10.0, DampingKind::Overdamped),
+        ] {
+            let solver = RungeKuttaIV::new(1e-3, spring(damping_factor));
+            assert_eq!(solver.ode().damping_kind(), kind);
+
+            let mut state = SpringODE::initial_state(2.0, -1.5);
+            let initial = solver.ode().state(&state);
+
+            for step in 1..=5000 {
+                state = solver.step(&state);
+                if step % 100 == 0 {
+                    let analytic = solver.ode().analytic_position(&initial, state.t);
+                    assert!(
+                        (SpringODE::position(&state) - analytic).abs() < 1e-8,
+                        "{kind:?} at t = {}: {} != {analytic}",
+                        state.t,
+                        SpringODE::position(&state)
+                    );
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,103 @@
+use crate::numerics::ode::{PlainODE, State};
+use nalgebra as na;
+
+pub const MAX_MASSES: usize = 8;
+/// Displacements of all masses followed by their velocities
+pub const DIM: usize = 2 * MAX_MASSES;
+
+/// Masses connected in series by springs, the first spring is attached to a wall.
+/// Positions are displacements from the rest configuration,
+/// masses beyond `count()` are not simulated.
+pub struct SpringChainODE {
+    masses: Vec<f64>,
+    /// `spring_constants[i]` belongs to the spring on the wall side of mass `i`
+    spring_constants: Vec<f64>,
+    pub damping_factor: f64,
+}
+
+impl SpringChainODE {
+    pub const DEFAULT_MASS: f64 = 1.0;
+    pub const DEFAULT_SPRING_CONSTANT: f64 = 1.0;
+
+    pub fn new(count: usize) -> Self {
+        let mut me = Self {
+            masses: Vec::new(),
+            spring_constants: Vec::new(),
+            damping_factor: 0.0,
+        };
+
+        me.set_count(count);
+
+        me
+    }
+
+    pub fn count(&self) -> usize {
+        self.masses.len()
+    }
+
+    pub fn set_count(&mut self, count: usize) {
+        let count = count.clamp(1, MAX_MASSES);
+        self.masses.resize(count, Self::DEFAULT_MASS);
+        self.spring_constants
+            .resize(count, Self::DEFAULT_SPRING_CONSTANT);
+    }
+
+    pub fn masses_mut(&mut self) -> &mut [f64] {
+        &mut self.masses
+    }
+
+    pub fn spring_constants_mut(&mut self) -> &mut [f64] {
+        &mut self.spring_constants
+    }
+
+    pub fn position(state: &State<DIM>, index: usize) -> f64 {
+        state.y[index]
+    }
+
+    pub fn velocity(state: &State<DIM>, index: usize) -> f64 {
+        state.y[MAX_MASSES + index]
+    }
+
+    /// Elongation of the spring on the wall side of mass `index`
+    pub fn elongation(state: &State<DIM>, index: usize) -> f64 {
+        if index == 0 {
+            Self::position(state, 0)
+        } else {
+            Self::position(state, index) - Self::position(state, index - 1)
+        }
+    }
+
+    pub fn force(&self, state: &State<DIM>, index: usize) -> f64 {
+        let mut force = -self.spring_constants[index] * Self::elongation(state, index)
+            - self.damping_factor * Self::velocity(state, index);
+
+        if index + 1 < self.count() {
+            force += self.spring_constants[index + 1] * Self::elongation(state, index + 1);
+        }
+
+        force
+    }
+
+    /// Kinetic energy of the masses and potential energy of the springs
+    pub fn energy(&self, state: &State<DIM>) -> f64 {
+        (0..self.count())
+            .map(|i| {
+                0.5 * self.masses[i] * Self::velocity(state, i).powi(2)
+                    + 0.5 * self.spring_constants[i] * Self::elongation(state, i).powi(2)
+            })
+            .sum()
+    }
+}
+
+impl PlainODE<DIM> for SpringChainODE {
+    fn derivative(&self, state: &State<DIM>) -> na::SVector<f64, DIM> {
+        let mut derivative = na::SVector::zeros();
+
+        for i in 0..self.count() {
+            derivative[i] = Self::velocity(state, i);
+            derivative[MAX_MASSES + i] = self.force(state, i) / self.masses[i];
+        }
+
+        derivative
+    }
+}
@@ -1,5 +1,7 @@
+use crate::controls::camera::{Camera, CameraMode, CameraPreset, CameraSettings};
 use egui::{containers::Frame, emath::Numeric, *};
 use nalgebra as na;
+use std::{fmt::Display, path::PathBuf};
 
 pub fn vector_drag<T: Numeric, const S: usize>(
     ui: &mut Ui,
@@ -33,3 +35,208 @@ pub fn vector_drag<T: Numeric, const S: usize>(
         })
         .inner
 }
+
+pub fn camera_ui(ui: &mut Ui, camera: &mut Camera) -> Response {
+    ui.horizontal(|ui| {
+        ui.label("Camera mode");
+        [
+            (CameraMode::Orbit, "Orbit"),
+            (CameraMode::Fly, "Fly (WASD, Q/E)"),
+        ]
+        .into_iter()
+        .map(|(mode, text)| {
+            let response = ui.selectable_label(camera.mode() == mode, text);
+            if response.clicked() {
+                camera.set_mode(mode);
+            }
+            response
+        })
+        .reduce(|a, b| a | b)
+        .unwrap()
+    })
+    .inner
+        | ui.horizontal(|ui| {
+            ui.label("Roll");
+            ui.drag_angle(&mut camera.roll)
+        })
+        .inner
+        | ui.horizontal(|ui| {
+            ui.label("Smoothing");
+            ui.add(
+                DragValue::new(&mut camera.smoothing)
+                    .clamp_range(0.0..=1.0)
+                    .suffix(" s")
+                    .speed(0.005),
+            )
+        })
+        .inner
+        | camera_sensitivity_ui(ui, camera)
+        | camera_presets_ui(ui, camera)
+}
+
+pub fn camera_sensitivity_ui(ui: &mut Ui, camera: &mut Camera) -> Response {
+    let defaults = CameraSettings::default();
+    let settings = &mut camera.settings;
+
+    let drag = |ui: &mut Ui, label: &str, value: &mut f32, default: f32| {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let mut response = ui.add(
+                DragValue::new(value)
+                    .clamp_range(0.0..=10.0 * default)
+                    .speed(0.01 * default),
+            );
+
+            if ui.button("Default").clicked() {
+                *value = default;
+                response.mark_changed();
+            }
+
+            response
+        })
+        .inner
+    };
+
+    ui.label("Sensitivity");
+    let rotation = drag(
+        ui,
+        "Rotation",
+        &mut settings.rotation_speed,
+        defaults.rotation_speed,
+    );
+    let movement = drag(
+        ui,
+        "Movement",
+        &mut settings.movement_speed,
+        defaults.movement_speed,
+    );
+    let scroll = drag(
+        ui,
+        "Zoom",
+        &mut settings.scroll_speed,
+        defaults.scroll_speed,
+    );
+    let inverts = ui.checkbox(&mut settings.invert_scroll, "Invert scroll")
+        | ui.checkbox(&mut settings.invert_look, "Invert look");
+
+    let status_id = ui.id().with("camera_settings_status");
+    if ui.button("Save as defaults").clicked() {
+        let status = match settings.save() {
+            Ok(()) => format!("Saved to {}", CameraSettings::PATH),
+            Err(error) => format!("Error: {}", error),
+        };
+        ui.data_mut(|data| data.insert_temp(status_id, status));
+    }
+
+    if let Some(status) = ui.data(|data| data.get_temp::<String>(status_id)) {
+        ui.label(status);
+    }
+
+    rotation | movement | scroll | inverts
+}
+
+pub fn camera_presets_ui(ui: &mut Ui, camera: &mut Camera) -> Response {
+    let mut response = ui
+        .horizontal(|ui| {
+            ui.label("Views");
+            [
+                (CameraPreset::top(), "Top"),
+                (CameraPreset::side(), "Side"),
+                (CameraPreset::iso(), "Iso"),
+            ]
+            .into_iter()
+            .map(|(preset, text)| {
+                let response = ui.button(text);
+                if response.clicked() {
+                    camera.apply_preset(&preset);
+                }
+                response
+            })
+            .reduce(|a, b| a | b)
+            .unwrap()
+        })
+        .inner;
+
+    for i in 0..Camera::PRESET_SLOTS {
+        response |= ui
+            .horizontal(|ui| {
+                ui.label(format!("Slot {}", i + 1));
+                let store = ui.button("Store");
+                if store.clicked() {
+                    camera.presets[i] = Some(camera.to_preset());
+                }
+
+                let preset = camera.presets[i];
+                let recall = ui.add_enabled(preset.is_some(), Button::new("Recall"));
+                if let (true, Some(preset)) = (recall.clicked(), preset) {
+                    camera.apply_preset(&preset);
+                }
+
+                store | recall
+            })
+            .inner;
+    }
+
+    response
+}
+
+/// Read-only grid of the matrix entries
+pub fn matrix_grid<const R: usize, const C: usize>(
+    ui: &mut Ui,
+    id: &str,
+    matrix: &na::SMatrix<f64, R, C>,
+) -> Response {
+    Frame::none()
+        .stroke(Stroke::new(0.5, Color32::GRAY))
+        .rounding(4.0)
+        .inner_margin(5.0)
+        .show(ui, |ui| {
+            Grid::new(id).striped(true).show(ui, |ui| {
+                for row in matrix.row_iter() {
+                    for entry in row.iter() {
+                        ui.monospace(format!("{:.3e}", entry));
+                    }
+                    ui.end_row();
+                }
+            })
+        })
+        .response
+}
+
+/// Path text field with an action button that shows the result of the last action
+pub struct PathAction {
+    pub path: String,
+    status: Option<String>,
+}
+
+impl PathAction {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            status: None,
+        }
+    }
+
+    /// Returns the path if the button has been clicked
+    pub fn ui(&mut self, ui: &mut Ui, text: &str) -> Option<PathBuf> {
+        let clicked = ui
+            .horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.path);
+                ui.button(text).clicked()
+            })
+            .inner;
+
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+
+        clicked.then(|| PathBuf::from(&self.path))
+    }
+
+    pub fn report<E: Display>(&mut self, result: Result<(), E>) {
+        self.status = Some(match result {
+            Ok(()) => format!("Done: {}", self.path),
+            Err(error) => format!("Error: {}", error),
+        });
+    }
+}
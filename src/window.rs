@@ -1,3 +1,7 @@
+use crate::render::{
+    drawbuffer::{read_framebuffer, Drawbuffer},
+    texture::Texture,
+};
 use egui_winit::winit;
 use glow::HasContext;
 use glutin::surface::GlSurface;
@@ -12,10 +16,11 @@ pub struct Window {
 }
 
 impl Window {
-    const CLEAR_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+    pub const CLEAR_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
 
     pub unsafe fn new(event_loop: &winit::event_loop::EventLoopWindowTarget<()>) -> Self {
         use egui::NumExt;
+        use glutin::config::GlConfig;
         use glutin::context::NotCurrentGlContextSurfaceAccessor;
         use glutin::display::GetGlDisplay;
         use glutin::display::GlDisplay;
@@ -39,15 +44,25 @@ impl Window {
             glutin_winit::DisplayBuilder::new() // let glutin-winit helper crate handle the complex parts of opengl context creation
                 .with_preference(glutin_winit::ApiPrefence::FallbackEgl) // https://github.com/emilk/egui/issues/2520#issuecomment-1367841150
                 .with_window_builder(Some(winit_window_builder.clone()))
-                .build(
-                    event_loop,
-                    config_template_builder,
-                    |mut config_iterator| {
-                        config_iterator.next().expect(
+                .build(event_loop, config_template_builder, |config_iterator| {
+                    // The most samples up to the drawbuffers' count, the earlier config on ties
+                    let key = |config: &glutin::config::Config| {
+                        let samples = config.num_samples() as i32;
+                        (samples <= Drawbuffer::SAMPLES, samples)
+                    };
+
+                    config_iterator
+                        .reduce(|best, config| {
+                            if key(&config) > key(&best) {
+                                config
+                            } else {
+                                best
+                            }
+                        })
+                        .expect(
                             "failed to find a matching configuration for creating glutin config",
                         )
-                    },
-                )
+                })
                 .expect("failed to create gl_config");
         let gl_display = gl_config.display();
 
@@ -90,13 +105,6 @@ impl Window {
 
         let gl_context = not_current_gl_context.make_current(&gl_surface).unwrap();
 
-        gl_surface
-            .set_swap_interval(
-                &gl_context,
-                glutin::surface::SwapInterval::Wait(std::num::NonZeroU32::new(1).unwrap()),
-            )
-            .unwrap();
-
         let gl = unsafe {
             glow::Context::from_loader_function(|s| {
                 let s = std::ffi::CString::new(s)
@@ -106,21 +114,17 @@ impl Window {
             })
         };
 
-        unsafe {
-            gl.clear_color(
-                Self::CLEAR_COLOR[0],
-                Self::CLEAR_COLOR[1],
-                Self::CLEAR_COLOR[2],
-                Self::CLEAR_COLOR[3],
-            );
-        }
-
-        Window {
+        let window = Window {
             window,
             gl: Arc::new(gl),
             gl_context,
             gl_surface,
-        }
+        };
+
+        window.set_vsync(true).unwrap();
+        window.set_clear_color(Self::CLEAR_COLOR);
+
+        window
     }
 
     pub fn gl(&self) -> &glow::Context {
@@ -150,10 +154,52 @@ impl Window {
             .map(|(w, h)| PhysicalSize::new(w, h))
     }
 
+    /// Also used by drawbuffers, which are cleared with the same color
+    pub fn set_clear_color(&self, rgba: [f32; 4]) {
+        unsafe { self.gl.clear_color(rgba[0], rgba[1], rgba[2], rgba[3]) };
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.window.fullscreen().is_some()
+    }
+
+    /// Borderless on the current monitor, the new size arrives as a `Resized` event
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        self.window
+            .set_fullscreen(fullscreen.then_some(winit::window::Fullscreen::Borderless(None)));
+    }
+
+    pub fn set_vsync(&self, on: bool) -> glutin::error::Result<()> {
+        let interval = if on {
+            glutin::surface::SwapInterval::Wait(std::num::NonZeroU32::new(1).unwrap())
+        } else {
+            glutin::surface::SwapInterval::DontWait
+        };
+
+        self.gl_surface
+            .set_swap_interval(&self.gl_context, interval)
+    }
+
+    /// Switches multisampling of the window and of all drawbuffers
+    pub fn set_multisampling(&self, on: bool) {
+        unsafe {
+            if on {
+                self.gl.enable(glow::MULTISAMPLE);
+            } else {
+                self.gl.disable(glow::MULTISAMPLE);
+            }
+        }
+    }
+
     pub fn swap_buffers(&self) -> glutin::error::Result<()> {
         self.gl_surface.swap_buffers(&self.gl_context)
     }
 
+    /// Reads the default framebuffer, after split-view presenters have blitted their halves
+    pub fn capture_framebuffer(&self) -> Option<Texture> {
+        read_framebuffer(&self.gl, None, self.size()?)
+    }
+
     pub fn clear(&self) {
         unsafe {
             self.gl.enable(glow::DEPTH_TEST);
@@ -163,3 +209,116 @@ impl Window {
         }
     }
 }
+
+/// Offscreen OpenGL context drawing into a `Drawbuffer`, lets presenters run without a window
+pub struct Headless {
+    // Has to be dropped while the context is still alive
+    drawbuffer: Drawbuffer,
+    gl: Arc<glow::Context>,
+    _gl_surface: glutin::surface::Surface<glutin::surface::PbufferSurface>,
+    _gl_context: glutin::context::PossiblyCurrentContext,
+}
+
+impl Headless {
+    /// `display` is only needed to reach the platform's OpenGL, e.g. a winit event loop
+    ///
+    /// # Safety
+    /// The context is made current on the calling thread, `display` has to outlive it
+    pub unsafe fn new(
+        display: &impl raw_window_handle::HasRawDisplayHandle,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        use glutin::context::NotCurrentGlContextSurfaceAccessor;
+        use glutin::display::{DisplayApiPreference, GlDisplay};
+
+        #[cfg(target_os = "macos")]
+        let preference = DisplayApiPreference::Cgl;
+        #[cfg(not(target_os = "macos"))]
+        let preference = DisplayApiPreference::Egl;
+
+        let gl_display = glutin::display::Display::new(display.raw_display_handle(), preference)
+            .expect("failed to create gl_display");
+
+        let config_template = glutin::config::ConfigTemplateBuilder::new()
+            .with_surface_type(glutin::config::ConfigSurfaceTypes::PBUFFER)
+            .with_depth_size(24)
+            .with_stencil_size(8)
+            .build();
+        let gl_config = gl_display
+            .find_configs(config_template)
+            .expect("failed to find configurations")
+            .next()
+            .expect("failed to find a matching configuration for creating glutin config");
+
+        let context_attributes = glutin::context::ContextAttributesBuilder::new().build(None);
+        let fallback_context_attributes = glutin::context::ContextAttributesBuilder::new()
+            .with_context_api(glutin::context::ContextApi::Gles(None))
+            .build(None);
+        let not_current_gl_context = gl_display
+            .create_context(&gl_config, &context_attributes)
+            .unwrap_or_else(|_| {
+                gl_display
+                    .create_context(&gl_config, &fallback_context_attributes)
+                    .expect("failed to create context even with fallback attributes")
+            });
+
+        // Drawing goes to the drawbuffer, the pbuffer only makes the context current
+        let one = std::num::NonZeroU32::new(1).unwrap();
+        let surface_attributes =
+            glutin::surface::SurfaceAttributesBuilder::<glutin::surface::PbufferSurface>::new()
+                .build(one, one);
+        let gl_surface = gl_display
+            .create_pbuffer_surface(&gl_config, &surface_attributes)
+            .expect("failed to create pbuffer surface");
+        let gl_context = not_current_gl_context.make_current(&gl_surface).unwrap();
+
+        let gl = Arc::new(glow::Context::from_loader_function(|s| {
+            let s = std::ffi::CString::new(s)
+                .expect("failed to construct C string from string for gl proc address");
+
+            gl_display.get_proc_address(&s)
+        }));
+
+        gl.clear_color(
+            Window::CLEAR_COLOR[0],
+            Window::CLEAR_COLOR[1],
+            Window::CLEAR_COLOR[2],
+            Window::CLEAR_COLOR[3],
+        );
+
+        Self {
+            drawbuffer: Drawbuffer::new(Arc::clone(&gl), size.width as i32, size.height as i32),
+            gl,
+            _gl_surface: gl_surface,
+            _gl_context: gl_context,
+        }
+    }
+
+    pub fn gl(&self) -> &glow::Context {
+        &self.gl
+    }
+
+    pub fn clone_gl(&self) -> Arc<glow::Context> {
+        Arc::clone(&self.gl)
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        let size = self.drawbuffer.size();
+        PhysicalSize::new(size.width as u32, size.height as u32)
+    }
+
+    /// Clears the drawbuffer and calls `draw` with it bound, like a frame of `Window`
+    pub fn draw_with<F: FnOnce()>(&self, draw: F) {
+        unsafe {
+            self.gl.enable(glow::DEPTH_TEST);
+            self.gl.enable(glow::CULL_FACE);
+        }
+
+        self.drawbuffer.clear();
+        self.drawbuffer.draw_with(draw);
+    }
+
+    pub fn capture(&self) -> Option<Texture> {
+        self.drawbuffer.capture()
+    }
+}